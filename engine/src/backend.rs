@@ -0,0 +1,786 @@
+use std::cell::{Cell, RefCell};
+use std::ffi::{c_void, CString};
+use std::fmt::Debug;
+
+use gl::types::{GLenum, GLint, GLsizei, GLsizeiptr, GLuint};
+
+use crate::glchk;
+
+/// Value passed to `Backend::set_uniform`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UniformValue {
+    Int(i32),
+    Float(f32),
+    Vec2([f32; 2]),
+    Vec3([f32; 3]),
+    Vec4([f32; 4]),
+    Mat4([f32; 16]),
+}
+
+/// Everything the engine needs from the graphics API.
+///
+/// Ids and enums are plain OpenGL values (`gl::ARRAY_BUFFER`, `gl::TRIANGLES`, ...)
+/// so that other backends only have to interpret them, not translate them.
+pub trait Backend: Debug {
+    fn create_buffer(&self) -> u32;
+    fn bind_buffer(&self, target: GLenum, buffer: u32);
+    fn buffer_data(&self, target: GLenum, data: &[u8], usage: GLenum);
+    fn delete_buffer(&self, buffer: u32);
+    /// Binds a buffer to an indexed target such as `gl::UNIFORM_BUFFER`
+    fn bind_buffer_base(&self, target: GLenum, index: u32, buffer: u32);
+
+    fn create_vertex_array(&self) -> u32;
+    fn bind_vertex_array(&self, vertex_array: u32);
+    fn enable_vertex_attrib_array(&self, index: u32);
+    fn vertex_attrib_pointer(&self, index: u32, size: i32, type_: GLenum, normalized: bool, stride: i32, offset: usize);
+    /// Integer attributes, read by the shader without conversion to float
+    fn vertex_attrib_i_pointer(&self, index: u32, size: i32, type_: GLenum, stride: i32, offset: usize);
+    /// Attribute advances once every `divisor` instances instead of every vertex, 0 turns it off
+    fn vertex_attrib_divisor(&self, index: u32, divisor: u32);
+    fn delete_vertex_array(&self, vertex_array: u32);
+
+    fn create_texture(&self) -> u32;
+    fn bind_texture(&self, target: GLenum, texture: u32);
+    fn tex_parameter(&self, target: GLenum, name: GLenum, value: i32);
+    #[allow(clippy::too_many_arguments)]
+    fn tex_image_2d(&self, target: GLenum, level: i32, internal_format: GLenum, width: i32, height: i32, format: GLenum, type_: GLenum, data: Option<&[u8]>);
+    #[allow(clippy::too_many_arguments)]
+    fn tex_image_3d(&self, target: GLenum, level: i32, internal_format: GLenum, width: i32, height: i32, depth: i32, format: GLenum, type_: GLenum, data: Option<&[u8]>);
+    #[allow(clippy::too_many_arguments)]
+    fn tex_sub_image_2d(&self, target: GLenum, level: i32, x: i32, y: i32, width: i32, height: i32, format: GLenum, type_: GLenum, data: &[u8]);
+    #[allow(clippy::too_many_arguments)]
+    fn tex_sub_image_3d(&self, target: GLenum, level: i32, x: i32, y: i32, z: i32, width: i32, height: i32, depth: i32, format: GLenum, type_: GLenum, data: &[u8]);
+    fn tex_parameter_f(&self, target: GLenum, name: GLenum, value: f32);
+    fn generate_mipmap(&self, target: GLenum);
+    /// Selects the texture unit `bind_texture` binds to, `unit` is 0-based
+    fn active_texture(&self, unit: u32);
+    fn delete_texture(&self, texture: u32);
+    /// `glGetFloatv` for single-value parameters
+    fn get_float(&self, name: GLenum) -> f32;
+
+    fn create_framebuffer(&self) -> u32;
+    /// 0 binds the default framebuffer
+    fn bind_framebuffer(&self, target: GLenum, framebuffer: u32);
+    fn framebuffer_texture_2d(&self, target: GLenum, attachment: GLenum, texture_target: GLenum, texture: u32, level: i32);
+    fn check_framebuffer_status(&self, target: GLenum) -> GLenum;
+    fn delete_framebuffer(&self, framebuffer: u32);
+    fn draw_buffer(&self, buffer: GLenum);
+    fn read_buffer(&self, buffer: GLenum);
+
+    /// Returns the shader id, or the info log if compilation failed
+    fn compile_shader(&self, stage: GLenum, source: &str) -> Result<u32, String>;
+    fn delete_shader(&self, shader: u32);
+    /// Returns the program id, or the info log if linking failed
+    fn link_program(&self, shaders: &[u32]) -> Result<u32, String>;
+    fn use_program(&self, program: u32);
+    fn uniform_location(&self, program: u32, name: &str) -> i32;
+    fn set_uniform(&self, location: i32, value: UniformValue);
+    /// Returns `gl::INVALID_INDEX` if the program has no such block
+    fn uniform_block_index(&self, program: u32, name: &str) -> u32;
+    fn uniform_block_binding(&self, program: u32, block_index: u32, binding: u32);
+    fn delete_program(&self, program: u32);
+
+    fn clear_color(&self, r: f32, g: f32, b: f32, a: f32);
+    fn clear(&self, mask: GLenum);
+    fn enable(&self, capability: GLenum);
+    fn disable(&self, capability: GLenum);
+    fn blend_func(&self, source: GLenum, destination: GLenum);
+    fn polygon_mode(&self, face: GLenum, mode: GLenum);
+    /// Depth offset applied while `gl::POLYGON_OFFSET_FILL` is enabled
+    fn polygon_offset(&self, factor: f32, units: f32);
+    fn viewport(&self, x: i32, y: i32, width: i32, height: i32);
+    fn draw_arrays(&self, mode: GLenum, first: i32, count: i32);
+    fn draw_arrays_instanced(&self, mode: GLenum, first: i32, count: i32, instances: i32);
+    /// Draws from the bound index buffer, `offset` is in bytes
+    fn draw_elements(&self, mode: GLenum, count: i32, index_type: GLenum, offset: usize);
+}
+
+/// OpenGL backend, requires `gl::load_with` to have been called on the current context
+#[derive(Debug, Default, Clone, Copy)]
+pub struct GlBackend;
+
+#[allow(redundant_semicolons)]
+impl Backend for GlBackend {
+    fn create_buffer(&self) -> u32 {
+        let mut id: GLuint = 0;
+        unsafe {
+            glchk!(gl::GenBuffers(1, &mut id););
+        }
+        id
+    }
+
+    fn bind_buffer(&self, target: GLenum, buffer: u32) {
+        unsafe { gl::BindBuffer(target, buffer) }
+    }
+
+    fn buffer_data(&self, target: GLenum, data: &[u8], usage: GLenum) {
+        unsafe {
+            gl::BufferData(
+                target,
+                data.len() as GLsizeiptr,
+                data.as_ptr() as *const _,
+                usage
+            )
+        }
+    }
+
+    fn delete_buffer(&self, buffer: u32) {
+        unsafe { gl::DeleteBuffers(1, [buffer].as_ptr()) }
+    }
+
+    fn bind_buffer_base(&self, target: GLenum, index: u32, buffer: u32) {
+        unsafe { gl::BindBufferBase(target, index, buffer) }
+    }
+
+    fn create_vertex_array(&self) -> u32 {
+        let mut id: GLuint = 0;
+        unsafe { gl::GenVertexArrays(1, &mut id) }
+        id
+    }
+
+    fn bind_vertex_array(&self, vertex_array: u32) {
+        unsafe { gl::BindVertexArray(vertex_array) }
+    }
+
+    fn enable_vertex_attrib_array(&self, index: u32) {
+        unsafe { gl::EnableVertexAttribArray(index) }
+    }
+
+    fn vertex_attrib_pointer(&self, index: u32, size: i32, type_: GLenum, normalized: bool, stride: i32, offset: usize) {
+        unsafe {
+            gl::VertexAttribPointer(
+                index,
+                size,
+                type_,
+                if normalized { gl::TRUE } else { gl::FALSE },
+                stride,
+                offset as *const c_void
+            )
+        }
+    }
+
+    fn vertex_attrib_i_pointer(&self, index: u32, size: i32, type_: GLenum, stride: i32, offset: usize) {
+        unsafe { gl::VertexAttribIPointer(index, size, type_, stride, offset as *const c_void) }
+    }
+
+    fn vertex_attrib_divisor(&self, index: u32, divisor: u32) {
+        unsafe { gl::VertexAttribDivisor(index, divisor) }
+    }
+
+    fn delete_vertex_array(&self, vertex_array: u32) {
+        unsafe { gl::DeleteVertexArrays(1, [vertex_array].as_ptr()) }
+    }
+
+    fn create_texture(&self) -> u32 {
+        let mut id: GLuint = 0;
+        unsafe { gl::GenTextures(1, &mut id) }
+        id
+    }
+
+    fn bind_texture(&self, target: GLenum, texture: u32) {
+        unsafe { gl::BindTexture(target, texture) }
+    }
+
+    fn tex_parameter(&self, target: GLenum, name: GLenum, value: i32) {
+        unsafe { gl::TexParameteri(target, name, value) }
+    }
+
+    fn tex_image_2d(&self, target: GLenum, level: i32, internal_format: GLenum, width: i32, height: i32, format: GLenum, type_: GLenum, data: Option<&[u8]>) {
+        unsafe {
+            gl::TexImage2D(
+                target,
+                level,
+                internal_format as GLint,
+                width,
+                height,
+                0,
+                format,
+                type_,
+                data.map_or(std::ptr::null(), |data| data.as_ptr() as *const c_void)
+            )
+        }
+    }
+
+    fn tex_image_3d(&self, target: GLenum, level: i32, internal_format: GLenum, width: i32, height: i32, depth: i32, format: GLenum, type_: GLenum, data: Option<&[u8]>) {
+        unsafe {
+            gl::TexImage3D(
+                target,
+                level,
+                internal_format as GLint,
+                width,
+                height,
+                depth,
+                0,
+                format,
+                type_,
+                data.map_or(std::ptr::null(), |data| data.as_ptr() as *const c_void)
+            )
+        }
+    }
+
+    fn tex_sub_image_2d(&self, target: GLenum, level: i32, x: i32, y: i32, width: i32, height: i32, format: GLenum, type_: GLenum, data: &[u8]) {
+        unsafe { gl::TexSubImage2D(target, level, x, y, width, height, format, type_, data.as_ptr() as *const c_void) }
+    }
+
+    fn tex_sub_image_3d(&self, target: GLenum, level: i32, x: i32, y: i32, z: i32, width: i32, height: i32, depth: i32, format: GLenum, type_: GLenum, data: &[u8]) {
+        unsafe { gl::TexSubImage3D(target, level, x, y, z, width, height, depth, format, type_, data.as_ptr() as *const c_void) }
+    }
+
+    fn tex_parameter_f(&self, target: GLenum, name: GLenum, value: f32) {
+        unsafe { gl::TexParameterf(target, name, value) }
+    }
+
+    fn generate_mipmap(&self, target: GLenum) {
+        unsafe { gl::GenerateMipmap(target) }
+    }
+
+    fn active_texture(&self, unit: u32) {
+        unsafe { gl::ActiveTexture(gl::TEXTURE0 + unit) }
+    }
+
+    fn delete_texture(&self, texture: u32) {
+        unsafe { gl::DeleteTextures(1, [texture].as_ptr()) }
+    }
+
+    fn get_float(&self, name: GLenum) -> f32 {
+        let mut value = 0.0;
+        unsafe { gl::GetFloatv(name, &mut value) }
+        value
+    }
+
+    fn create_framebuffer(&self) -> u32 {
+        let mut id: GLuint = 0;
+        unsafe { gl::GenFramebuffers(1, &mut id) }
+        id
+    }
+
+    fn bind_framebuffer(&self, target: GLenum, framebuffer: u32) {
+        unsafe { gl::BindFramebuffer(target, framebuffer) }
+    }
+
+    fn framebuffer_texture_2d(&self, target: GLenum, attachment: GLenum, texture_target: GLenum, texture: u32, level: i32) {
+        unsafe { gl::FramebufferTexture2D(target, attachment, texture_target, texture, level) }
+    }
+
+    fn check_framebuffer_status(&self, target: GLenum) -> GLenum {
+        unsafe { gl::CheckFramebufferStatus(target) }
+    }
+
+    fn delete_framebuffer(&self, framebuffer: u32) {
+        unsafe { gl::DeleteFramebuffers(1, [framebuffer].as_ptr()) }
+    }
+
+    fn draw_buffer(&self, buffer: GLenum) {
+        unsafe { gl::DrawBuffer(buffer) }
+    }
+
+    fn read_buffer(&self, buffer: GLenum) {
+        unsafe { gl::ReadBuffer(buffer) }
+    }
+
+    fn compile_shader(&self, stage: GLenum, source: &str) -> Result<u32, String> {
+        let source = CString::new(source).map_err(|err| err.to_string())?;
+        let id = unsafe { gl::CreateShader(stage) };
+
+        unsafe {
+            gl::ShaderSource(id, 1, &source.as_ptr(), std::ptr::null());
+            gl::CompileShader(id)
+        };
+
+        let mut success: GLint = 1;
+
+        unsafe {
+            gl::GetShaderiv(id, gl::COMPILE_STATUS, &mut success);
+        }
+
+        if success == 0 {
+            let mut len: GLint = 0;
+
+            unsafe {
+                gl::GetShaderiv(id, gl::INFO_LOG_LENGTH, &mut len);
+            }
+
+            let error = create_whitespace_cstring_with_len(len as usize);
+
+            unsafe {
+                gl::GetShaderInfoLog(
+                    id,
+                    len,
+                    std::ptr::null_mut(),
+                    error.as_ptr() as *mut gl::types::GLchar
+                );
+                gl::DeleteShader(id);
+            }
+            return Err(error.to_string_lossy().into_owned());
+        }
+        Ok(id)
+    }
+
+    fn delete_shader(&self, shader: u32) {
+        unsafe { gl::DeleteShader(shader) }
+    }
+
+    fn link_program(&self, shaders: &[u32]) -> Result<u32, String> {
+        let id = unsafe { gl::CreateProgram() };
+
+        unsafe {
+            for shader in shaders {
+                gl::AttachShader(id, *shader);
+            }
+            gl::LinkProgram(id);
+        }
+
+        let mut success: GLint = 1;
+
+        unsafe {
+            gl::GetProgramiv(id, gl::LINK_STATUS, &mut success);
+        }
+
+        if success == 0 {
+            let mut len: GLint = 0;
+
+            unsafe {
+                gl::GetProgramiv(id, gl::INFO_LOG_LENGTH, &mut len);
+            }
+
+            let error = create_whitespace_cstring_with_len(len as usize);
+
+            unsafe {
+                gl::GetProgramInfoLog(
+                    id,
+                    len,
+                    std::ptr::null_mut(),
+                    error.as_ptr() as *mut gl::types::GLchar
+                );
+                gl::DeleteProgram(id);
+            }
+            return Err(error.to_string_lossy().into_owned());
+        }
+        Ok(id)
+    }
+
+    fn use_program(&self, program: u32) {
+        unsafe { gl::UseProgram(program) }
+    }
+
+    fn uniform_location(&self, program: u32, name: &str) -> i32 {
+        let c_name = CString::new(name).unwrap();
+        unsafe { gl::GetUniformLocation(program, c_name.as_ptr()) }
+    }
+
+    fn set_uniform(&self, location: i32, value: UniformValue) {
+        unsafe {
+            match value {
+                UniformValue::Int(value) => gl::Uniform1i(location, value),
+                UniformValue::Float(value) => gl::Uniform1f(location, value),
+                UniformValue::Vec2(value) => gl::Uniform2fv(location, 1, value.as_ptr()),
+                UniformValue::Vec3(value) => gl::Uniform3fv(location, 1, value.as_ptr()),
+                UniformValue::Vec4(value) => gl::Uniform4fv(location, 1, value.as_ptr()),
+                UniformValue::Mat4(value) => gl::UniformMatrix4fv(location, 1, gl::FALSE, value.as_ptr()),
+            }
+        }
+    }
+
+    fn uniform_block_index(&self, program: u32, name: &str) -> u32 {
+        let c_name = CString::new(name).unwrap();
+        unsafe { gl::GetUniformBlockIndex(program, c_name.as_ptr()) }
+    }
+
+    fn uniform_block_binding(&self, program: u32, block_index: u32, binding: u32) {
+        unsafe { gl::UniformBlockBinding(program, block_index, binding) }
+    }
+
+    fn delete_program(&self, program: u32) {
+        unsafe { gl::DeleteProgram(program) }
+    }
+
+    fn clear_color(&self, r: f32, g: f32, b: f32, a: f32) {
+        unsafe { gl::ClearColor(r, g, b, a) }
+    }
+
+    fn clear(&self, mask: GLenum) {
+        unsafe { gl::Clear(mask) }
+    }
+
+    fn enable(&self, capability: GLenum) {
+        unsafe { gl::Enable(capability) }
+    }
+
+    fn disable(&self, capability: GLenum) {
+        unsafe { gl::Disable(capability) }
+    }
+
+    fn blend_func(&self, source: GLenum, destination: GLenum) {
+        unsafe { gl::BlendFunc(source, destination) }
+    }
+
+    fn polygon_mode(&self, face: GLenum, mode: GLenum) {
+        unsafe { gl::PolygonMode(face, mode) }
+    }
+
+    fn polygon_offset(&self, factor: f32, units: f32) {
+        unsafe { gl::PolygonOffset(factor, units) }
+    }
+
+    fn viewport(&self, x: i32, y: i32, width: i32, height: i32) {
+        unsafe { gl::Viewport(x, y, width, height) }
+    }
+
+    fn draw_arrays(&self, mode: GLenum, first: i32, count: i32) {
+        unsafe { gl::DrawArrays(mode, first, count as GLsizei) }
+    }
+
+    fn draw_arrays_instanced(&self, mode: GLenum, first: i32, count: i32, instances: i32) {
+        unsafe { gl::DrawArraysInstanced(mode, first, count as GLsizei, instances as GLsizei) }
+    }
+
+    fn draw_elements(&self, mode: GLenum, count: i32, index_type: GLenum, offset: usize) {
+        unsafe { gl::DrawElements(mode, count as GLsizei, index_type, offset as *const c_void) }
+    }
+}
+
+fn create_whitespace_cstring_with_len(len: usize) -> CString {
+    // allocate buffer of correct size
+    let mut buffer: Vec<u8> = Vec::with_capacity(len + 1);
+    // fill it with len spaces
+    buffer.extend([b' '].iter().cycle().take(len));
+    // convert buffer to CString
+    unsafe { CString::from_vec_unchecked(buffer) }
+}
+
+/// A command issued to a `NullBackend`
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    CreateBuffer(u32),
+    BindBuffer { target: GLenum, buffer: u32 },
+    BufferData { target: GLenum, size: usize, usage: GLenum },
+    DeleteBuffer(u32),
+    BindBufferBase { target: GLenum, index: u32, buffer: u32 },
+    CreateVertexArray(u32),
+    BindVertexArray(u32),
+    EnableVertexAttribArray(u32),
+    VertexAttribPointer { index: u32, size: i32, type_: GLenum, normalized: bool, stride: i32, offset: usize },
+    VertexAttribIPointer { index: u32, size: i32, type_: GLenum, stride: i32, offset: usize },
+    VertexAttribDivisor { index: u32, divisor: u32 },
+    DeleteVertexArray(u32),
+    CreateTexture(u32),
+    BindTexture { target: GLenum, texture: u32 },
+    TexParameter { target: GLenum, name: GLenum, value: i32 },
+    TexImage2D { target: GLenum, level: i32, internal_format: GLenum, width: i32, height: i32, format: GLenum, type_: GLenum },
+    TexImage3D { target: GLenum, level: i32, internal_format: GLenum, width: i32, height: i32, depth: i32, format: GLenum, type_: GLenum },
+    TexSubImage2D { target: GLenum, level: i32, x: i32, y: i32, width: i32, height: i32, format: GLenum, type_: GLenum },
+    TexSubImage3D { target: GLenum, level: i32, x: i32, y: i32, z: i32, width: i32, height: i32, depth: i32, format: GLenum, type_: GLenum },
+    TexParameterF { target: GLenum, name: GLenum, value: f32 },
+    GenerateMipmap(GLenum),
+    ActiveTexture(u32),
+    DeleteTexture(u32),
+    CompileShader { stage: GLenum, shader: u32 },
+    DeleteShader(u32),
+    LinkProgram { shaders: Vec<u32>, program: u32 },
+    UseProgram(u32),
+    /// A uniform location lookup, recorded so tests can tell whether locations are cached
+    UniformLocation { program: u32, name: String },
+    SetUniform { location: i32, value: UniformValue },
+    UniformBlockBinding { program: u32, block_index: u32, binding: u32 },
+    DeleteProgram(u32),
+    ClearColor([f32; 4]),
+    Clear(GLenum),
+    Enable(GLenum),
+    Disable(GLenum),
+    BlendFunc { source: GLenum, destination: GLenum },
+    CreateFramebuffer(u32),
+    BindFramebuffer { target: GLenum, framebuffer: u32 },
+    FramebufferTexture2D { target: GLenum, attachment: GLenum, texture_target: GLenum, texture: u32, level: i32 },
+    DeleteFramebuffer(u32),
+    DrawBuffer(GLenum),
+    ReadBuffer(GLenum),
+    PolygonMode { face: GLenum, mode: GLenum },
+    PolygonOffset { factor: f32, units: f32 },
+    Viewport { x: i32, y: i32, width: i32, height: i32 },
+    DrawArrays { mode: GLenum, first: i32, count: i32 },
+    DrawArraysInstanced { mode: GLenum, first: i32, count: i32, instances: i32 },
+    DrawElements { mode: GLenum, count: i32, index_type: GLenum, offset: usize },
+}
+
+/// Backend that talks to no GPU and records every command it receives,
+/// so rendering code can be run and asserted on in tests
+#[derive(Debug)]
+pub struct NullBackend {
+    commands: RefCell<Vec<Command>>,
+    uniforms: RefCell<Vec<(u32, String)>>,
+    next_id: Cell<u32>,
+}
+
+impl Default for NullBackend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl NullBackend {
+    pub fn new() -> Self {
+        Self {
+            commands: RefCell::new(Vec::new()),
+            uniforms: RefCell::new(Vec::new()),
+            // 0 is the "no object" id in OpenGL
+            next_id: Cell::new(1),
+        }
+    }
+
+    /// Commands recorded so far, in the order they were issued
+    pub fn commands(&self) -> Vec<Command> {
+        self.commands.borrow().clone()
+    }
+
+    /// Returns the recorded commands and clears the log
+    pub fn take_commands(&self) -> Vec<Command> {
+        self.commands.take()
+    }
+
+    /// Name of the uniform a location was handed out for by `uniform_location`
+    pub fn uniform_name(&self, location: i32) -> Option<String> {
+        self.uniforms.borrow().get(location as usize).map(|(_, name)| name.clone())
+    }
+
+    fn record(&self, command: Command) {
+        self.commands.borrow_mut().push(command)
+    }
+
+    fn gen_id(&self) -> u32 {
+        let id = self.next_id.get();
+        self.next_id.set(id + 1);
+        id
+    }
+
+    /// Hands out locations in the order names are first seen, per program
+    fn location_of(&self, program: u32, name: &str) -> i32 {
+        let mut uniforms = self.uniforms.borrow_mut();
+        match uniforms.iter().position(|(p, n)| *p == program && n == name) {
+            Some(location) => location as i32,
+            None => {
+                uniforms.push((program, name.to_string()));
+                uniforms.len() as i32 - 1
+            }
+        }
+    }
+}
+
+impl Backend for NullBackend {
+    fn create_buffer(&self) -> u32 {
+        let id = self.gen_id();
+        self.record(Command::CreateBuffer(id));
+        id
+    }
+
+    fn bind_buffer(&self, target: GLenum, buffer: u32) {
+        self.record(Command::BindBuffer { target, buffer })
+    }
+
+    fn buffer_data(&self, target: GLenum, data: &[u8], usage: GLenum) {
+        self.record(Command::BufferData { target, size: data.len(), usage })
+    }
+
+    fn delete_buffer(&self, buffer: u32) {
+        self.record(Command::DeleteBuffer(buffer))
+    }
+
+    fn bind_buffer_base(&self, target: GLenum, index: u32, buffer: u32) {
+        self.record(Command::BindBufferBase { target, index, buffer })
+    }
+
+    fn create_vertex_array(&self) -> u32 {
+        let id = self.gen_id();
+        self.record(Command::CreateVertexArray(id));
+        id
+    }
+
+    fn bind_vertex_array(&self, vertex_array: u32) {
+        self.record(Command::BindVertexArray(vertex_array))
+    }
+
+    fn enable_vertex_attrib_array(&self, index: u32) {
+        self.record(Command::EnableVertexAttribArray(index))
+    }
+
+    fn vertex_attrib_pointer(&self, index: u32, size: i32, type_: GLenum, normalized: bool, stride: i32, offset: usize) {
+        self.record(Command::VertexAttribPointer { index, size, type_, normalized, stride, offset })
+    }
+
+    fn vertex_attrib_i_pointer(&self, index: u32, size: i32, type_: GLenum, stride: i32, offset: usize) {
+        self.record(Command::VertexAttribIPointer { index, size, type_, stride, offset })
+    }
+
+    fn vertex_attrib_divisor(&self, index: u32, divisor: u32) {
+        self.record(Command::VertexAttribDivisor { index, divisor })
+    }
+
+    fn delete_vertex_array(&self, vertex_array: u32) {
+        self.record(Command::DeleteVertexArray(vertex_array))
+    }
+
+    fn create_texture(&self) -> u32 {
+        let id = self.gen_id();
+        self.record(Command::CreateTexture(id));
+        id
+    }
+
+    fn bind_texture(&self, target: GLenum, texture: u32) {
+        self.record(Command::BindTexture { target, texture })
+    }
+
+    fn tex_parameter(&self, target: GLenum, name: GLenum, value: i32) {
+        self.record(Command::TexParameter { target, name, value })
+    }
+
+    fn tex_image_2d(&self, target: GLenum, level: i32, internal_format: GLenum, width: i32, height: i32, format: GLenum, type_: GLenum, _data: Option<&[u8]>) {
+        self.record(Command::TexImage2D { target, level, internal_format, width, height, format, type_ })
+    }
+
+    fn tex_image_3d(&self, target: GLenum, level: i32, internal_format: GLenum, width: i32, height: i32, depth: i32, format: GLenum, type_: GLenum, _data: Option<&[u8]>) {
+        self.record(Command::TexImage3D { target, level, internal_format, width, height, depth, format, type_ })
+    }
+
+    fn tex_sub_image_2d(&self, target: GLenum, level: i32, x: i32, y: i32, width: i32, height: i32, format: GLenum, type_: GLenum, _data: &[u8]) {
+        self.record(Command::TexSubImage2D { target, level, x, y, width, height, format, type_ })
+    }
+
+    fn tex_sub_image_3d(&self, target: GLenum, level: i32, x: i32, y: i32, z: i32, width: i32, height: i32, depth: i32, format: GLenum, type_: GLenum, _data: &[u8]) {
+        self.record(Command::TexSubImage3D { target, level, x, y, z, width, height, depth, format, type_ })
+    }
+
+    fn tex_parameter_f(&self, target: GLenum, name: GLenum, value: f32) {
+        self.record(Command::TexParameterF { target, name, value })
+    }
+
+    fn generate_mipmap(&self, target: GLenum) {
+        self.record(Command::GenerateMipmap(target))
+    }
+
+    fn active_texture(&self, unit: u32) {
+        self.record(Command::ActiveTexture(unit))
+    }
+
+    fn delete_texture(&self, texture: u32) {
+        self.record(Command::DeleteTexture(texture))
+    }
+
+    fn get_float(&self, _name: GLenum) -> f32 {
+        0.0
+    }
+
+    fn create_framebuffer(&self) -> u32 {
+        let id = self.gen_id();
+        self.record(Command::CreateFramebuffer(id));
+        id
+    }
+
+    fn bind_framebuffer(&self, target: GLenum, framebuffer: u32) {
+        self.record(Command::BindFramebuffer { target, framebuffer })
+    }
+
+    fn framebuffer_texture_2d(&self, target: GLenum, attachment: GLenum, texture_target: GLenum, texture: u32, level: i32) {
+        self.record(Command::FramebufferTexture2D { target, attachment, texture_target, texture, level })
+    }
+
+    fn check_framebuffer_status(&self, _target: GLenum) -> GLenum {
+        gl::FRAMEBUFFER_COMPLETE
+    }
+
+    fn delete_framebuffer(&self, framebuffer: u32) {
+        self.record(Command::DeleteFramebuffer(framebuffer))
+    }
+
+    fn draw_buffer(&self, buffer: GLenum) {
+        self.record(Command::DrawBuffer(buffer))
+    }
+
+    fn read_buffer(&self, buffer: GLenum) {
+        self.record(Command::ReadBuffer(buffer))
+    }
+
+    fn compile_shader(&self, stage: GLenum, _source: &str) -> Result<u32, String> {
+        let shader = self.gen_id();
+        self.record(Command::CompileShader { stage, shader });
+        Ok(shader)
+    }
+
+    fn delete_shader(&self, shader: u32) {
+        self.record(Command::DeleteShader(shader))
+    }
+
+    fn link_program(&self, shaders: &[u32]) -> Result<u32, String> {
+        let program = self.gen_id();
+        self.record(Command::LinkProgram { shaders: shaders.to_vec(), program });
+        Ok(program)
+    }
+
+    fn use_program(&self, program: u32) {
+        self.record(Command::UseProgram(program))
+    }
+
+    fn uniform_location(&self, program: u32, name: &str) -> i32 {
+        self.record(Command::UniformLocation { program, name: name.to_string() });
+        self.location_of(program, name)
+    }
+
+    fn set_uniform(&self, location: i32, value: UniformValue) {
+        self.record(Command::SetUniform { location, value })
+    }
+
+    fn uniform_block_index(&self, program: u32, name: &str) -> u32 {
+        // blocks share the uniform name table, their index is never used as a location
+        self.location_of(program, name) as u32
+    }
+
+    fn uniform_block_binding(&self, program: u32, block_index: u32, binding: u32) {
+        self.record(Command::UniformBlockBinding { program, block_index, binding })
+    }
+
+    fn delete_program(&self, program: u32) {
+        self.record(Command::DeleteProgram(program))
+    }
+
+    fn clear_color(&self, r: f32, g: f32, b: f32, a: f32) {
+        self.record(Command::ClearColor([r, g, b, a]))
+    }
+
+    fn clear(&self, mask: GLenum) {
+        self.record(Command::Clear(mask))
+    }
+
+    fn enable(&self, capability: GLenum) {
+        self.record(Command::Enable(capability))
+    }
+
+    fn disable(&self, capability: GLenum) {
+        self.record(Command::Disable(capability))
+    }
+
+    fn blend_func(&self, source: GLenum, destination: GLenum) {
+        self.record(Command::BlendFunc { source, destination })
+    }
+
+    fn polygon_mode(&self, face: GLenum, mode: GLenum) {
+        self.record(Command::PolygonMode { face, mode })
+    }
+
+    fn polygon_offset(&self, factor: f32, units: f32) {
+        self.record(Command::PolygonOffset { factor, units })
+    }
+
+    fn viewport(&self, x: i32, y: i32, width: i32, height: i32) {
+        self.record(Command::Viewport { x, y, width, height })
+    }
+
+    fn draw_arrays(&self, mode: GLenum, first: i32, count: i32) {
+        self.record(Command::DrawArrays { mode, first, count })
+    }
+
+    fn draw_arrays_instanced(&self, mode: GLenum, first: i32, count: i32, instances: i32) {
+        self.record(Command::DrawArraysInstanced { mode, first, count, instances })
+    }
+
+    fn draw_elements(&self, mode: GLenum, count: i32, index_type: GLenum, offset: usize) {
+        self.record(Command::DrawElements { mode, count, index_type, offset })
+    }
+}
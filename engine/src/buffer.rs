@@ -1,42 +1,255 @@
-use gl::types::{GLuint, GLsizeiptr};
+use std::{cell::Cell, fmt, marker::PhantomData, rc::Rc};
 
-use crate::glchk;
+use gl::types::GLenum;
+use glm::{Mat4, Vec2, Vec3, Vec4};
 
-pub struct Buffer {
-    pub id: GLuint,
-    target: GLuint
+use crate::backend::Backend;
+
+/// A GPU buffer holding elements of type `T`
+pub struct Buffer<T = u8> {
+    pub id: u32,
+    target: GLenum,
+    len: Cell<usize>,
+    backend: Rc<dyn Backend>,
+    _marker: PhantomData<T>
 }
-#[allow(redundant_semicolons)]
-impl Buffer {
-    pub unsafe fn new(target: GLuint) -> Self {
-        let mut id: GLuint = 0;
-        glchk!(gl::GenBuffers(1, &mut id););
+
+impl<T: Copy> Buffer<T> {
+    pub fn new(backend: &Rc<dyn Backend>, target: GLenum) -> Self {
         Self {
-            id, target
+            id: backend.create_buffer(),
+            target,
+            len: Cell::new(0),
+            backend: backend.clone(),
+            _marker: PhantomData
         }
     }
 
-    pub unsafe fn bind(&self) {
-        gl::BindBuffer(self.target, self.id);
+    /// Creates a buffer and uploads `data` to it
+    pub fn with_data(backend: &Rc<dyn Backend>, target: GLenum, data: &[T], usage: GLenum) -> Self {
+        let buffer = Self::new(backend, target);
+        buffer.set_data(data, usage);
+        buffer
     }
 
-    pub unsafe fn set_data<D>(&self, data: &[D], usage: GLuint) {
+    pub fn bind(&self) {
+        self.backend.bind_buffer(self.target, self.id);
+    }
+
+    pub fn set_data(&self, data: &[T], usage: GLenum) {
         self.bind();
-        let (_, data_bytes, _) = data.align_to::<u8>();
-        gl::BufferData(
-            self.target,
-            data_bytes.len() as GLsizeiptr,
-            data_bytes.as_ptr() as *const _,
-            usage
-        )
+        let (_, data_bytes, _) = unsafe { data.align_to::<u8>() };
+        self.backend.buffer_data(self.target, data_bytes, usage);
+        self.len.set(data.len());
+    }
+
+    /// Number of elements last uploaded
+    pub fn len(&self) -> usize {
+        self.len.get()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len.get() == 0
+    }
+
+    pub fn target(&self) -> GLenum {
+        self.target
+    }
+
+    /// Size of the uploaded data in bytes
+    pub fn size_bytes(&self) -> usize {
+        self.len.get() * std::mem::size_of::<T>()
+    }
+}
+
+impl<T> fmt::Debug for Buffer<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Buffer")
+            .field("id", &self.id)
+            .field("target", &self.target)
+            .field("len", &self.len.get())
+            .finish()
     }
 }
 
-impl Drop for Buffer {
+impl<T> Drop for Buffer<T> {
     fn drop(&mut self) {
-        unsafe {
-            gl::DeleteBuffers(1, [self.id].as_ptr());
+        self.backend.delete_buffer(self.id)
+    }
+}
+
+/// Packs values into a byte buffer following the GLSL std140 layout rules
+#[derive(Debug, Default)]
+pub struct Std140Writer {
+    bytes: Vec<u8>
+}
+
+impl Std140Writer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn float(&mut self, value: f32) -> &mut Self {
+        self.push(4, &[value])
+    }
+
+    pub fn int(&mut self, value: i32) -> &mut Self {
+        self.align(4);
+        self.bytes.extend_from_slice(&value.to_ne_bytes());
+        self
+    }
+
+    pub fn vec2(&mut self, value: &Vec2) -> &mut Self {
+        self.push(8, value.as_slice())
+    }
+
+    /// vec3 is aligned like a vec4 but only takes 12 bytes, a following scalar fills the gap
+    pub fn vec3(&mut self, value: &Vec3) -> &mut Self {
+        self.push(16, value.as_slice())
+    }
+
+    pub fn vec4(&mut self, value: &Vec4) -> &mut Self {
+        self.push(16, value.as_slice())
+    }
+
+    /// Each element of a `float[]` takes a whole vec4 slot
+    pub fn float_array(&mut self, values: &[f32]) -> &mut Self {
+        for value in values {
+            self.push(16, &[*value]);
+        }
+        // whatever follows the array starts on a new slot too
+        self.align(16);
+        self
+    }
+
+    /// Matrices are stored as an array of column vec4s
+    pub fn mat4(&mut self, value: &Mat4) -> &mut Self {
+        self.push(16, value.as_slice())
+    }
+
+    /// Returns the packed bytes, padded to a multiple of a vec4
+    pub fn finish(mut self) -> Vec<u8> {
+        self.align(16);
+        self.bytes
+    }
+
+    fn push(&mut self, alignment: usize, values: &[f32]) -> &mut Self {
+        self.align(alignment);
+        for value in values {
+            self.bytes.extend_from_slice(&value.to_ne_bytes());
         }
+        self
+    }
+
+    fn align(&mut self, alignment: usize) {
+        let padding = (alignment - self.bytes.len() % alignment) % alignment;
+        self.bytes.resize(self.bytes.len() + padding, 0);
+    }
+}
+
+/// A type that can be written into a std140 uniform block.
+/// Fields must be written in the order they are declared in the GLSL block.
+pub trait Std140 {
+    fn write_std140(&self, writer: &mut Std140Writer);
+}
+
+/// A uniform buffer object holding one `T`, bound to a fixed binding point
+/// that programs attach their blocks to with `Shader::bind_uniform_block`
+pub struct UniformBuffer<T: Std140> {
+    pub buffer: Buffer<u8>,
+    pub binding: u32,
+    _marker: PhantomData<T>
+}
+
+impl<T: Std140> UniformBuffer<T> {
+    pub fn new(backend: &Rc<dyn Backend>, binding: u32) -> Self {
+        Self {
+            buffer: Buffer::new(backend, gl::UNIFORM_BUFFER),
+            binding,
+            _marker: PhantomData
+        }
+    }
+
+    /// Uploads `value` and binds the buffer to its binding point
+    pub fn update(&self, value: &T) {
+        let mut writer = Std140Writer::new();
+        value.write_std140(&mut writer);
+        self.buffer.set_data(&writer.finish(), gl::DYNAMIC_DRAW);
+        self.bind();
+    }
+
+    pub fn bind(&self) {
+        self.buffer.backend.bind_buffer_base(gl::UNIFORM_BUFFER, self.binding, self.buffer.id)
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::{Command, NullBackend};
+    use crate::shader::FrameUniforms;
+
+    #[test]
+    fn set_data_binds_then_uploads_every_byte() {
+        let null = Rc::new(NullBackend::new());
+        let backend: Rc<dyn Backend> = null.clone();
+        let buffer = Buffer::<[f32; 3]>::new(&backend, gl::ARRAY_BUFFER);
+        null.take_commands();
+
+        buffer.set_data(&[[0.0; 3]; 4], gl::STATIC_DRAW);
+
+        assert_eq!(null.take_commands(), vec![
+            Command::BindBuffer { target: gl::ARRAY_BUFFER, buffer: buffer.id },
+            Command::BufferData { target: gl::ARRAY_BUFFER, size: 4 * 12, usage: gl::STATIC_DRAW }
+        ]);
+        assert_eq!(buffer.len(), 4);
+        assert_eq!(buffer.size_bytes(), 48);
+    }
+
+    fn float_at(bytes: &[u8], offset: usize) -> f32 {
+        f32::from_ne_bytes(bytes[offset..offset + 4].try_into().unwrap())
+    }
+
+    #[test]
+    fn frame_uniforms_match_the_glsl_block_offsets() {
+        let uniforms = FrameUniforms {
+            view: Mat4::from_fn(|row, column| (column * 4 + row) as f32),
+            projection: Mat4::from_fn(|row, column| (100 + column * 4 + row) as f32),
+            camera_position: Vec3::new(1.0, 2.0, 3.0),
+            time: 4.0,
+            fog_colour: Vec4::new(5.0, 6.0, 7.0, 8.0),
+            fog_start: 9.0,
+            fog_end: 10.0,
+            light_direction: Vec3::new(11.0, 12.0, 13.0),
+            ambient_light: 14.0,
+            light_colour: Vec4::new(15.0, 16.0, 17.0, 18.0),
+            view_mode: 19
+        };
+        let mut writer = Std140Writer::new();
+        uniforms.write_std140(&mut writer);
+        let bytes = writer.finish();
+
+        assert_eq!(bytes.len(), 224);
+        // mat4 view, columns one after another
+        assert_eq!(float_at(&bytes, 4 * 4), 4.0);
+        assert_eq!(float_at(&bytes, 64 + 15 * 4), 115.0);
+        // vec3 cameraPosition, with float time in its fourth component
+        assert_eq!([float_at(&bytes, 128), float_at(&bytes, 136), float_at(&bytes, 140)], [1.0, 3.0, 4.0]);
+        assert_eq!(float_at(&bytes, 144), 5.0);
+        assert_eq!([float_at(&bytes, 160), float_at(&bytes, 164)], [9.0, 10.0]);
+        // vec3 lightDirection skips to the next vec4 slot
+        assert_eq!([float_at(&bytes, 168), float_at(&bytes, 176), float_at(&bytes, 188)], [0.0, 11.0, 14.0]);
+        assert_eq!(float_at(&bytes, 192), 15.0);
+        assert_eq!(i32::from_ne_bytes(bytes[208..212].try_into().unwrap()), 19);
+    }
+
+    #[test]
+    fn float_arrays_use_a_vec4_stride() {
+        let mut writer = Std140Writer::new();
+        writer.float(1.0).float_array(&[2.0, 3.0]).float(4.0);
+        let bytes = writer.finish();
+
+        assert_eq!(bytes.len(), 64);
+        assert_eq!([float_at(&bytes, 16), float_at(&bytes, 32), float_at(&bytes, 48)], [2.0, 3.0, 4.0]);
+    }
+}
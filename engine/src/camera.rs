@@ -38,7 +38,13 @@ pub struct Camera {
     pub pitch: f32,
     // Camera options
     pub movement_speed: f32,
+    /// `movement_speed` while walking, set from `ControlsConfig::walk_speed`
+    pub walk_speed: f32,
+    /// `movement_speed` while the sprint key is held, set from `ControlsConfig::sprint_speed`
+    pub sprint_speed: f32,
     pub mouse_sensitivity: f32,
+    /// Changed by scrolling but not used for the projection, which takes its
+    /// field of view from `CameraConfig::fov`
     pub zoom: f32,
     pub cursor_mode: bool,
     pub last_frame_movements: Vec<CameraMovement>,
@@ -55,6 +61,8 @@ impl Default for Camera {
             yaw: YAW,
             pitch: PITCH,
             movement_speed: SPEED,
+            walk_speed: SPEED,
+            sprint_speed: SPEED * 2.0,
             mouse_sensitivity: SENSITIVTY,
             zoom: ZOOM,
             cursor_mode: false,
@@ -99,12 +107,14 @@ impl Camera {
         }
     }
 
-    pub fn process_mouse_input(&mut self, mut x_offset: f32, mut y_offset: f32, constrain_pitch: bool) {
-        x_offset *= self.mouse_sensitivity;
-        y_offset *= self.mouse_sensitivity;
+    pub fn process_mouse_input(&mut self, x_offset: f32, y_offset: f32, constrain_pitch: bool) {
+        self.process_look_input(x_offset * self.mouse_sensitivity, y_offset * self.mouse_sensitivity, constrain_pitch)
+    }
 
-        self.yaw += x_offset;
-        self.pitch += y_offset;
+    /// Turns the camera by angles in degrees, for input that isn't scaled by `mouse_sensitivity`
+    pub fn process_look_input(&mut self, yaw: f32, pitch: f32, constrain_pitch: bool) {
+        self.yaw += yaw;
+        self.pitch += pitch;
 
         if constrain_pitch {
             if self.pitch > 89.0 {
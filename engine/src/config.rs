@@ -0,0 +1,381 @@
+use std::{fs, io, path::{Path, PathBuf}};
+
+use glm::Vec2;
+use serde::{Deserialize, Serialize, Serializer};
+use thiserror::Error;
+
+use crate::camera::Camera;
+use crate::gamepad;
+use crate::input::InputManager;
+use crate::keybinds::KeyMap;
+use crate::window::Window;
+
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("Failed to access config file {}: {source}", path.display())]
+    Io { path: PathBuf, source: io::Error },
+    #[error("Failed to parse config file {}: {source}", path.display())]
+    Parse { path: PathBuf, source: toml::de::Error },
+    #[error("Failed to serialise config: {0}")]
+    Serialize(#[from] toml::ser::Error),
+    #[error("Invalid setting {field}: {reason}")]
+    Invalid { field: &'static str, reason: String }
+}
+
+/// Window and rendering options
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VideoConfig {
+    /// Size of the window when not fullscreen
+    pub width: u32,
+    pub height: u32,
+    pub title: String,
+    pub fullscreen: bool,
+    pub vsync: bool,
+    pub shadows: bool,
+    pub clouds: bool
+}
+
+impl Default for VideoConfig {
+    fn default() -> Self {
+        Self {
+            width: 1280,
+            height: 720,
+            title: String::from("test"),
+            fullscreen: false,
+            vsync: true,
+            shadows: true,
+            clouds: true
+        }
+    }
+}
+
+/// Projection options
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CameraConfig {
+    /// Vertical field of view in degrees
+    #[serde(serialize_with = "short_float")]
+    pub fov: f32,
+    #[serde(serialize_with = "short_float")]
+    pub near_plane: f32,
+    #[serde(serialize_with = "short_float")]
+    pub far_plane: f32
+}
+
+impl Default for CameraConfig {
+    fn default() -> Self {
+        Self {
+            fov: 57.3,
+            near_plane: 0.1,
+            far_plane: 100.0
+        }
+    }
+}
+
+impl CameraConfig {
+    pub fn fov_radians(&self) -> f32 {
+        self.fov.to_radians()
+    }
+}
+
+/// Mouse and movement options
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ControlsConfig {
+    #[serde(serialize_with = "short_float")]
+    pub mouse_sensitivity: f32,
+    /// Blocks per second
+    #[serde(serialize_with = "short_float")]
+    pub walk_speed: f32,
+    /// Blocks per second while the sprint key is held
+    #[serde(serialize_with = "short_float")]
+    pub sprint_speed: f32
+}
+
+impl Default for ControlsConfig {
+    fn default() -> Self {
+        Self {
+            mouse_sensitivity: 0.1,
+            walk_speed: 5.0,
+            sprint_speed: 10.0
+        }
+    }
+}
+
+/// Gamepad options, see `gamepad::Gamepads`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GamepadConfig {
+    #[serde(serialize_with = "short_float")]
+    pub stick_dead_zone: f32,
+    #[serde(serialize_with = "short_float")]
+    pub trigger_dead_zone: f32,
+    /// Degrees per second the camera turns with the look stick fully tilted
+    #[serde(serialize_with = "short_float")]
+    pub look_sensitivity: f32,
+    /// Exponent of the look stick's response, 1.0 is linear
+    #[serde(serialize_with = "short_float")]
+    pub look_curve: f32,
+    pub invert_y: bool
+}
+
+impl Default for GamepadConfig {
+    fn default() -> Self {
+        Self {
+            stick_dead_zone: 0.15,
+            trigger_dead_zone: 0.1,
+            look_sensitivity: 180.0,
+            look_curve: 2.0,
+            invert_y: false
+        }
+    }
+}
+
+impl GamepadConfig {
+    /// Degrees to turn the camera by this frame for a look stick's position
+    pub fn look(&self, stick: Vec2, delta_time: f32) -> Vec2 {
+        let mut look = gamepad::response_curve(stick, self.look_curve) * self.look_sensitivity * delta_time;
+        if self.invert_y {
+            look.y = -look.y;
+        }
+        look
+    }
+}
+
+/// World generation options, only read when a world is created
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WorldConfig {
+    pub seed: u32,
+    /// Width of the world in chunks
+    pub size: u32,
+    /// Generate 16 chunks of height instead of `size`
+    pub tall: bool,
+    /// Multiplier on how fast the day passes
+    #[serde(serialize_with = "short_float")]
+    pub day_speed: f32
+}
+
+impl Default for WorldConfig {
+    fn default() -> Self {
+        Self {
+            seed: 0,
+            size: 16,
+            tall: false,
+            day_speed: 1.0
+        }
+    }
+}
+
+/// Settings persisted between runs as a TOML file. Missing sections and keys
+/// take their default values, so old files keep loading as settings are added.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub video: VideoConfig,
+    pub camera: CameraConfig,
+    pub controls: ControlsConfig,
+    pub gamepad: GamepadConfig,
+    pub world: WorldConfig,
+    /// Action names to the keys and mouse buttons bound to them
+    pub bindings: KeyMap
+}
+
+impl Config {
+    /// Reads and validates the config at `path`
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, ConfigError> {
+        let path = path.as_ref();
+        let source = fs::read_to_string(path).map_err(|source| ConfigError::Io { path: path.to_path_buf(), source })?;
+        let config: Self = toml::from_str(&source).map_err(|source| ConfigError::Parse { path: path.to_path_buf(), source })?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Loads the config at `path`, writing the defaults there if it doesn't exist yet.
+    /// Unreadable or invalid files are reported and replaced by the defaults in memory,
+    /// the file is left alone so it can be fixed by hand.
+    pub fn load_or_default<P: AsRef<Path>>(path: P) -> Self {
+        let path = path.as_ref();
+        match Self::load(path) {
+            Ok(config) => config,
+            Err(ConfigError::Io { source, .. }) if source.kind() == io::ErrorKind::NotFound => {
+                let config = Self::default();
+                if let Err(err) = config.save(path) {
+                    println!("{}", err);
+                }
+                config
+            },
+            Err(err) => {
+                println!("{}, using the default settings", err);
+                Self::default()
+            }
+        }
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), ConfigError> {
+        let path = path.as_ref();
+        let source = toml::to_string_pretty(self)?;
+        fs::write(path, source).map_err(|source| ConfigError::Io { path: path.to_path_buf(), source })
+    }
+
+    /// Checks values that would break rendering or movement
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let invalid = |field, reason: String| Err(ConfigError::Invalid { field, reason });
+        if self.video.width == 0 || self.video.height == 0 {
+            return invalid("video.width", format!("window size must be above zero, got {}x{}", self.video.width, self.video.height))
+        }
+        if !(10.0..=170.0).contains(&self.camera.fov) {
+            return invalid("camera.fov", format!("must be between 10 and 170 degrees, got {}", self.camera.fov))
+        }
+        if self.camera.near_plane <= 0.0 {
+            return invalid("camera.near_plane", format!("must be above zero, got {}", self.camera.near_plane))
+        }
+        if self.camera.far_plane <= self.camera.near_plane {
+            return invalid("camera.far_plane", format!("must be beyond the near plane, got {}", self.camera.far_plane))
+        }
+        if self.controls.mouse_sensitivity <= 0.0 {
+            return invalid("controls.mouse_sensitivity", format!("must be above zero, got {}", self.controls.mouse_sensitivity))
+        }
+        if self.controls.walk_speed <= 0.0 {
+            return invalid("controls.walk_speed", format!("must be above zero, got {}", self.controls.walk_speed))
+        }
+        if self.controls.sprint_speed <= 0.0 {
+            return invalid("controls.sprint_speed", format!("must be above zero, got {}", self.controls.sprint_speed))
+        }
+        if !(0.0..1.0).contains(&self.gamepad.stick_dead_zone) {
+            return invalid("gamepad.stick_dead_zone", format!("must be at least 0 and below 1, got {}", self.gamepad.stick_dead_zone))
+        }
+        if !(0.0..1.0).contains(&self.gamepad.trigger_dead_zone) {
+            return invalid("gamepad.trigger_dead_zone", format!("must be at least 0 and below 1, got {}", self.gamepad.trigger_dead_zone))
+        }
+        if self.gamepad.look_sensitivity <= 0.0 {
+            return invalid("gamepad.look_sensitivity", format!("must be above zero, got {}", self.gamepad.look_sensitivity))
+        }
+        if self.gamepad.look_curve <= 0.0 {
+            return invalid("gamepad.look_curve", format!("must be above zero, got {}", self.gamepad.look_curve))
+        }
+        if !(1..=64).contains(&self.world.size) {
+            return invalid("world.size", format!("must be between 1 and 64 chunks, got {}", self.world.size))
+        }
+        if self.world.day_speed < 0.0 {
+            return invalid("world.day_speed", format!("can't be negative, got {}", self.world.day_speed))
+        }
+        Ok(())
+    }
+
+    /// Applies the window options that can change while running
+    pub fn apply_to_window(&self, window: &mut Window) {
+        if self.video.fullscreen != window.is_fullscreen {
+            window.toggle_fullscreen();
+        }
+        window.context.set_swap_interval(if self.video.vsync { glfw::SwapInterval::Sync(1) } else { glfw::SwapInterval::None });
+    }
+
+    pub fn apply_to_camera(&self, camera: &mut Camera) {
+        camera.mouse_sensitivity = self.controls.mouse_sensitivity;
+        camera.walk_speed = self.controls.walk_speed;
+        camera.sprint_speed = self.controls.sprint_speed;
+        camera.movement_speed = self.controls.walk_speed;
+    }
+
+    pub fn apply_to_input(&self, input: &mut InputManager) {
+        let gamepads = input.gamepads_mut();
+        gamepads.stick_dead_zone = self.gamepad.stick_dead_zone;
+        gamepads.trigger_dead_zone = self.gamepad.trigger_dead_zone;
+    }
+}
+
+/// Writes an `f32` as its shortest decimal form, widening it to `f64` would write 0.1 as 0.10000000149011612
+fn short_float<S: Serializer>(value: &f32, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_f64(value.to_string().parse().unwrap_or(*value as f64))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn invalid_field(config: &Config) -> Option<&'static str> {
+        match config.validate() {
+            Err(ConfigError::Invalid { field, .. }) => Some(field),
+            Err(err) => panic!("unexpected error {}", err),
+            Ok(()) => None
+        }
+    }
+
+    #[test]
+    fn defaults_are_valid() {
+        assert_eq!(invalid_field(&Config::default()), None);
+        assert_eq!(Config::default().camera.far_plane, 100.0);
+    }
+
+    #[test]
+    fn range_limits_are_inclusive_where_documented() {
+        let mut config = Config::default();
+        config.camera.fov = 10.0;
+        assert_eq!(invalid_field(&config), None);
+        config.camera.fov = 170.0;
+        assert_eq!(invalid_field(&config), None);
+        config.camera.fov = 9.9;
+        assert_eq!(invalid_field(&config), Some("camera.fov"));
+        config.camera.fov = 170.1;
+        assert_eq!(invalid_field(&config), Some("camera.fov"));
+
+        let mut config = Config::default();
+        config.gamepad.stick_dead_zone = 0.0;
+        assert_eq!(invalid_field(&config), None);
+        config.gamepad.stick_dead_zone = 1.0;
+        assert_eq!(invalid_field(&config), Some("gamepad.stick_dead_zone"));
+
+        let mut config = Config::default();
+        config.world.size = 1;
+        assert_eq!(invalid_field(&config), None);
+        config.world.size = 64;
+        assert_eq!(invalid_field(&config), None);
+        config.world.size = 65;
+        assert_eq!(invalid_field(&config), Some("world.size"));
+        config.world.size = 0;
+        assert_eq!(invalid_field(&config), Some("world.size"));
+
+        let mut config = Config::default();
+        config.world.day_speed = 0.0;
+        assert_eq!(invalid_field(&config), None);
+        config.world.day_speed = -0.1;
+        assert_eq!(invalid_field(&config), Some("world.day_speed"));
+    }
+
+    #[test]
+    fn each_invalid_setting_names_its_field() {
+        let cases: [(_, fn(&mut Config)); 13] = [
+            ("video.width", |config| config.video.height = 0),
+            ("camera.fov", |config| config.camera.fov = 0.0),
+            ("camera.near_plane", |config| config.camera.near_plane = 0.0),
+            ("camera.far_plane", |config| config.camera.far_plane = config.camera.near_plane),
+            ("controls.mouse_sensitivity", |config| config.controls.mouse_sensitivity = -1.0),
+            ("controls.walk_speed", |config| config.controls.walk_speed = 0.0),
+            ("controls.sprint_speed", |config| config.controls.sprint_speed = 0.0),
+            ("gamepad.stick_dead_zone", |config| config.gamepad.stick_dead_zone = -0.1),
+            ("gamepad.trigger_dead_zone", |config| config.gamepad.trigger_dead_zone = 1.5),
+            ("gamepad.look_sensitivity", |config| config.gamepad.look_sensitivity = 0.0),
+            ("gamepad.look_curve", |config| config.gamepad.look_curve = 0.0),
+            ("world.size", |config| config.world.size = 100),
+            ("world.day_speed", |config| config.world.day_speed = -1.0)
+        ];
+        for (field, break_setting) in cases {
+            let mut config = Config::default();
+            break_setting(&mut config);
+            assert_eq!(invalid_field(&config), Some(field));
+        }
+    }
+
+    #[test]
+    fn load_rejects_invalid_files() {
+        let path = std::env::temp_dir().join(format!("engine-config-test-{}.toml", std::process::id()));
+        fs::write(&path, "[camera]\nnear_plane = 10.0\nfar_plane = 5.0\n").unwrap();
+        let result = Config::load(&path);
+        fs::remove_file(&path).unwrap();
+        assert!(matches!(result, Err(ConfigError::Invalid { field: "camera.far_plane", .. })));
+
+        assert!(matches!(Config::load(&path), Err(ConfigError::Io { .. })));
+    }
+}
@@ -0,0 +1,176 @@
+use std::{f32::consts::TAU, rc::Rc};
+
+use glm::{Vec3, Vec4};
+
+use crate::backend::Backend;
+use crate::shader::{Shader, ShaderError, FRAME_UNIFORMS_BINDING, FRAME_UNIFORMS_GLSL};
+use crate::vertex_array::Mesh;
+
+/// Segments in each of a sphere's circles
+const CIRCLE_SEGMENTS: usize = 24;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct DebugVertex {
+    position: [f32; 3],
+    colour: [u8; 4]
+}
+crate::impl_vertex!(DebugVertex { 0 => position, 1 => colour });
+
+const VERTEX_SOURCE: &str = "
+layout (location = 0) in vec3 Position;
+layout (location = 1) in vec4 aColour;
+
+out vec4 Colour;
+
+void main()
+{
+    gl_Position = projection * view * vec4(Position, 1.0);
+    Colour = aColour;
+}
+";
+
+const FRAGMENT_SOURCE: &str = "
+out vec4 Color;
+
+in vec4 Colour;
+
+void main()
+{
+    Color = Colour;
+}
+";
+
+#[derive(Debug, Clone, Copy)]
+struct DebugLine {
+    start: Vec3,
+    end: Vec3,
+    colour: [u8; 4],
+    /// Seconds left to draw the line for, it's drawn at least once
+    lifetime: f32
+}
+
+/// Immediate mode lines in world space for visualising bounds, rays and the like.
+/// Shapes are queued from anywhere during a frame and kept for their lifetime in
+/// seconds, a lifetime of 0.0 draws them for a single frame.
+pub struct DebugDraw {
+    /// Hide lines behind geometry, turn off to see them through the world
+    pub depth_test: bool,
+    lines: Vec<DebugLine>,
+    vertices: Vec<DebugVertex>,
+    shader: Shader,
+    mesh: Mesh<DebugVertex>,
+    backend: Rc<dyn Backend>
+}
+
+impl DebugDraw {
+    pub fn new(backend: &Rc<dyn Backend>) -> Result<Self, ShaderError> {
+        let vertex_source = format!("#version 330 core\n{}{}", FRAME_UNIFORMS_GLSL, VERTEX_SOURCE);
+        let fragment_source = format!("#version 330 core\n{}", FRAGMENT_SOURCE);
+        let mut shader = Shader::from_source(backend, "debug draw", &vertex_source, &fragment_source)?;
+        shader.bind_uniform_block("Frame", FRAME_UNIFORMS_BINDING);
+        Ok(Self {
+            depth_test: true,
+            lines: Vec::new(),
+            vertices: Vec::new(),
+            shader,
+            mesh: Mesh::new(backend, &[], None, gl::STREAM_DRAW),
+            backend: backend.clone()
+        })
+    }
+
+    pub fn line(&mut self, start: Vec3, end: Vec3, colour: Vec4, lifetime: f32) {
+        let colour = colour.map(|channel| (channel.clamp(0.0, 1.0) * 255.0).round() as u8);
+        self.lines.push(DebugLine {
+            start,
+            end,
+            colour: [colour.x, colour.y, colour.z, colour.w],
+            lifetime
+        });
+    }
+
+    /// The 12 edges of an axis aligned box
+    pub fn aabb(&mut self, min: Vec3, max: Vec3, colour: Vec4, lifetime: f32) {
+        let corner = |x: bool, y: bool, z: bool| Vec3::new(
+            if x { max.x } else { min.x },
+            if y { max.y } else { min.y },
+            if z { max.z } else { min.z }
+        );
+        for (a, b) in [(false, false), (true, false), (false, true), (true, true)] {
+            self.line(corner(false, a, b), corner(true, a, b), colour, lifetime);
+            self.line(corner(a, false, b), corner(a, true, b), colour, lifetime);
+            self.line(corner(a, b, false), corner(a, b, true), colour, lifetime);
+        }
+    }
+
+    /// Circles around the x, y and z axes
+    pub fn sphere(&mut self, centre: Vec3, radius: f32, colour: Vec4, lifetime: f32) {
+        let axes = [Vec3::x(), Vec3::y(), Vec3::z()];
+        for i in 0..3 {
+            let (u, v) = (axes[(i + 1) % 3] * radius, axes[(i + 2) % 3] * radius);
+            let point = |segment: usize| {
+                let angle = segment as f32 / CIRCLE_SEGMENTS as f32 * TAU;
+                centre + u * angle.cos() + v * angle.sin()
+            };
+            for segment in 0..CIRCLE_SEGMENTS {
+                self.line(point(segment), point(segment + 1), colour, lifetime);
+            }
+        }
+    }
+
+    /// A line with a four pronged head at `end`
+    pub fn arrow(&mut self, start: Vec3, end: Vec3, colour: Vec4, lifetime: f32) {
+        self.line(start, end, colour, lifetime);
+        let length = glm::distance(&start, &end);
+        if length <= f32::EPSILON {
+            return
+        }
+        let direction = (end - start) / length;
+        // any vector not parallel to the arrow gives a perpendicular basis
+        let reference = if direction.y.abs() < 0.9 { Vec3::y() } else { Vec3::x() };
+        let side = glm::normalize(&direction.cross(&reference));
+        let up = direction.cross(&side);
+        let head_length = (length * 0.25).min(0.5);
+        let base = end - direction * head_length;
+        for offset in [side, -side, up, -up] {
+            self.line(end, base + offset * head_length * 0.5, colour, lifetime);
+        }
+    }
+
+    /// Ages queued shapes, call once per frame before drawing
+    pub fn update(&mut self, delta_time: f32) {
+        for line in self.lines.iter_mut() {
+            line.lifetime -= delta_time;
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.lines.clear();
+    }
+
+    /// Draws every live shape, removing the ones whose lifetime has run out
+    pub fn draw(&mut self) {
+        if self.lines.is_empty() {
+            return
+        }
+        self.vertices.clear();
+        for line in self.lines.iter() {
+            self.vertices.push(DebugVertex { position: line.start.into(), colour: line.colour });
+            self.vertices.push(DebugVertex { position: line.end.into(), colour: line.colour });
+        }
+        self.lines.retain(|line| line.lifetime > 0.0);
+        self.mesh.update(&self.vertices, None, gl::STREAM_DRAW);
+
+        if !self.depth_test {
+            self.backend.disable(gl::DEPTH_TEST);
+        }
+        self.backend.enable(gl::BLEND);
+        self.backend.blend_func(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+        self.shader.use_program();
+        self.mesh.draw(gl::LINES);
+        self.backend.disable(gl::BLEND);
+        if !self.depth_test {
+            self.backend.enable(gl::DEPTH_TEST);
+        }
+    }
+}
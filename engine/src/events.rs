@@ -0,0 +1,50 @@
+use glfw::{Action, Key, Modifiers, MouseButton, WindowEvent};
+use glm::Vec2;
+
+/// Something that happened to the window, collected by `Window::process_events`.
+/// The window only uses them to track the cursor, it's up to the game what a key press or
+/// cursor movement means and to pass resizes on to the renderer and render targets.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Event {
+    Key { key: Key, action: Action, modifiers: Modifiers },
+    /// Text typed, after the keyboard layout is applied
+    Char(char),
+    MouseButton { button: MouseButton, action: Action, modifiers: Modifiers },
+    /// Cursor position and movement since the previous one in screen coordinates, down is positive.
+    /// The first movement after the cursor mode changes has no delta, since the cursor jumps.
+    CursorMoved { position: Vec2, delta: Vec2 },
+    /// Away from the user and right are positive
+    Scroll(Vec2),
+    Focus(bool),
+    /// The close button was pressed, the window closes unless `set_should_close(false)` is called
+    CloseRequested,
+    /// In screen coordinates
+    Moved { x: i32, y: i32 },
+    /// In screen coordinates
+    Resized { width: i32, height: i32 },
+    /// In pixels, for resizing render targets
+    FramebufferResized { width: u32, height: u32 }
+}
+
+impl Event {
+    /// The engine event for a GLFW one, `None` for the ones nothing uses yet. Cursor
+    /// movement needs the previous position for its delta, which `Window` keeps.
+    pub(crate) fn from_glfw(event: WindowEvent, last_cursor: Option<Vec2>) -> Option<Self> {
+        Some(match event {
+            WindowEvent::Key(key, _, action, modifiers) => Event::Key { key, action, modifiers },
+            WindowEvent::Char(character) => Event::Char(character),
+            WindowEvent::MouseButton(button, action, modifiers) => Event::MouseButton { button, action, modifiers },
+            WindowEvent::CursorPos(x, y) => {
+                let position = Vec2::new(x as f32, y as f32);
+                Event::CursorMoved { position, delta: last_cursor.map_or(Vec2::zeros(), |last| position - last) }
+            },
+            WindowEvent::Scroll(x, y) => Event::Scroll(Vec2::new(x as f32, y as f32)),
+            WindowEvent::Focus(focused) => Event::Focus(focused),
+            WindowEvent::Close => Event::CloseRequested,
+            WindowEvent::Pos(x, y) => Event::Moved { x, y },
+            WindowEvent::Size(width, height) => Event::Resized { width, height },
+            WindowEvent::FramebufferSize(width, height) => Event::FramebufferResized { width: width.max(0) as u32, height: height.max(0) as u32 },
+            _ => return None
+        })
+    }
+}
@@ -0,0 +1,131 @@
+use std::rc::Rc;
+
+use gl::types::GLenum;
+use thiserror::Error;
+
+use crate::backend::Backend;
+use crate::events::Event;
+use crate::texture::{Texture, TextureFormat, TextureSettings};
+
+#[derive(Debug, Error)]
+pub enum FramebufferError {
+    #[error("Framebuffer is incomplete, status {0:#x}")]
+    Incomplete(GLenum),
+    #[error("Framebuffer needs a colour or depth attachment")]
+    NoAttachments,
+    #[error("{0:?} can't be used as a colour attachment")]
+    DepthAsColour(TextureFormat),
+    #[error("{0:?} can't be used as a depth attachment")]
+    ColourAsDepth(TextureFormat)
+}
+
+/// An offscreen render target with an optional colour texture and an optional depth texture.
+/// Both can be sampled after rendering, e.g. for post processing or shadow maps.
+pub struct Framebuffer {
+    pub id: u32,
+    pub size: (u32, u32),
+    colour_format: Option<TextureFormat>,
+    depth_format: Option<TextureFormat>,
+    settings: TextureSettings,
+    colour: Option<Texture>,
+    depth: Option<Texture>,
+    backend: Rc<dyn Backend>
+}
+
+impl Framebuffer {
+    pub fn new(backend: &Rc<dyn Backend>, size: (u32, u32), colour_format: Option<TextureFormat>, depth_format: Option<TextureFormat>) -> Result<Self, FramebufferError> {
+        Self::with_settings(backend, size, colour_format, depth_format, TextureSettings::render_target())
+    }
+
+    /// Creates a framebuffer whose attachments are sampled with `settings`
+    pub fn with_settings(backend: &Rc<dyn Backend>, size: (u32, u32), colour_format: Option<TextureFormat>, depth_format: Option<TextureFormat>, settings: TextureSettings) -> Result<Self, FramebufferError> {
+        match (colour_format, depth_format) {
+            (None, None) => return Err(FramebufferError::NoAttachments),
+            (Some(format), _) if format.is_depth() => return Err(FramebufferError::DepthAsColour(format)),
+            (_, Some(format)) if !format.is_depth() => return Err(FramebufferError::ColourAsDepth(format)),
+            _ => {}
+        }
+        let mut framebuffer = Self {
+            id: backend.create_framebuffer(),
+            size,
+            colour_format,
+            depth_format,
+            settings,
+            colour: None,
+            depth: None,
+            backend: backend.clone()
+        };
+        framebuffer.create_attachments()?;
+        Ok(framebuffer)
+    }
+
+    pub fn colour(&self) -> Option<&Texture> {
+        self.colour.as_ref()
+    }
+
+    pub fn depth(&self) -> Option<&Texture> {
+        self.depth.as_ref()
+    }
+
+    /// Recreates the attachments at a new size, their contents are lost
+    pub fn resize(&mut self, size: (u32, u32)) -> Result<(), FramebufferError> {
+        // minimised windows report a size of zero
+        if size == self.size || size.0 == 0 || size.1 == 0 {
+            return Ok(())
+        }
+        self.size = size;
+        self.create_attachments()
+    }
+
+    /// Follows the window's framebuffer size
+    pub fn handle_event(&mut self, event: &Event) -> Result<(), FramebufferError> {
+        match *event {
+            Event::FramebufferResized { width, height } => self.resize((width, height)),
+            _ => Ok(())
+        }
+    }
+
+    /// Renders into this framebuffer, setting the viewport to cover it
+    pub fn bind(&self) {
+        self.backend.bind_framebuffer(gl::FRAMEBUFFER, self.id);
+        self.backend.viewport(0, 0, self.size.0 as i32, self.size.1 as i32);
+    }
+
+    /// Renders into the window again, setting the viewport to `size`
+    pub fn bind_default(backend: &Rc<dyn Backend>, size: (u32, u32)) {
+        backend.bind_framebuffer(gl::FRAMEBUFFER, 0);
+        backend.viewport(0, 0, size.0 as i32, size.1 as i32);
+    }
+
+    fn create_attachments(&mut self) -> Result<(), FramebufferError> {
+        self.backend.bind_framebuffer(gl::FRAMEBUFFER, self.id);
+        self.colour = self.colour_format.map(|format| {
+            let texture = Texture::new_render_target(&self.backend, self.size, format, self.settings);
+            self.backend.framebuffer_texture_2d(gl::FRAMEBUFFER, gl::COLOR_ATTACHMENT0, gl::TEXTURE_2D, texture.id, 0);
+            texture
+        });
+        self.depth = self.depth_format.map(|format| {
+            let texture = Texture::new_render_target(&self.backend, self.size, format, self.settings);
+            self.backend.framebuffer_texture_2d(gl::FRAMEBUFFER, gl::DEPTH_ATTACHMENT, gl::TEXTURE_2D, texture.id, 0);
+            texture
+        });
+        if self.colour.is_none() {
+            // depth only framebuffers are incomplete while they'd draw to a missing colour attachment
+            self.backend.draw_buffer(gl::NONE);
+            self.backend.read_buffer(gl::NONE);
+        }
+
+        let status = self.backend.check_framebuffer_status(gl::FRAMEBUFFER);
+        self.backend.bind_framebuffer(gl::FRAMEBUFFER, 0);
+        if status != gl::FRAMEBUFFER_COMPLETE {
+            return Err(FramebufferError::Incomplete(status))
+        }
+        Ok(())
+    }
+}
+
+impl Drop for Framebuffer {
+    fn drop(&mut self) {
+        self.backend.delete_framebuffer(self.id)
+    }
+}
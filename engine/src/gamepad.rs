@@ -0,0 +1,215 @@
+use glfw::{Action, GamepadAxis, GamepadButton, GamepadState, Glfw, JoystickId};
+use glm::Vec2;
+
+use crate::keybinds::Input;
+
+/// How far a trigger bound like a button has to be pulled to count as held
+pub const TRIGGER_THRESHOLD: f32 = 0.5;
+
+const JOYSTICKS: [JoystickId; 16] = [
+    JoystickId::Joystick1, JoystickId::Joystick2, JoystickId::Joystick3, JoystickId::Joystick4,
+    JoystickId::Joystick5, JoystickId::Joystick6, JoystickId::Joystick7, JoystickId::Joystick8,
+    JoystickId::Joystick9, JoystickId::Joystick10, JoystickId::Joystick11, JoystickId::Joystick12,
+    JoystickId::Joystick13, JoystickId::Joystick14, JoystickId::Joystick15, JoystickId::Joystick16
+];
+
+const BUTTONS: [GamepadButton; 15] = [
+    GamepadButton::ButtonA, GamepadButton::ButtonB, GamepadButton::ButtonX, GamepadButton::ButtonY,
+    GamepadButton::ButtonLeftBumper, GamepadButton::ButtonRightBumper,
+    GamepadButton::ButtonBack, GamepadButton::ButtonStart, GamepadButton::ButtonGuide,
+    GamepadButton::ButtonLeftThumb, GamepadButton::ButtonRightThumb,
+    GamepadButton::ButtonDpadUp, GamepadButton::ButtonDpadRight, GamepadButton::ButtonDpadDown, GamepadButton::ButtonDpadLeft
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum GamepadEvent {
+    Connected { id: JoystickId, name: String },
+    Disconnected(JoystickId)
+}
+
+/// Polls the gamepads GLFW has a mapping for, noticing them being plugged in and out.
+/// Input is read from the active gamepad, the earliest connected one still plugged in.
+#[derive(Debug)]
+pub struct Gamepads {
+    /// Fraction of a stick's tilt that reads as centred, so worn sticks don't drift
+    pub stick_dead_zone: f32,
+    /// Fraction of a trigger's travel that reads as released
+    pub trigger_dead_zone: f32,
+    /// In the order they were connected
+    connected: Vec<JoystickId>,
+    state: Option<GamepadState>,
+    previous: Option<GamepadState>,
+    events: Vec<GamepadEvent>
+}
+
+impl Default for Gamepads {
+    fn default() -> Self {
+        Self {
+            stick_dead_zone: 0.15,
+            trigger_dead_zone: 0.1,
+            connected: Vec::new(),
+            state: None,
+            previous: None,
+            events: Vec::new()
+        }
+    }
+}
+
+impl Gamepads {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Checks for gamepads being connected or disconnected and reads the active one, call once per frame
+    pub fn update(&mut self, glfw: &Glfw) {
+        self.events.clear();
+        for id in JOYSTICKS {
+            let joystick = glfw.get_joystick(id);
+            // false for joysticks without a mapping as well as missing ones
+            let present = joystick.is_gamepad();
+            let known = self.connected.contains(&id);
+            if present && !known {
+                self.connected.push(id);
+                let name = joystick.get_gamepad_name().unwrap_or_else(|| format!("{:?}", id));
+                self.events.push(GamepadEvent::Connected { id, name });
+            } else if !present && known {
+                self.connected.retain(|connected| *connected != id);
+                self.events.push(GamepadEvent::Disconnected(id));
+            }
+        }
+        self.previous = self.state.take();
+        self.state = self.active().and_then(|id| glfw.get_joystick(id).get_gamepad_state());
+    }
+
+    /// Connections and disconnections found by the last `update`
+    pub fn events(&self) -> &[GamepadEvent] {
+        &self.events
+    }
+
+    pub fn connected(&self) -> &[JoystickId] {
+        &self.connected
+    }
+
+    pub fn active(&self) -> Option<JoystickId> {
+        self.connected.first().copied()
+    }
+
+    pub fn is_down(&self, button: GamepadButton) -> bool {
+        self.state.is_some_and(|state| state.get_button_state(button) != Action::Release)
+    }
+
+    /// An axis with its dead zone applied. Sticks are -1.0 to 1.0 with right and up positive,
+    /// triggers are 0.0 released to 1.0 pulled fully.
+    pub fn axis(&self, axis: GamepadAxis) -> f32 {
+        let Some(state) = self.state else {
+            return 0.0
+        };
+        match axis {
+            GamepadAxis::AxisLeftX => stick(&state, false, self.stick_dead_zone).x,
+            GamepadAxis::AxisLeftY => stick(&state, false, self.stick_dead_zone).y,
+            GamepadAxis::AxisRightX => stick(&state, true, self.stick_dead_zone).x,
+            GamepadAxis::AxisRightY => stick(&state, true, self.stick_dead_zone).y,
+            GamepadAxis::AxisLeftTrigger | GamepadAxis::AxisRightTrigger => trigger_dead_zone(trigger(&state, axis), self.trigger_dead_zone)
+        }
+    }
+
+    /// Whether a trigger bound like a button is past `TRIGGER_THRESHOLD`
+    pub fn is_trigger_down(&self, axis: GamepadAxis) -> bool {
+        self.axis(axis) > TRIGGER_THRESHOLD
+    }
+
+    /// A button or trigger pressed since the last update, for capturing new bindings
+    pub fn just_pressed(&self) -> Option<Input> {
+        let (state, previous) = (self.state?, self.previous?);
+        BUTTONS.into_iter()
+            .find(|button| state.get_button_state(*button) != Action::Release && previous.get_button_state(*button) == Action::Release)
+            .map(Input::Gamepad)
+            .or_else(|| [GamepadAxis::AxisLeftTrigger, GamepadAxis::AxisRightTrigger].into_iter()
+                .find(|axis| trigger(&state, *axis) > TRIGGER_THRESHOLD && trigger(&previous, *axis) <= TRIGGER_THRESHOLD)
+                .map(Input::Trigger))
+    }
+}
+
+/// Scales a stick so it reads zero inside `dead_zone` and reaches full tilt at the edge.
+/// Applied to the stick's length rather than each axis, so diagonals don't snap to the axes.
+pub fn stick_dead_zone(stick: Vec2, dead_zone: f32) -> Vec2 {
+    let length = stick.norm();
+    if length <= dead_zone {
+        return Vec2::zeros()
+    }
+    stick * (((length - dead_zone) / (1.0 - dead_zone)).min(1.0) / length)
+}
+
+pub fn trigger_dead_zone(value: f32, dead_zone: f32) -> f32 {
+    ((value - dead_zone) / (1.0 - dead_zone)).clamp(0.0, 1.0)
+}
+
+/// Raises a stick's tilt to `exponent` keeping its direction, above 1.0 gives finer control near the centre
+pub fn response_curve(stick: Vec2, exponent: f32) -> Vec2 {
+    let length = stick.norm();
+    if length == 0.0 {
+        return stick
+    }
+    stick * (length.min(1.0).powf(exponent) / length)
+}
+
+/// GLFW has down as positive on sticks, flipped here to match the mouse
+fn stick(state: &GamepadState, right: bool, dead_zone: f32) -> Vec2 {
+    let (x, y) = if right {
+        (GamepadAxis::AxisRightX, GamepadAxis::AxisRightY)
+    } else {
+        (GamepadAxis::AxisLeftX, GamepadAxis::AxisLeftY)
+    };
+    stick_dead_zone(Vec2::new(state.get_axis(x), -state.get_axis(y)), dead_zone)
+}
+
+/// GLFW reports triggers from -1.0 released to 1.0 pulled
+fn trigger(state: &GamepadState, axis: GamepadAxis) -> f32 {
+    state.get_axis(axis) * 0.5 + 0.5
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: Vec2, expected: Vec2) {
+        assert!((actual - expected).norm() < 1e-5, "{} != {}", actual, expected);
+    }
+
+    #[test]
+    fn stick_dead_zone_rescales_the_rest_of_the_range() {
+        assert_eq!(stick_dead_zone(Vec2::new(0.1, -0.1), 0.2), Vec2::zeros());
+        assert_eq!(stick_dead_zone(Vec2::new(0.2, 0.0), 0.2), Vec2::zeros());
+        assert_close(stick_dead_zone(Vec2::new(0.6, 0.0), 0.2), Vec2::new(0.5, 0.0));
+        assert_close(stick_dead_zone(Vec2::new(0.0, -0.6), 0.2), Vec2::new(0.0, -0.5));
+        assert_close(stick_dead_zone(Vec2::new(-1.0, 0.0), 0.2), Vec2::new(-1.0, 0.0));
+
+        // diagonals keep their direction and corners past the unit circle are capped
+        let diagonal = stick_dead_zone(Vec2::new(-0.5, 0.5), 0.2);
+        assert!((diagonal.x + diagonal.y).abs() < 1e-6 && diagonal.x < 0.0);
+        assert!((stick_dead_zone(Vec2::new(1.0, 1.0), 0.2).norm() - 1.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn trigger_dead_zone_rescales_the_rest_of_the_range() {
+        assert_eq!(trigger_dead_zone(0.0, 0.1), 0.0);
+        assert_eq!(trigger_dead_zone(0.1, 0.1), 0.0);
+        assert!((trigger_dead_zone(0.55, 0.1) - 0.5).abs() < 1e-6);
+        assert_eq!(trigger_dead_zone(1.0, 0.1), 1.0);
+        // values below the range clamp rather than going negative
+        assert_eq!(trigger_dead_zone(-1.0, 0.1), 0.0);
+    }
+
+    #[test]
+    fn response_curve_keeps_the_endpoints_and_direction() {
+        assert_eq!(response_curve(Vec2::zeros(), 2.0), Vec2::zeros());
+        assert_close(response_curve(Vec2::new(1.0, 0.0), 2.0), Vec2::new(1.0, 0.0));
+        assert_close(response_curve(Vec2::new(0.0, -1.0), 2.0), Vec2::new(0.0, -1.0));
+        assert_close(response_curve(Vec2::new(-0.5, 0.0), 2.0), Vec2::new(-0.25, 0.0));
+        assert_close(response_curve(Vec2::new(0.3, -0.4), 1.0), Vec2::new(0.3, -0.4));
+
+        let curved = response_curve(Vec2::new(0.3, -0.4), 2.0);
+        assert_close(curved, Vec2::new(0.3, -0.4) * 0.5);
+        assert!((response_curve(Vec2::new(2.0, 0.0), 3.0).norm() - 1.0).abs() < 1e-6);
+    }
+}
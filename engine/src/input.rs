@@ -0,0 +1,413 @@
+use std::collections::{HashMap, HashSet};
+
+use glfw::{Action, CursorMode, GamepadAxis, Key, MouseButton};
+use glm::Vec2;
+
+use crate::events::Event;
+use crate::gamepad::Gamepads;
+use crate::keybinds::{held_modifiers, Binding, Input, KeyMap};
+use crate::replay::InputFrame;
+use crate::window::Window;
+
+/// A value read from input each update rather than a button's on and off
+#[derive(Debug, Clone, PartialEq)]
+pub enum Axis {
+    /// -1.0 while the `negative` action's bindings are held, 1.0 for `positive`, 0.0 for both or neither
+    Actions { negative: String, positive: String },
+    /// Cursor movement in pixels since the last update while the cursor is captured, right is positive
+    MouseX,
+    /// Cursor movement in pixels since the last update while the cursor is captured, up is positive
+    MouseY,
+    /// Scroll wheel movement since the last update, away from the user is positive
+    Scroll,
+    /// A gamepad stick or trigger, see `Gamepads::axis`
+    Gamepad(GamepadAxis)
+}
+
+impl Axis {
+    pub fn actions(negative: &str, positive: &str) -> Self {
+        Axis::Actions { negative: negative.to_string(), positive: positive.to_string() }
+    }
+}
+
+/// Actions and axes that respond while the context is on the stack
+#[derive(Debug, Clone, Default)]
+pub struct InputContext {
+    pub actions: HashSet<String>,
+    /// Disables the contexts below this one, like a menu stopping gameplay
+    pub blocking: bool
+}
+
+impl InputContext {
+    pub fn new(actions: &[&str], blocking: bool) -> Self {
+        Self {
+            actions: actions.iter().map(|action| action.to_string()).collect(),
+            blocking
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ActionState {
+    pub down: bool,
+    /// Pressed since the last update
+    pub pressed: bool,
+    /// Released since the last update
+    pub released: bool
+}
+
+/// Turns window events and gamepads into the state of named actions from a `KeyMap` and of axes.
+/// Actions and axes only respond while a context listing them is active, contexts are
+/// stacked and a blocking context disables everything below it, so opening a menu or
+/// typing into a text field stops the player moving.
+///
+/// Edges come from the bindings themselves, so an action enabled while its key is
+/// already held isn't pressed until the key is pressed again.
+#[derive(Debug, Default)]
+pub struct InputManager {
+    contexts: HashMap<String, InputContext>,
+    stack: Vec<String>,
+    /// Every source of each axis
+    axes: HashMap<String, Vec<Axis>>,
+    actions: HashMap<String, ActionState>,
+    /// Whether each action's bindings were held at the last update, regardless of context
+    held: HashMap<String, bool>,
+    keys: HashSet<Key>,
+    buttons: HashSet<MouseButton>,
+    /// Pressed since the last update, so taps shorter than a frame still count
+    tapped: HashSet<Input>,
+    mouse_delta: Vec2,
+    scroll: f32,
+    gamepads: Gamepads,
+    /// Axis values from the frame being replayed, until the next `update`
+    replayed_axes: Option<HashMap<String, f32>>
+}
+
+impl InputManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_context(&mut self, name: &str, context: InputContext) {
+        self.contexts.insert(name.to_string(), context);
+    }
+
+    /// Adds a source to an axis, an axis with several sources reads as their sum,
+    /// like the movement keys plus a gamepad stick
+    pub fn add_axis(&mut self, name: &str, axis: Axis) {
+        self.axes.entry(name.to_string()).or_default().push(axis);
+    }
+
+    pub fn gamepads(&self) -> &Gamepads {
+        &self.gamepads
+    }
+
+    pub fn gamepads_mut(&mut self) -> &mut Gamepads {
+        &mut self.gamepads
+    }
+
+    /// Makes a context the top of the stack, moving it there if it's already active
+    pub fn push_context(&mut self, name: &str) {
+        self.stack.retain(|active| active != name);
+        self.stack.push(name.to_string());
+    }
+
+    pub fn pop_context(&mut self) -> Option<String> {
+        self.stack.pop()
+    }
+
+    /// Takes a context off the stack wherever it is
+    pub fn remove_context(&mut self, name: &str) {
+        self.stack.retain(|active| active != name);
+    }
+
+    /// Pushes the context if it's inactive or removes it, for contexts that follow some state like a menu being open
+    pub fn set_context(&mut self, name: &str, active: bool) {
+        if active && !self.is_context_active(name) {
+            self.push_context(name);
+        } else if !active {
+            self.remove_context(name);
+        }
+    }
+
+    pub fn is_context_active(&self, name: &str) -> bool {
+        self.stack.iter().any(|active| active == name)
+    }
+
+    /// Active contexts, bottom first
+    pub fn context_stack(&self) -> &[String] {
+        &self.stack
+    }
+
+    /// Whether an action or axis is listed by a context that isn't blocked
+    pub fn is_enabled(&self, name: &str) -> bool {
+        for context in self.stack.iter().rev().filter_map(|active| self.contexts.get(active)) {
+            if context.actions.contains(name) {
+                return true
+            }
+            if context.blocking {
+                return false
+            }
+        }
+        false
+    }
+
+    /// Polls the gamepads and reads the window's events from its last `process_events`,
+    /// call once per frame after it
+    pub fn update_from_window(&mut self, window: &Window, keymap: &KeyMap) {
+        self.gamepads.update(&window.context);
+        let mouse_delta = if window.get_cursor_mode() == CursorMode::Disabled {
+            let delta = window.cursor_delta();
+            Vec2::new(delta.x, -delta.y)
+        } else {
+            Vec2::zeros()
+        };
+        self.update(window.events(), mouse_delta, keymap);
+    }
+
+    /// Works out this update's action states from the events since the last one. `mouse_delta`
+    /// is the captured cursor's movement with up positive, zero while the cursor is free.
+    /// Gamepads are read as of their last `Gamepads::update`.
+    pub fn update(&mut self, events: &[Event], mouse_delta: Vec2, keymap: &KeyMap) {
+        self.replayed_axes = None;
+        self.tapped.clear();
+        self.mouse_delta = mouse_delta;
+        self.scroll = 0.0;
+        for event in events {
+            match *event {
+                Event::Key { key, action: Action::Press, .. } => {
+                    self.keys.insert(key);
+                    self.tapped.insert(Input::Key(key));
+                },
+                Event::Key { key, action: Action::Release, .. } => {
+                    self.keys.remove(&key);
+                },
+                Event::MouseButton { button, action: Action::Press, .. } => {
+                    self.buttons.insert(button);
+                    self.tapped.insert(Input::Mouse(button));
+                },
+                Event::MouseButton { button, action: Action::Release, .. } => {
+                    self.buttons.remove(&button);
+                },
+                Event::Scroll(scroll) => {
+                    self.scroll += scroll.y;
+                },
+                // releases while unfocused go to another window
+                Event::Focus(false) => {
+                    self.keys.clear();
+                    self.buttons.clear();
+                },
+                _ => {}
+            }
+        }
+
+        let mut actions = HashMap::new();
+        let mut held = HashMap::new();
+        for (action, bindings) in keymap.actions() {
+            let is_held = bindings.iter().any(|binding| self.is_held(binding));
+            let tapped = bindings.iter().any(|binding| self.tapped.contains(&binding.input) && self.has_modifiers(binding));
+            let was_held = self.held.get(action).copied().unwrap_or(false);
+            let enabled = self.is_enabled(action);
+            actions.insert(action.to_string(), ActionState {
+                down: enabled && is_held,
+                pressed: enabled && (tapped || (is_held && !was_held)),
+                released: enabled && !is_held && (was_held || tapped)
+            });
+            held.insert(action.to_string(), is_held);
+        }
+        self.actions = actions;
+        self.held = held;
+    }
+
+    /// This update's action states and axis values, for recording
+    pub fn frame(&self, delta_time: f32) -> InputFrame {
+        let named = |filter: fn(&ActionState) -> bool| {
+            let mut actions: Vec<String> = self.actions.iter()
+                .filter(|(_, state)| filter(state))
+                .map(|(action, _)| action.clone())
+                .collect();
+            actions.sort();
+            actions
+        };
+        InputFrame {
+            delta_time,
+            down: named(|state| state.down),
+            pressed: named(|state| state.pressed),
+            released: named(|state| state.released),
+            axes: self.axes.keys()
+                .map(|name| (name.clone(), self.axis(name)))
+                .filter(|(_, value)| *value != 0.0)
+                .collect()
+        }
+    }
+
+    /// Takes a recorded frame's input in place of `update`, the window is left unread
+    pub fn replay(&mut self, frame: &InputFrame) {
+        let mut actions: HashMap<String, ActionState> = HashMap::new();
+        for action in &frame.down {
+            actions.entry(action.clone()).or_default().down = true;
+        }
+        for action in &frame.pressed {
+            actions.entry(action.clone()).or_default().pressed = true;
+        }
+        for action in &frame.released {
+            actions.entry(action.clone()).or_default().released = true;
+        }
+        self.held = frame.down.iter().map(|action| (action.clone(), true)).collect();
+        self.actions = actions;
+        self.replayed_axes = Some(frame.axes.iter().map(|(name, value)| (name.clone(), *value)).collect());
+    }
+
+    pub fn state(&self, action: &str) -> ActionState {
+        self.actions.get(action).copied().unwrap_or_default()
+    }
+
+    pub fn is_down(&self, action: &str) -> bool {
+        self.state(action).down
+    }
+
+    pub fn just_pressed(&self, action: &str) -> bool {
+        self.state(action).pressed
+    }
+
+    pub fn just_released(&self, action: &str) -> bool {
+        self.state(action).released
+    }
+
+    /// The axis' value, 0.0 while it's disabled
+    pub fn axis(&self, name: &str) -> f32 {
+        if let Some(replayed) = &self.replayed_axes {
+            return replayed.get(name).copied().unwrap_or(0.0)
+        }
+        if !self.is_enabled(name) {
+            return 0.0
+        }
+        let held = |action: &String| if self.held.get(action).copied().unwrap_or(false) { 1.0 } else { 0.0 };
+        self.axes.get(name).map_or(&[][..], Vec::as_slice).iter().map(|axis| match axis {
+            Axis::Actions { negative, positive } => held(positive) - held(negative),
+            Axis::MouseX => self.mouse_delta.x,
+            Axis::MouseY => self.mouse_delta.y,
+            Axis::Scroll => self.scroll,
+            Axis::Gamepad(axis) => self.gamepads.axis(*axis)
+        }).sum()
+    }
+
+    /// Two axes together, like movement or mouse look
+    pub fn vector(&self, x: &str, y: &str) -> Vec2 {
+        Vec2::new(self.axis(x), self.axis(y))
+    }
+
+    /// Whether the input is held along with at least the binding's modifiers,
+    /// extra modifiers are allowed so sprinting with Ctrl doesn't block movement
+    fn is_held(&self, binding: &Binding) -> bool {
+        let input_held = match binding.input {
+            Input::Key(key) => self.keys.contains(&key),
+            Input::Mouse(button) => self.buttons.contains(&button),
+            Input::Gamepad(button) => self.gamepads.is_down(button),
+            Input::Trigger(axis) => self.gamepads.is_trigger_down(axis)
+        };
+        input_held && self.has_modifiers(binding)
+    }
+
+    fn has_modifiers(&self, binding: &Binding) -> bool {
+        held_modifiers(|key| self.keys.contains(&key)).contains(binding.modifiers)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glfw::Modifiers;
+
+    use super::*;
+
+    fn key(key: Key, action: Action) -> Event {
+        Event::Key { key, action, modifiers: Modifiers::empty() }
+    }
+
+    fn keymap() -> KeyMap {
+        let mut keymap = KeyMap::new();
+        keymap.bind("jump", Binding::key(Key::Space)).unwrap();
+        keymap.bind("move_forward", Binding::key(Key::W)).unwrap();
+        keymap.bind("close_menu", Binding::key(Key::Escape)).unwrap();
+        keymap
+    }
+
+    fn input() -> InputManager {
+        let mut input = InputManager::new();
+        input.add_context("game", InputContext::new(&["jump", "move_forward", "look_x"], false));
+        input.add_context("menu", InputContext::new(&["close_menu"], true));
+        input.add_context("overlay", InputContext::new(&["jump"], false));
+        input.add_axis("look_x", Axis::MouseX);
+        input.push_context("game");
+        input
+    }
+
+    #[test]
+    fn presses_and_releases_last_one_update() {
+        let (keymap, mut input) = (keymap(), input());
+        input.update(&[key(Key::Space, Action::Press)], Vec2::zeros(), &keymap);
+        assert_eq!(input.state("jump"), ActionState { down: true, pressed: true, released: false });
+
+        input.update(&[], Vec2::zeros(), &keymap);
+        assert_eq!(input.state("jump"), ActionState { down: true, pressed: false, released: false });
+
+        input.update(&[key(Key::Space, Action::Release)], Vec2::zeros(), &keymap);
+        assert_eq!(input.state("jump"), ActionState { down: false, pressed: false, released: true });
+
+        input.update(&[], Vec2::zeros(), &keymap);
+        assert_eq!(input.state("jump"), ActionState::default());
+    }
+
+    #[test]
+    fn taps_within_one_update_are_pressed_and_released() {
+        let (keymap, mut input) = (keymap(), input());
+        input.update(&[key(Key::Space, Action::Press), key(Key::Space, Action::Release)], Vec2::zeros(), &keymap);
+        assert_eq!(input.state("jump"), ActionState { down: false, pressed: true, released: true });
+
+        input.update(&[], Vec2::zeros(), &keymap);
+        assert_eq!(input.state("jump"), ActionState::default());
+    }
+
+    #[test]
+    fn blocking_contexts_disable_the_ones_below() {
+        let (keymap, mut input) = (keymap(), input());
+        input.push_context("menu");
+        input.update(&[key(Key::W, Action::Press), key(Key::Escape, Action::Press)], Vec2::new(5.0, 0.0), &keymap);
+        assert!(!input.is_down("move_forward"));
+        assert!(input.just_pressed("close_menu"));
+        assert_eq!(input.axis("look_x"), 0.0);
+
+        // the key was held through the menu, so closing it doesn't press it
+        input.remove_context("menu");
+        input.update(&[], Vec2::new(5.0, 0.0), &keymap);
+        assert!(input.is_down("move_forward"));
+        assert!(!input.just_pressed("move_forward"));
+        assert_eq!(input.axis("look_x"), 5.0);
+    }
+
+    #[test]
+    fn contexts_above_a_blocking_one_stay_enabled() {
+        let (keymap, mut input) = (keymap(), input());
+        input.push_context("menu");
+        input.push_context("overlay");
+        assert_eq!(input.context_stack(), ["game", "menu", "overlay"]);
+        input.update(&[key(Key::Space, Action::Press), key(Key::W, Action::Press)], Vec2::zeros(), &keymap);
+        assert!(input.just_pressed("jump"));
+        assert!(!input.is_down("move_forward"));
+
+        // pushing an active context moves it to the top
+        input.push_context("menu");
+        assert_eq!(input.context_stack(), ["game", "overlay", "menu"]);
+        input.update(&[], Vec2::zeros(), &keymap);
+        assert!(!input.is_down("jump"));
+        assert!(!input.is_down("move_forward"));
+    }
+
+    #[test]
+    fn losing_focus_releases_everything() {
+        let (keymap, mut input) = (keymap(), input());
+        input.update(&[key(Key::W, Action::Press)], Vec2::zeros(), &keymap);
+        input.update(&[Event::Focus(false)], Vec2::zeros(), &keymap);
+        assert_eq!(input.state("move_forward"), ActionState { down: false, pressed: false, released: true });
+    }
+}
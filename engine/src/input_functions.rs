@@ -1,50 +1,66 @@
-use super::{keybinds::InputFunctionArguments, camera::CameraMovement};
+use glfw::Action;
 
-pub fn set_window_should_close(args: InputFunctionArguments) {
-    args.window.unwrap().set_should_close(true)
+use super::{camera::{Camera, CameraMovement}, window::Window};
+
+/// State with a camera, for binding the camera functions on
+pub trait CameraContext {
+    fn camera(&mut self) -> &mut Camera;
 }
 
-pub fn camera_forward(args: InputFunctionArguments) {
-    args.camera.unwrap().process_action_input(CameraMovement::Forward, args.delta_time.unwrap())
+/// State with a window, for binding the window functions on
+pub trait WindowContext {
+    fn window(&mut self) -> &mut Window;
 }
 
-pub fn camera_backward(args: InputFunctionArguments) {
-    args.camera.unwrap().process_action_input(CameraMovement::Backward, args.delta_time.unwrap())
+/// State that knows how long the current frame is, for functions that move things
+pub trait TimeContext {
+    fn delta_time(&self) -> f32;
 }
 
-pub fn camera_left(args: InputFunctionArguments) {
-    args.camera.unwrap().process_action_input(CameraMovement::Left, args.delta_time.unwrap())
+pub fn set_window_should_close<C: WindowContext>(context: &mut C, _action: Action) {
+    context.window().set_should_close(true)
 }
 
-pub fn camera_right(args: InputFunctionArguments) {
-    args.camera.unwrap().process_action_input(CameraMovement::Right, args.delta_time.unwrap())
+pub fn camera_forward<C: CameraContext + TimeContext>(context: &mut C, _action: Action) {
+    move_camera(context, CameraMovement::Forward)
 }
 
-pub fn camera_up(args: InputFunctionArguments) {
-    args.camera.unwrap().process_action_input(CameraMovement::Up, args.delta_time.unwrap())
+pub fn camera_backward<C: CameraContext + TimeContext>(context: &mut C, _action: Action) {
+    move_camera(context, CameraMovement::Backward)
 }
 
-pub fn camera_down(args: InputFunctionArguments) {
-    args.camera.unwrap().process_action_input(CameraMovement::Down, args.delta_time.unwrap())
+pub fn camera_left<C: CameraContext + TimeContext>(context: &mut C, _action: Action) {
+    move_camera(context, CameraMovement::Left)
 }
 
-pub fn toggle_cursor_mode(args: InputFunctionArguments) {
-    
-    args.window.unwrap().set_cursor_mode(if args.action.unwrap() == glfw::Action::Press {glfw::CursorMode::Normal} else {glfw::CursorMode::Disabled})
+pub fn camera_right<C: CameraContext + TimeContext>(context: &mut C, _action: Action) {
+    move_camera(context, CameraMovement::Right)
 }
 
-pub fn print_camera_pos(args: InputFunctionArguments) {
-    if args.action.unwrap() == glfw::Action::Press {
-        println!("{:?}", args.camera.unwrap().position)
-    }
+pub fn camera_up<C: CameraContext + TimeContext>(context: &mut C, _action: Action) {
+    move_camera(context, CameraMovement::Up)
+}
+
+pub fn camera_down<C: CameraContext + TimeContext>(context: &mut C, _action: Action) {
+    move_camera(context, CameraMovement::Down)
 }
 
-pub fn increase_movement_speed(args: InputFunctionArguments) {
-    args.camera.unwrap().movement_speed = if args.action.unwrap() == glfw::Action::Press {10.0} else {5.0};
+pub fn toggle_cursor_mode<C: WindowContext>(context: &mut C, action: Action) {
+    context.window().set_cursor_mode(if action == Action::Press {glfw::CursorMode::Normal} else {glfw::CursorMode::Disabled})
 }
 
-pub fn toggle_fullscreen(args: InputFunctionArguments) {
-    if args.action.unwrap() == glfw::Action::Press {
-        args.window.unwrap().toggle_fullscreen()
+pub fn increase_movement_speed<C: CameraContext>(context: &mut C, action: Action) {
+    let camera = context.camera();
+    camera.movement_speed = if action == Action::Press {camera.sprint_speed} else {camera.walk_speed};
+}
+
+pub fn toggle_fullscreen<C: WindowContext>(context: &mut C, action: Action) {
+    if action == Action::Press {
+        context.window().toggle_fullscreen()
     }
 }
+
+fn move_camera<C: CameraContext + TimeContext>(context: &mut C, direction: CameraMovement) {
+    let delta_time = context.delta_time();
+    context.camera().process_action_input(direction, &delta_time)
+}
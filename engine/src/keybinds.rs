@@ -1,91 +1,452 @@
-use glfw::{Key, Action};
-use super::window::Window;
+use std::{collections::BTreeMap, fmt, str::FromStr};
 
-use super::camera::Camera;
+use glfw::{Key, Action, GamepadAxis, GamepadButton, Modifiers, MouseButton};
+use serde::{ser::Error as _, Deserialize, Serialize, Serializer};
+use thiserror::Error;
 
-pub struct KeyBinding {
-    pub key: Key,
+use super::events::Event;
+use super::input::InputManager;
+
+type Callback<C> = Box<dyn FnMut(&mut C, Action)>;
+
+/// Runs a callback on the state `C` it's bound to when an action goes down and up, or every
+/// update it's held. Callbacks only get what `C` offers, so binding a function that needs a
+/// camera on state without one doesn't compile.
+pub struct KeyBinding<C> {
+    pub action: String,
+    /// Whether the action was down at the last update
     pub state: bool,
-    pub callback: fn(InputFunctionArguments),
+    callback: Callback<C>,
     run_every_frame: bool
 }
 
-impl KeyBinding {
-    pub fn new(key: Key, run_every_frame: bool, callback: fn(InputFunctionArguments)) -> KeyBinding {
-        KeyBinding {
-            key,
+impl<C> KeyBinding<C> {
+    pub fn new(action: &str, run_every_frame: bool, callback: impl FnMut(&mut C, Action) + 'static) -> Self {
+        Self {
+            action: action.to_string(),
             state: false,
-            callback,
+            callback: Box::new(callback),
             run_every_frame
         }
     }
 
-    pub fn update(&mut self, action: Action, args: InputFunctionArguments) {
-            if self.run_every_frame {
-                if action == Action::Press {
-                    (self.callback)(args)
-                }
-            } else {
-                match action {
-                    Action::Press if !self.state => {
-                        self.state = true;
-                        (self.callback)(args);
-                    },
-                    Action::Release if self.state => {
-                        self.state = false;
-                        (self.callback)(args);
-                    },
-                    Action::Release => {
-                        self.state = false;
-                    },
-                    _ => {}
-                }
+    /// Runs the callback with `Press` when the action goes down and `Release` when it goes up,
+    /// including when its context is disabled, or with `Press` every update while it's down
+    pub fn update(&mut self, input: &InputManager, context: &mut C) {
+        let down = input.is_down(&self.action);
+        if self.run_every_frame {
+            if down {
+                (self.callback)(context, Action::Press)
+            }
+        } else if down != self.state {
+            (self.callback)(context, if down { Action::Press } else { Action::Release });
+        }
+        self.state = down;
+    }
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum BindingError {
+    #[error("Unknown key, mouse or gamepad button \"{0}\"")]
+    UnknownInput(String),
+    #[error("Unknown modifier \"{0}\", expected Ctrl, Shift, Alt or Super")]
+    UnknownModifier(String),
+    #[error("{0:?} has no name, so it can't be bound")]
+    Unbindable(Input),
+    #[error("{binding} is already bound to {action}")]
+    Conflict { binding: Binding, action: String }
+}
+
+macro_rules! key_names {
+    ($($key:ident),* $(,)?) => {
+        /// Names used for keys in config files, the same as the `Key` variants
+        const KEY_NAMES: &[(Key, &str)] = &[$((Key::$key, stringify!($key))),*];
+    };
+}
+
+key_names!(
+    Space, Apostrophe, Comma, Minus, Period, Slash,
+    Num0, Num1, Num2, Num3, Num4, Num5, Num6, Num7, Num8, Num9,
+    Semicolon, Equal,
+    A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R, S, T, U, V, W, X, Y, Z,
+    LeftBracket, Backslash, RightBracket, GraveAccent, World1, World2,
+    Escape, Enter, Tab, Backspace, Insert, Delete, Right, Left, Down, Up,
+    PageUp, PageDown, Home, End, CapsLock, ScrollLock, NumLock, PrintScreen, Pause,
+    F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11, F12, F13, F14, F15, F16, F17, F18, F19, F20,
+    F21, F22, F23, F24, F25,
+    Kp0, Kp1, Kp2, Kp3, Kp4, Kp5, Kp6, Kp7, Kp8, Kp9,
+    KpDecimal, KpDivide, KpMultiply, KpSubtract, KpAdd, KpEnter, KpEqual,
+    LeftShift, LeftControl, LeftAlt, LeftSuper, RightShift, RightControl, RightAlt, RightSuper,
+    Menu
+);
+
+const MOUSE_NAMES: &[(MouseButton, &str)] = &[
+    (MouseButton::Button1, "MouseLeft"),
+    (MouseButton::Button2, "MouseRight"),
+    (MouseButton::Button3, "MouseMiddle"),
+    (MouseButton::Button4, "Mouse4"),
+    (MouseButton::Button5, "Mouse5"),
+    (MouseButton::Button6, "Mouse6"),
+    (MouseButton::Button7, "Mouse7"),
+    (MouseButton::Button8, "Mouse8")
+];
+
+const GAMEPAD_NAMES: &[(GamepadButton, &str)] = &[
+    (GamepadButton::ButtonA, "PadA"),
+    (GamepadButton::ButtonB, "PadB"),
+    (GamepadButton::ButtonX, "PadX"),
+    (GamepadButton::ButtonY, "PadY"),
+    (GamepadButton::ButtonLeftBumper, "PadLB"),
+    (GamepadButton::ButtonRightBumper, "PadRB"),
+    (GamepadButton::ButtonBack, "PadBack"),
+    (GamepadButton::ButtonStart, "PadStart"),
+    (GamepadButton::ButtonGuide, "PadGuide"),
+    (GamepadButton::ButtonLeftThumb, "PadLS"),
+    (GamepadButton::ButtonRightThumb, "PadRS"),
+    (GamepadButton::ButtonDpadUp, "PadUp"),
+    (GamepadButton::ButtonDpadRight, "PadRight"),
+    (GamepadButton::ButtonDpadDown, "PadDown"),
+    (GamepadButton::ButtonDpadLeft, "PadLeft")
+];
+
+const TRIGGER_NAMES: &[(GamepadAxis, &str)] = &[
+    (GamepadAxis::AxisLeftTrigger, "PadLT"),
+    (GamepadAxis::AxisRightTrigger, "PadRT")
+];
+
+/// Modifiers a binding can require, in the order they're written
+const MODIFIER_NAMES: &[(Modifiers, &str)] = &[
+    (Modifiers::Control, "Ctrl"),
+    (Modifiers::Shift, "Shift"),
+    (Modifiers::Alt, "Alt"),
+    (Modifiers::Super, "Super")
+];
+
+/// A physical key, mouse button or gamepad button
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Input {
+    Key(Key),
+    Mouse(MouseButton),
+    Gamepad(GamepadButton),
+    /// A trigger pulled past `gamepad::TRIGGER_THRESHOLD`
+    Trigger(GamepadAxis)
+}
+
+impl Input {
+    /// The name used in config files, `None` for `Key::Unknown`, which GLFW reports
+    /// for every key it has no name for, so binding it would bind all of them
+    pub fn name(&self) -> Option<&'static str> {
+        match self {
+            Input::Key(key) => KEY_NAMES.iter().find(|(k, _)| k == key).map(|(_, name)| *name),
+            Input::Mouse(button) => MOUSE_NAMES.iter().find(|(b, _)| b == button).map(|(_, name)| *name),
+            Input::Gamepad(button) => GAMEPAD_NAMES.iter().find(|(b, _)| b == button).map(|(_, name)| *name),
+            Input::Trigger(axis) => TRIGGER_NAMES.iter().find(|(a, _)| a == axis).map(|(_, name)| *name)
+        }
+    }
+
+    /// The modifier a key counts as, so holding Ctrl doesn't also require Ctrl
+    fn own_modifier(&self) -> Modifiers {
+        match *self {
+            Input::Key(Key::LeftShift | Key::RightShift) => Modifiers::Shift,
+            Input::Key(Key::LeftControl | Key::RightControl) => Modifiers::Control,
+            Input::Key(Key::LeftAlt | Key::RightAlt) => Modifiers::Alt,
+            Input::Key(Key::LeftSuper | Key::RightSuper) => Modifiers::Super,
+            _ => Modifiers::empty()
+        }
+    }
+}
+
+impl fmt::Display for Input {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // "?" can't be mistaken for a name that parses
+        f.write_str(self.name().unwrap_or("?"))
+    }
+}
+
+impl FromStr for Input {
+    type Err = BindingError;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        let name = name.trim();
+        KEY_NAMES.iter()
+            .find(|(_, key_name)| key_name.eq_ignore_ascii_case(name))
+            .map(|(key, _)| Input::Key(*key))
+            .or_else(|| MOUSE_NAMES.iter()
+                .find(|(_, button_name)| button_name.eq_ignore_ascii_case(name))
+                .map(|(button, _)| Input::Mouse(*button)))
+            .or_else(|| GAMEPAD_NAMES.iter()
+                .find(|(_, button_name)| button_name.eq_ignore_ascii_case(name))
+                .map(|(button, _)| Input::Gamepad(*button)))
+            .or_else(|| TRIGGER_NAMES.iter()
+                .find(|(_, trigger_name)| trigger_name.eq_ignore_ascii_case(name))
+                .map(|(axis, _)| Input::Trigger(*axis)))
+            .ok_or_else(|| BindingError::UnknownInput(name.to_string()))
+    }
+}
+
+/// A key, mouse or gamepad button along with modifiers that have to be held with it,
+/// written like `"Ctrl+Shift+S"`, `"MouseLeft"` or `"PadA"` in config files
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Binding {
+    pub input: Input,
+    pub modifiers: Modifiers
+}
+
+impl Binding {
+    pub const fn key(key: Key) -> Self {
+        Self { input: Input::Key(key), modifiers: Modifiers::empty() }
+    }
+
+    pub const fn mouse(button: MouseButton) -> Self {
+        Self { input: Input::Mouse(button), modifiers: Modifiers::empty() }
+    }
+
+    pub const fn gamepad(button: GamepadButton) -> Self {
+        Self { input: Input::Gamepad(button), modifiers: Modifiers::empty() }
+    }
+
+    /// `axis` should be `AxisLeftTrigger` or `AxisRightTrigger`
+    pub const fn trigger(axis: GamepadAxis) -> Self {
+        Self { input: Input::Trigger(axis), modifiers: Modifiers::empty() }
+    }
+
+    pub fn with(self, modifiers: Modifiers) -> Self {
+        Self { modifiers: (self.modifiers | modifiers) - self.input.own_modifier(), ..self }
+    }
+
+    /// Whether pressing `self` also triggers `other`. Extra held modifiers don't stop a
+    /// binding, so `Ctrl+S` triggers `S` as well.
+    pub fn triggers(&self, other: &Binding) -> bool {
+        self.input == other.input && self.modifiers.contains(other.modifiers)
+    }
+
+    /// Whether either binding triggers the other
+    pub fn overlaps(&self, other: &Binding) -> bool {
+        self.triggers(other) || other.triggers(self)
+    }
+
+    /// The binding a key or mouse button press would make, for capturing new bindings.
+    /// `None` for keys without a name.
+    pub fn from_event(event: &Event) -> Option<Self> {
+        match *event {
+            Event::Key { key, action: Action::Press, modifiers } => Some(Self::key(key).with(modifiers & all_modifiers())),
+            Event::MouseButton { button, action: Action::Press, modifiers } => Some(Self::mouse(button).with(modifiers & all_modifiers())),
+            _ => None
+        }.filter(|binding| binding.input.name().is_some())
+    }
+}
+
+impl From<Input> for Binding {
+    fn from(input: Input) -> Self {
+        Self { input, modifiers: Modifiers::empty() }
+    }
+}
+
+impl fmt::Display for Binding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (modifier, name) in MODIFIER_NAMES {
+            if self.modifiers.contains(*modifier) {
+                write!(f, "{}+", name)?;
             }
+        }
+        write!(f, "{}", self.input)
+    }
+}
+
+impl FromStr for Binding {
+    type Err = BindingError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        // the last part is the input so "Shift" on its own is the key
+        let mut parts = text.rsplit('+');
+        let input = parts.next().unwrap_or_default().parse::<Input>()?;
+        let mut modifiers = Modifiers::empty();
+        for part in parts {
+            let part = part.trim();
+            let alias = if part.eq_ignore_ascii_case("Control") { "Ctrl" } else { part };
+            let (modifier, _) = MODIFIER_NAMES.iter()
+                .find(|(_, name)| name.eq_ignore_ascii_case(alias))
+                .ok_or_else(|| BindingError::UnknownModifier(part.to_string()))?;
+            modifiers |= *modifier;
+        }
+        Ok(Self { input, modifiers: Modifiers::empty() }.with(modifiers))
     }
+}
 
+impl TryFrom<String> for Binding {
+    type Error = BindingError;
+
+    fn try_from(text: String) -> Result<Self, Self::Error> {
+        text.parse()
+    }
 }
-pub struct InputFunctionArguments<'a> {
-    pub window: Option<&'a mut Window>,
-    pub camera: Option<&'a mut Camera>,
-    pub delta_time: Option<&'a f32>,
-    pub action: Option<Action>,
+
+impl Serialize for Binding {
+    /// Fails for inputs without a name rather than writing one that wouldn't load
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if self.input.name().is_none() {
+            return Err(S::Error::custom(BindingError::Unbindable(self.input)))
+        }
+        serializer.collect_str(self)
+    }
 }
 
-impl<'a> InputFunctionArguments<'a> {
+fn all_modifiers() -> Modifiers {
+    Modifiers::Shift | Modifiers::Control | Modifiers::Alt | Modifiers::Super
+}
+
+/// Modifiers whose left or right key is held according to `key_down`
+pub(crate) fn held_modifiers(key_down: impl Fn(Key) -> bool) -> Modifiers {
+    [
+        (Key::LeftShift, Key::RightShift, Modifiers::Shift),
+        (Key::LeftControl, Key::RightControl, Modifiers::Control),
+        (Key::LeftAlt, Key::RightAlt, Modifiers::Alt),
+        (Key::LeftSuper, Key::RightSuper, Modifiers::Super)
+    ].into_iter()
+        .filter(|(left, right, _)| key_down(*left) || key_down(*right))
+        .fold(Modifiers::empty(), |held, (_, _, modifier)| held | modifier)
+}
+
+/// Named actions, like `"move_forward"` or `"toggle_fullscreen"`, and the bindings that trigger them.
+/// Saved as a table of action names to lists of bindings. A binding can only
+/// trigger one action, `bind` refuses bindings that overlap one another action
+/// already has, like `Ctrl+S` when `S` is taken.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct KeyMap {
+    actions: BTreeMap<String, Vec<Binding>>
+}
+
+impl KeyMap {
     pub fn new() -> Self {
-        Self {
-            window: None,
-            camera: None,
-            delta_time: None,
-            action: None
+        Self::default()
+    }
+
+    /// Adds the default bindings of actions that aren't in the map yet,
+    /// so actions added since a config file was saved still get bound
+    pub fn add_defaults(&mut self, defaults: &[(&str, &[Binding])]) {
+        for (action, bindings) in defaults {
+            if !self.actions.contains_key(*action) {
+                let free = bindings.iter().copied().filter(|binding| self.overlapping(binding, action).is_none()).collect();
+                self.actions.insert(action.to_string(), free);
+            }
         }
     }
 
-    pub fn window(self, window: &'a mut Window) -> Self {
-        Self {
-            window: Some(window),
-            ..self
+    pub fn actions(&self) -> impl Iterator<Item = (&str, &[Binding])> {
+        self.actions.iter().map(|(action, bindings)| (action.as_str(), bindings.as_slice()))
+    }
+
+    pub fn bindings(&self, action: &str) -> &[Binding] {
+        self.actions.get(action).map_or(&[], Vec::as_slice)
+    }
+
+    /// The action a binding triggers, if any
+    pub fn action_for(&self, binding: &Binding) -> Option<&str> {
+        self.actions.iter()
+            .find(|(_, bindings)| bindings.contains(binding))
+            .map(|(action, _)| action.as_str())
+    }
+
+    /// An action other than `action` with a binding that overlaps `binding`
+    fn overlapping(&self, binding: &Binding, action: &str) -> Option<&str> {
+        self.actions.iter()
+            .find(|(other, bindings)| *other != action && bindings.iter().any(|other| other.overlaps(binding)))
+            .map(|(other, _)| other.as_str())
+    }
+
+    /// Adds a binding to an action, failing if it overlaps one another action uses
+    pub fn bind(&mut self, action: &str, binding: Binding) -> Result<(), BindingError> {
+        if binding.input.name().is_none() {
+            return Err(BindingError::Unbindable(binding.input))
+        }
+        if let Some(other) = self.overlapping(&binding, action) {
+            return Err(BindingError::Conflict { binding, action: other.to_string() })
         }
+        let bindings = self.actions.entry(action.to_string()).or_default();
+        if !bindings.contains(&binding) {
+            bindings.push(binding);
+        }
+        Ok(())
     }
 
-    pub fn camera(self, camera: &'a mut Camera) -> Self {
-        Self {
-            camera: Some(camera),
-            ..self
+    /// Replaces all of an action's bindings with one, failing if it overlaps one another action uses
+    pub fn rebind(&mut self, action: &str, binding: Binding) -> Result<(), BindingError> {
+        if binding.input.name().is_none() {
+            return Err(BindingError::Unbindable(binding.input))
+        }
+        if let Some(other) = self.overlapping(&binding, action) {
+            return Err(BindingError::Conflict { binding, action: other.to_string() })
         }
+        self.actions.insert(action.to_string(), vec![binding]);
+        Ok(())
     }
-    
-    pub fn delta_time(self, delta_time: &'a f32) -> Self {
-        Self {
-            delta_time: Some(delta_time),
-            ..self
+
+    /// Leaves the action in the map without any bindings
+    pub fn unbind(&mut self, action: &str) {
+        if let Some(bindings) = self.actions.get_mut(action) {
+            bindings.clear();
         }
     }
 
-    pub fn action(self, action: Action) -> Self {
-        Self {
-            action: Some(action),
-            ..self
+    /// Bindings that trigger more than one action, e.g. from an edited config file,
+    /// along with the actions they trigger. `S` and `Ctrl+S` on different actions
+    /// are reported as `Ctrl+S`, which triggers both.
+    pub fn conflicts(&self) -> Vec<(Binding, Vec<&str>)> {
+        let mut users: BTreeMap<String, (Binding, Vec<&str>)> = BTreeMap::new();
+        for binding in self.actions.values().flatten() {
+            let actions = self.actions.iter()
+                .filter(|(_, bindings)| bindings.iter().any(|other| binding.triggers(other)))
+                .map(|(action, _)| action.as_str())
+                .collect();
+            users.insert(binding.to_string(), (*binding, actions));
         }
-    } 
-}
\ No newline at end of file
+        users.into_values().filter(|(_, actions)| actions.len() > 1).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unknown_key_is_not_bindable() {
+        let event = Event::Key { key: Key::Unknown, action: Action::Press, modifiers: Modifiers::empty() };
+        assert_eq!(Binding::from_event(&event), None);
+
+        let binding = Binding::key(Key::Unknown);
+        let mut keymap = KeyMap::new();
+        assert_eq!(keymap.bind("jump", binding), Err(BindingError::Unbindable(binding.input)));
+        assert_eq!(keymap.rebind("jump", binding), Err(BindingError::Unbindable(binding.input)));
+        assert!(binding.to_string().parse::<Binding>().is_err());
+    }
+
+    #[test]
+    fn bindings_that_add_modifiers_conflict() {
+        let save = Binding::key(Key::S).with(Modifiers::Control);
+        let mut keymap = KeyMap::new();
+        keymap.bind("move_backward", Binding::key(Key::S)).unwrap();
+        assert_eq!(keymap.bind("save", save), Err(BindingError::Conflict { binding: save, action: "move_backward".to_string() }));
+        assert_eq!(keymap.rebind("save", save), Err(BindingError::Conflict { binding: save, action: "move_backward".to_string() }));
+        // the same action can have both
+        keymap.bind("move_backward", save).unwrap();
+        keymap.bind("save", Binding::key(Key::S).with(Modifiers::Shift).with(Modifiers::Alt)).unwrap_err();
+        keymap.bind("save", Binding::key(Key::D).with(Modifiers::Control)).unwrap();
+
+        let keymap: KeyMap = toml::from_str("move_backward = [\"S\"]\nsave = [\"Ctrl+S\"]\nquit = [\"Ctrl+Q\"]\n").unwrap();
+        assert_eq!(keymap.conflicts(), vec![(save, vec!["move_backward", "save"])]);
+    }
+
+    #[test]
+    fn named_bindings_round_trip() {
+        let mut keymap = KeyMap::new();
+        keymap.bind("save", Binding::key(Key::S).with(Modifiers::Control | Modifiers::Shift)).unwrap();
+        keymap.bind("attack", Binding::mouse(MouseButton::Button1)).unwrap();
+        keymap.bind("jump", Binding::gamepad(GamepadButton::ButtonA)).unwrap();
+        keymap.bind("aim", Binding::trigger(GamepadAxis::AxisLeftTrigger)).unwrap();
+
+        let saved = toml::to_string(&keymap).unwrap();
+        assert_eq!(toml::from_str::<KeyMap>(&saved).unwrap(), keymap);
+    }
+}
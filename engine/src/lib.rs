@@ -1,12 +1,28 @@
 pub mod texture;
 pub mod window;
+pub mod events;
 pub mod camera;
 pub mod renderer;
 pub mod keybinds;
+pub mod input;
+pub mod gamepad;
+pub mod replay;
 pub mod input_functions;
 pub mod buffer;
+pub mod vertex_array;
 pub mod shader;
+pub mod preprocessor;
 pub mod texture_atlas;
+pub mod particles;
+pub mod text;
+pub mod sprite_batch;
+pub mod ui;
+pub mod debug_draw;
+pub mod framebuffer;
+pub mod post_process;
+pub mod shadows;
+pub mod config;
+pub mod backend;
 
 pub extern crate nalgebra_glm as glm;
 pub extern crate glfw;
@@ -14,14 +30,7 @@ pub extern crate gl;
 pub extern crate nalgebra as na;
 pub extern crate image;
 
-
-pub struct Config {
-
-}
-
-impl Config {
-
-}
+pub use config::Config;
 
 //#[cfg(test)]
 //mod tests {
@@ -0,0 +1,384 @@
+use std::rc::Rc;
+
+use glm::{IVec3, Vec2, Vec3, Vec4};
+
+use crate::backend::Backend;
+use crate::buffer::Buffer;
+use crate::shader::{Shader, ShaderError, FRAME_UNIFORMS_BINDING, FRAME_UNIFORMS_GLSL};
+use crate::texture::Texture;
+use crate::texture_atlas::UvRect;
+use crate::vertex_array::VertexArray;
+
+/// How particles spawned by an emitter start out and behave
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParticleSettings {
+    pub velocity: Vec3,
+    /// Random velocity added on top of `velocity`, up to this much either way on each axis
+    pub velocity_spread: Vec3,
+    /// Random offset from the emitter position, up to this much either way on each axis
+    pub position_spread: Vec3,
+    /// Downwards acceleration in blocks per second squared
+    pub gravity: f32,
+    /// Fraction of velocity lost per second
+    pub drag: f32,
+    /// Lifetime in seconds, picked between min and max
+    pub lifetime: (f32, f32),
+    /// Width in blocks, picked between min and max
+    pub size: (f32, f32),
+    /// Multiplied with the texture colour
+    pub colour: Vec4,
+    pub uv: UvRect,
+    /// Fraction of `uv` each particle shows, taken from a random spot. 1.0 shows all of it.
+    pub uv_fraction: f32,
+    /// Stops particles at solid voxels, see `ParticleSystem::update`
+    pub collide: bool,
+    /// Fraction of velocity kept when hitting a voxel
+    pub bounce: f32
+}
+
+impl Default for ParticleSettings {
+    fn default() -> Self {
+        Self {
+            velocity: Vec3::zeros(),
+            velocity_spread: Vec3::new(1.0, 1.0, 1.0),
+            position_spread: Vec3::zeros(),
+            gravity: 9.8,
+            drag: 0.5,
+            lifetime: (1.0, 1.5),
+            size: (0.1, 0.15),
+            colour: Vec4::new(1.0, 1.0, 1.0, 1.0),
+            uv: UvRect::FULL,
+            uv_fraction: 1.0,
+            collide: true,
+            bounce: 0.3
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EmitterMode {
+    /// Spawns `count` particles at once, then the emitter is removed
+    Burst { count: u32 },
+    /// Spawns `rate` particles per second, for `duration` seconds or until removed
+    Continuous { rate: f32, duration: Option<f32> }
+}
+
+#[derive(Debug, Clone)]
+pub struct Emitter {
+    pub position: Vec3,
+    pub mode: EmitterMode,
+    pub settings: ParticleSettings,
+    elapsed: f32,
+    /// Fractional particles carried over between updates
+    pending: f32
+}
+
+impl Emitter {
+    pub fn burst(position: Vec3, count: u32, settings: ParticleSettings) -> Self {
+        Self::new(position, EmitterMode::Burst { count }, settings)
+    }
+
+    pub fn continuous(position: Vec3, rate: f32, settings: ParticleSettings) -> Self {
+        Self::new(position, EmitterMode::Continuous { rate, duration: None }, settings)
+    }
+
+    /// Stops a continuous emitter after `duration` seconds
+    pub fn with_duration(mut self, duration: f32) -> Self {
+        if let EmitterMode::Continuous { rate, .. } = self.mode {
+            self.mode = EmitterMode::Continuous { rate, duration: Some(duration) };
+        }
+        self
+    }
+
+    fn new(position: Vec3, mode: EmitterMode, settings: ParticleSettings) -> Self {
+        Self {
+            position,
+            mode,
+            settings,
+            elapsed: 0.0,
+            pending: 0.0
+        }
+    }
+
+    /// Number of particles to spawn this update and whether the emitter is finished
+    fn advance(&mut self, delta_time: f32) -> (u32, bool) {
+        match self.mode {
+            EmitterMode::Burst { count } => (count, true),
+            EmitterMode::Continuous { rate, duration } => {
+                let active_time = match duration {
+                    Some(duration) => delta_time.min(duration - self.elapsed).max(0.0),
+                    None => delta_time
+                };
+                self.elapsed += delta_time;
+                self.pending += rate * active_time;
+                let count = self.pending.floor();
+                self.pending -= count;
+                (count as u32, duration.is_some_and(|duration| self.elapsed >= duration))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct EmitterId(u64);
+
+#[derive(Debug, Clone)]
+struct Particle {
+    position: Vec3,
+    velocity: Vec3,
+    age: f32,
+    lifetime: f32,
+    size: f32,
+    colour: Vec4,
+    uv: UvRect,
+    gravity: f32,
+    drag: f32,
+    collide: bool,
+    bounce: f32
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct ParticleInstance {
+    position_size: [f32; 4],
+    colour: [f32; 4],
+    uv: [f32; 4]
+}
+crate::impl_vertex!(ParticleInstance { 0 => position_size, 1 => colour, 2 => uv });
+
+/// Small xorshift generator, particles only need cheap noise
+#[derive(Debug, Clone)]
+struct Rng(u64);
+
+impl Rng {
+    fn next_f32(&mut self) -> f32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    fn range(&mut self, (min, max): (f32, f32)) -> f32 {
+        min + (max - min) * self.next_f32()
+    }
+
+    /// Up to `spread` either way on each axis
+    fn spread(&mut self, spread: &Vec3) -> Vec3 {
+        Vec3::new(
+            spread.x * (self.next_f32() * 2.0 - 1.0),
+            spread.y * (self.next_f32() * 2.0 - 1.0),
+            spread.z * (self.next_f32() * 2.0 - 1.0)
+        )
+    }
+}
+
+const VERTEX_SOURCE: &str = "
+layout (location = 0) in vec4 PositionSize;
+layout (location = 1) in vec4 Colour;
+layout (location = 2) in vec4 Uv;
+
+out vec2 TexCoord;
+out vec4 Tint;
+out float Fog;
+
+void main()
+{
+    // triangle strip corners (0, 0), (1, 0), (0, 1), (1, 1)
+    vec2 corner = vec2(gl_VertexID & 1, (gl_VertexID >> 1) & 1);
+    vec3 right = vec3(view[0][0], view[1][0], view[2][0]);
+    vec3 up = vec3(view[0][1], view[1][1], view[2][1]);
+    vec3 worldPos = PositionSize.xyz + (right * (corner.x - 0.5) + up * (corner.y - 0.5)) * PositionSize.w;
+    gl_Position = projection * view * vec4(worldPos, 1.0);
+    TexCoord = mix(Uv.xy, Uv.zw, vec2(corner.x, 1.0 - corner.y));
+    Tint = Colour;
+    Fog = clamp((length(worldPos - cameraPosition) - fogStart) / (fogEnd - fogStart), 0.0, 1.0);
+}
+";
+
+const FRAGMENT_SOURCE: &str = "
+out vec4 Color;
+
+in vec2 TexCoord;
+in vec4 Tint;
+in float Fog;
+
+uniform sampler2D particleTexture;
+
+void main()
+{
+    vec4 colour = texture(particleTexture, TexCoord) * Tint;
+    if (colour.a < 0.1) {
+        discard;
+    }
+    vec3 light = vec3(ambientLight) + lightColour.rgb * max(lightDirection.y, 0.0);
+    Color = vec4(mix(colour.rgb * light, fogColour.rgb, Fog), 1.0);
+}
+";
+
+/// Simulates particles on the CPU and draws them as camera facing quads in one instanced draw.
+/// Particles are alpha tested rather than blended so they don't need sorting.
+pub struct ParticleSystem {
+    particles: Vec<Particle>,
+    emitters: Vec<(EmitterId, Emitter)>,
+    next_emitter: u64,
+    /// New particles are dropped once this many are alive
+    pub max_particles: usize,
+    rng: Rng,
+    shader: Shader,
+    vertex_array: VertexArray,
+    instances: Buffer<ParticleInstance>,
+    backend: Rc<dyn Backend>
+}
+
+impl ParticleSystem {
+    pub fn new(backend: &Rc<dyn Backend>, max_particles: usize) -> Result<Self, ShaderError> {
+        let vertex_source = format!("#version 330 core\n{}{}", FRAME_UNIFORMS_GLSL, VERTEX_SOURCE);
+        let fragment_source = format!("#version 330 core\n{}{}", FRAME_UNIFORMS_GLSL, FRAGMENT_SOURCE);
+        let mut shader = Shader::from_source(backend, "particles", &vertex_source, &fragment_source)?;
+        shader.bind_uniform_block("Frame", FRAME_UNIFORMS_BINDING);
+
+        let vertex_array = VertexArray::new(backend);
+        let instances = Buffer::new(backend, gl::ARRAY_BUFFER);
+        vertex_array.set_instance_buffer(&instances);
+
+        Ok(Self {
+            particles: Vec::new(),
+            emitters: Vec::new(),
+            next_emitter: 0,
+            max_particles,
+            rng: Rng(0x2545_f491_4f6c_dd1d),
+            shader,
+            vertex_array,
+            instances,
+            backend: backend.clone()
+        })
+    }
+
+    pub fn add_emitter(&mut self, emitter: Emitter) -> EmitterId {
+        let id = EmitterId(self.next_emitter);
+        self.next_emitter += 1;
+        self.emitters.push((id, emitter));
+        id
+    }
+
+    pub fn remove_emitter(&mut self, id: EmitterId) -> Option<Emitter> {
+        let index = self.emitters.iter().position(|(i, _)| *i == id)?;
+        Some(self.emitters.remove(index).1)
+    }
+
+    /// `None` once the emitter has finished or been removed
+    pub fn emitter_mut(&mut self, id: EmitterId) -> Option<&mut Emitter> {
+        self.emitters.iter_mut().find(|(i, _)| *i == id).map(|(_, emitter)| emitter)
+    }
+
+    /// Spawns `count` particles straight away
+    pub fn burst(&mut self, position: Vec3, count: u32, settings: &ParticleSettings) {
+        for _ in 0..count {
+            self.spawn(&position, settings);
+        }
+    }
+
+    /// Number of live particles
+    pub fn len(&self) -> usize {
+        self.particles.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.particles.is_empty()
+    }
+
+    /// Removes every particle and emitter
+    pub fn clear(&mut self) {
+        self.particles.clear();
+        self.emitters.clear();
+    }
+
+    /// Runs emitters and moves particles. `is_solid` is asked about the voxel
+    /// (unit cube with its minimum corner at the given position) a colliding particle moves into.
+    pub fn update(&mut self, delta_time: f32, is_solid: impl Fn(IVec3) -> bool) {
+        let mut emitters = std::mem::take(&mut self.emitters);
+        emitters.retain_mut(|(_, emitter)| {
+            let (count, finished) = emitter.advance(delta_time);
+            for _ in 0..count {
+                self.spawn(&emitter.position, &emitter.settings);
+            }
+            !finished
+        });
+        self.emitters = emitters;
+
+        self.particles.retain_mut(|particle| {
+            particle.age += delta_time;
+            if particle.age >= particle.lifetime {
+                return false
+            }
+            particle.velocity.y -= particle.gravity * delta_time;
+            particle.velocity *= (1.0 - particle.drag * delta_time).max(0.0);
+
+            // one axis at a time so particles slide along surfaces
+            for axis in 0..3 {
+                let mut position = particle.position;
+                position[axis] += particle.velocity[axis] * delta_time;
+                let voxel = IVec3::new(position.x.floor() as i32, position.y.floor() as i32, position.z.floor() as i32);
+                if particle.collide && is_solid(voxel) {
+                    particle.velocity[axis] *= -particle.bounce;
+                    if axis == 1 {
+                        // ground friction
+                        particle.velocity.x *= 0.7;
+                        particle.velocity.z *= 0.7;
+                    }
+                } else {
+                    particle.position = position;
+                }
+            }
+            true
+        });
+    }
+
+    /// Draws every particle with `texture` bound to unit 0
+    pub fn draw(&self, texture: &Texture) {
+        if self.particles.is_empty() {
+            return
+        }
+        let instances: Vec<ParticleInstance> = self.particles.iter().map(|particle| {
+            // shrink away over the last quarter of the lifetime
+            let remaining = particle.lifetime - particle.age;
+            let size = particle.size * (remaining / (particle.lifetime * 0.25)).min(1.0);
+            ParticleInstance {
+                position_size: [particle.position.x, particle.position.y, particle.position.z, size],
+                colour: [particle.colour.x, particle.colour.y, particle.colour.z, particle.colour.w],
+                uv: [particle.uv.min.x, particle.uv.min.y, particle.uv.max.x, particle.uv.max.y]
+            }
+        }).collect();
+        self.instances.set_data(&instances, gl::STREAM_DRAW);
+
+        texture.bind(0);
+        self.shader.use_program();
+        self.shader.set_int("particleTexture", 0);
+        self.vertex_array.bind();
+        self.backend.draw_arrays_instanced(gl::TRIANGLE_STRIP, 0, 4, instances.len() as i32);
+        self.vertex_array.unbind();
+        texture.unbind(0);
+    }
+
+    fn spawn(&mut self, position: &Vec3, settings: &ParticleSettings) {
+        if self.particles.len() >= self.max_particles {
+            return
+        }
+        let fraction = settings.uv_fraction.clamp(0.0, 1.0);
+        let uv_offset = Vec2::new(self.rng.next_f32(), self.rng.next_f32()) * (1.0 - fraction);
+        let particle = Particle {
+            position: position + self.rng.spread(&settings.position_spread),
+            velocity: settings.velocity + self.rng.spread(&settings.velocity_spread),
+            age: 0.0,
+            lifetime: self.rng.range(settings.lifetime),
+            size: self.rng.range(settings.size),
+            colour: settings.colour,
+            uv: settings.uv.sub_rect(uv_offset, Vec2::new(fraction, fraction)),
+            gravity: settings.gravity,
+            drag: settings.drag,
+            collide: settings.collide,
+            bounce: settings.bounce
+        };
+        self.particles.push(particle);
+    }
+}
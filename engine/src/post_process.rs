@@ -0,0 +1,355 @@
+use std::rc::Rc;
+
+use glm::{Vec3, Vec4};
+use thiserror::Error;
+
+use crate::backend::Backend;
+use crate::events::Event;
+use crate::framebuffer::{Framebuffer, FramebufferError};
+use crate::shader::{Shader, ShaderError};
+use crate::texture::TextureFormat;
+use crate::vertex_array::VertexArray;
+
+#[derive(Debug, Error)]
+pub enum PostProcessError {
+    #[error(transparent)]
+    Framebuffer(#[from] FramebufferError),
+    #[error(transparent)]
+    Shader(#[from] ShaderError)
+}
+
+const VERTEX_SOURCE: &str = "#version 330 core
+out vec2 TexCoord;
+
+// fullscreen triangle, no vertex buffer needed
+void main()
+{
+    vec2 position = vec2((gl_VertexID << 1) & 2, gl_VertexID & 2);
+    TexCoord = position;
+    gl_Position = vec4(position * 2.0 - 1.0, 0.0, 1.0);
+}
+";
+
+/// Shared by every pass, the effect's code is appended
+const FRAGMENT_HEADER: &str = "#version 330 core
+out vec4 Color;
+
+in vec2 TexCoord;
+
+// output of the previous pass
+uniform sampler2D screen;
+uniform vec2 texelSize;
+";
+
+const COPY_SOURCE: &str = "
+void main()
+{
+    Color = texture(screen, TexCoord);
+}
+";
+
+const TONEMAP_SOURCE: &str = "
+uniform float exposure;
+
+// Narkowicz's fit of the ACES filmic curve
+void main()
+{
+    vec3 x = texture(screen, TexCoord).rgb * exposure;
+    Color = vec4(clamp((x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14), 0.0, 1.0), 1.0);
+}
+";
+
+const GAMMA_SOURCE: &str = "
+uniform float gamma;
+
+void main()
+{
+    vec3 colour = texture(screen, TexCoord).rgb;
+    Color = vec4(pow(max(colour, 0.0), vec3(1.0 / gamma)), 1.0);
+}
+";
+
+const COLOUR_GRADE_SOURCE: &str = "
+uniform float brightness;
+uniform float contrast;
+uniform float saturation;
+uniform vec3 tint;
+
+void main()
+{
+    vec3 colour = texture(screen, TexCoord).rgb;
+    colour = (colour - 0.5) * contrast + 0.5 + brightness;
+    float luma = dot(colour, vec3(0.2126, 0.7152, 0.0722));
+    colour = mix(vec3(luma), colour, saturation) * tint;
+    Color = vec4(max(colour, 0.0), 1.0);
+}
+";
+
+const FXAA_SOURCE: &str = "
+const float REDUCE_MIN = 1.0 / 128.0;
+const float REDUCE_MUL = 1.0 / 8.0;
+const float SPAN_MAX = 8.0;
+
+// FXAA without the quality presets: blur along the edge direction
+// estimated from the luma of the four diagonal neighbours
+void main()
+{
+    vec3 lumaWeights = vec3(0.299, 0.587, 0.114);
+    float lumaNW = dot(texture(screen, TexCoord + vec2(-1.0, -1.0) * texelSize).rgb, lumaWeights);
+    float lumaNE = dot(texture(screen, TexCoord + vec2(1.0, -1.0) * texelSize).rgb, lumaWeights);
+    float lumaSW = dot(texture(screen, TexCoord + vec2(-1.0, 1.0) * texelSize).rgb, lumaWeights);
+    float lumaSE = dot(texture(screen, TexCoord + vec2(1.0, 1.0) * texelSize).rgb, lumaWeights);
+    float lumaM = dot(texture(screen, TexCoord).rgb, lumaWeights);
+    float lumaMin = min(lumaM, min(min(lumaNW, lumaNE), min(lumaSW, lumaSE)));
+    float lumaMax = max(lumaM, max(max(lumaNW, lumaNE), max(lumaSW, lumaSE)));
+
+    vec2 direction = vec2(
+        -((lumaNW + lumaNE) - (lumaSW + lumaSE)),
+        (lumaNW + lumaSW) - (lumaNE + lumaSE)
+    );
+    float directionReduce = max((lumaNW + lumaNE + lumaSW + lumaSE) * 0.25 * REDUCE_MUL, REDUCE_MIN);
+    float inverseDirectionMin = 1.0 / (min(abs(direction.x), abs(direction.y)) + directionReduce);
+    direction = clamp(direction * inverseDirectionMin, -SPAN_MAX, SPAN_MAX) * texelSize;
+
+    vec3 colourA = 0.5 * (
+        texture(screen, TexCoord + direction * (1.0 / 3.0 - 0.5)).rgb +
+        texture(screen, TexCoord + direction * (2.0 / 3.0 - 0.5)).rgb
+    );
+    vec3 colourB = colourA * 0.5 + 0.25 * (
+        texture(screen, TexCoord - direction * 0.5).rgb +
+        texture(screen, TexCoord + direction * 0.5).rgb
+    );
+    float lumaB = dot(colourB, lumaWeights);
+    Color = vec4((lumaB < lumaMin || lumaB > lumaMax) ? colourA : colourB, 1.0);
+}
+";
+
+const VIGNETTE_SOURCE: &str = "
+uniform float strength;
+uniform float radius;
+uniform float softness;
+
+void main()
+{
+    vec3 colour = texture(screen, TexCoord).rgb;
+    float dist = distance(TexCoord, vec2(0.5));
+    float vignette = smoothstep(radius, radius - softness, dist);
+    Color = vec4(colour * mix(1.0, vignette, strength), 1.0);
+}
+";
+
+const TINT_SOURCE: &str = "
+uniform vec4 tint;
+
+void main()
+{
+    vec3 colour = texture(screen, TexCoord).rgb;
+    Color = vec4(mix(colour, colour * tint.rgb, tint.a), 1.0);
+}
+";
+
+/// A full screen effect and its settings, which are read every frame so they can be changed live
+#[derive(Debug, Clone, PartialEq)]
+pub enum PostEffect {
+    /// Maps HDR colour into 0.0 to 1.0 with the ACES filmic curve
+    Tonemap {
+        exposure: f32
+    },
+    /// Converts linear colour for display
+    Gamma {
+        gamma: f32
+    },
+    ColourGrade {
+        /// Added to every channel, 0.0 is neutral
+        brightness: f32,
+        /// Scales distance from mid grey, 1.0 is neutral
+        contrast: f32,
+        /// 0.0 is greyscale, 1.0 is neutral
+        saturation: f32,
+        /// Multiplied with the result
+        tint: Vec3
+    },
+    /// Fast approximate anti-aliasing, runs best after tonemapping and gamma
+    Fxaa,
+    /// Darkens the corners of the screen
+    Vignette {
+        /// 0.0 to 1.0, how dark the corners get
+        strength: f32,
+        /// Distance from the centre where darkening ends, 0.5 reaches the screen edges
+        radius: f32,
+        /// Width of the fade from dark to clear
+        softness: f32
+    },
+    /// Multiplies the screen by a colour, blended in by its alpha, e.g. while underwater
+    Tint {
+        colour: Vec4
+    }
+}
+
+impl PostEffect {
+    pub fn name(&self) -> &'static str {
+        match self {
+            PostEffect::Tonemap { .. } => "Tonemap",
+            PostEffect::Gamma { .. } => "Gamma",
+            PostEffect::ColourGrade { .. } => "Colour grade",
+            PostEffect::Fxaa => "FXAA",
+            PostEffect::Vignette { .. } => "Vignette",
+            PostEffect::Tint { .. } => "Tint"
+        }
+    }
+
+    fn fragment_source(&self) -> &'static str {
+        match self {
+            PostEffect::Tonemap { .. } => TONEMAP_SOURCE,
+            PostEffect::Gamma { .. } => GAMMA_SOURCE,
+            PostEffect::ColourGrade { .. } => COLOUR_GRADE_SOURCE,
+            PostEffect::Fxaa => FXAA_SOURCE,
+            PostEffect::Vignette { .. } => VIGNETTE_SOURCE,
+            PostEffect::Tint { .. } => TINT_SOURCE
+        }
+    }
+
+    fn set_uniforms(&self, shader: &Shader) {
+        match *self {
+            PostEffect::Tonemap { exposure } => shader.set_float("exposure", exposure),
+            PostEffect::Gamma { gamma } => shader.set_float("gamma", gamma),
+            PostEffect::ColourGrade { brightness, contrast, saturation, tint } => {
+                shader.set_float("brightness", brightness);
+                shader.set_float("contrast", contrast);
+                shader.set_float("saturation", saturation);
+                shader.set_vector3("tint", &tint);
+            },
+            PostEffect::Fxaa => {},
+            PostEffect::Vignette { strength, radius, softness } => {
+                shader.set_float("strength", strength);
+                shader.set_float("radius", radius);
+                shader.set_float("softness", softness);
+            },
+            PostEffect::Tint { colour } => shader.set_vec4("tint", colour.x, colour.y, colour.z, colour.w)
+        }
+    }
+}
+
+/// One step of a `PostChain`
+pub struct PostPass {
+    pub effect: PostEffect,
+    pub enabled: bool,
+    shader: Shader
+}
+
+/// Renders the scene into an offscreen HDR framebuffer and runs it through a list of
+/// full screen passes, the last enabled pass drawing to the window.
+///
+/// Draw the scene between `begin` and `finish`, anything drawn after `finish`
+/// (like the UI) goes straight to the window untouched.
+pub struct PostChain {
+    passes: Vec<PostPass>,
+    /// Colour and depth of the scene
+    scene: Framebuffer,
+    /// Passes alternate reading one and writing the other
+    targets: [Framebuffer; 2],
+    copy: Shader,
+    vertex_array: VertexArray,
+    backend: Rc<dyn Backend>
+}
+
+impl PostChain {
+    pub fn new(backend: &Rc<dyn Backend>, size: (u32, u32)) -> Result<Self, PostProcessError> {
+        let target = || Framebuffer::new(backend, size, Some(TextureFormat::Rgba16F), None);
+        Ok(Self {
+            passes: Vec::new(),
+            scene: Framebuffer::new(backend, size, Some(TextureFormat::Rgba16F), Some(TextureFormat::Depth24))?,
+            targets: [target()?, target()?],
+            copy: Self::create_shader(backend, "copy", COPY_SOURCE)?,
+            vertex_array: VertexArray::new(backend),
+            backend: backend.clone()
+        })
+    }
+
+    /// Adds a pass at the end of the chain, enabled
+    pub fn push(&mut self, effect: PostEffect) -> Result<&mut PostPass, ShaderError> {
+        let shader = Self::create_shader(&self.backend, effect.name(), effect.fragment_source())?;
+        self.passes.push(PostPass { effect, enabled: true, shader });
+        Ok(self.passes.last_mut().expect("pass was just pushed"))
+    }
+
+    pub fn passes(&self) -> &[PostPass] {
+        &self.passes
+    }
+
+    pub fn passes_mut(&mut self) -> &mut [PostPass] {
+        &mut self.passes
+    }
+
+    /// The framebuffer the scene is drawn into, its depth can be sampled after `finish`
+    pub fn scene(&self) -> &Framebuffer {
+        &self.scene
+    }
+
+    pub fn resize(&mut self, size: (u32, u32)) -> Result<(), FramebufferError> {
+        self.scene.resize(size)?;
+        for target in self.targets.iter_mut() {
+            target.resize(size)?;
+        }
+        Ok(())
+    }
+
+    /// Follows the window's framebuffer size
+    pub fn handle_event(&mut self, event: &Event) -> Result<(), FramebufferError> {
+        self.scene.handle_event(event)?;
+        for target in self.targets.iter_mut() {
+            target.handle_event(event)?;
+        }
+        Ok(())
+    }
+
+    /// Starts drawing the scene offscreen, clear it after calling this
+    pub fn begin(&self) {
+        self.scene.bind();
+    }
+
+    /// Runs the enabled passes over the scene and leaves the window's framebuffer bound
+    pub fn finish(&self) {
+        let size = self.scene.size;
+        let texel_size = (1.0 / size.0 as f32, 1.0 / size.1 as f32);
+        let enabled: Vec<&PostPass> = self.passes.iter().filter(|pass| pass.enabled).collect();
+
+        self.backend.disable(gl::DEPTH_TEST);
+        self.vertex_array.bind();
+        let mut source = &self.scene;
+        if enabled.is_empty() {
+            Framebuffer::bind_default(&self.backend, size);
+            self.draw_pass(&self.copy, source, texel_size);
+        }
+        for (index, pass) in enabled.iter().enumerate() {
+            let target = &self.targets[index % 2];
+            if index + 1 == enabled.len() {
+                Framebuffer::bind_default(&self.backend, size);
+            } else {
+                target.bind();
+            }
+            pass.shader.use_program();
+            pass.effect.set_uniforms(&pass.shader);
+            self.draw_pass(&pass.shader, source, texel_size);
+            source = target;
+        }
+        self.vertex_array.unbind();
+        self.backend.bind_texture(gl::TEXTURE_2D, 0);
+        self.backend.enable(gl::DEPTH_TEST);
+    }
+
+    fn draw_pass(&self, shader: &Shader, source: &Framebuffer, texel_size: (f32, f32)) {
+        if let Some(colour) = source.colour() {
+            colour.bind(0);
+        }
+        shader.use_program();
+        shader.set_int("screen", 0);
+        shader.set_vec2("texelSize", texel_size.0, texel_size.1);
+        self.backend.draw_arrays(gl::TRIANGLES, 0, 3);
+    }
+
+    fn create_shader(backend: &Rc<dyn Backend>, name: &str, source: &str) -> Result<Shader, ShaderError> {
+        let fragment_source = format!("{}{}", FRAGMENT_HEADER, source);
+        Shader::from_source(backend, &format!("{} post pass", name), VERTEX_SOURCE, &fragment_source)
+    }
+}
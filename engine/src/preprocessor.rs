@@ -0,0 +1,304 @@
+use std::{fs::read_to_string, io, path::{Path, PathBuf}};
+
+use thiserror::Error;
+
+use crate::shader::FRAME_UNIFORMS_GLSL;
+
+#[derive(Debug, Error)]
+pub enum PreprocessError {
+    #[error("Failed to read shader file {}: {source}", path.display())]
+    Io {
+        path: PathBuf,
+        source: io::Error
+    },
+    #[error("Include cycle: {}", chain.iter().map(|path| path.display().to_string()).collect::<Vec<_>>().join(" -> "))]
+    IncludeCycle {
+        chain: Vec<PathBuf>
+    },
+    #[error("{}:{line}: malformed #include, expected #include \"file\"", path.display())]
+    MalformedInclude {
+        path: PathBuf,
+        line: usize
+    }
+}
+
+/// Resolves `#include "file"` and injects `#define`s into GLSL source.
+///
+/// Includes are resolved relative to the include directory, which defaults to the
+/// directory of the file being processed. Files containing `#pragma once` are only
+/// included the first time.
+///
+/// The engine's own GLSL is included by name from memory rather than from disk, only once
+/// per shader: `#include "engine/frame.glsl"` for the `Frame` uniform block.
+#[derive(Debug, Clone, Default)]
+pub struct Preprocessor {
+    defines: Vec<(String, Option<String>)>,
+    include_dir: Option<PathBuf>
+}
+
+/// Include names that resolve to GLSL built into the engine
+const BUILTIN_INCLUDES: [(&str, &str); 1] = [
+    ("engine/frame.glsl", FRAME_UNIFORMS_GLSL)
+];
+
+/// Preprocessed source along with where each of its lines came from
+#[derive(Debug, Clone, Default)]
+pub struct PreprocessedSource {
+    pub source: String,
+    /// Every file read, the root file first
+    pub files: Vec<PathBuf>,
+    /// (index into `files`, 1-based line) for each output line, `None` for injected lines
+    line_origins: Vec<Option<(usize, usize)>>
+}
+
+impl Preprocessor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn define(mut self, name: &str) -> Self {
+        self.set_define(name, None);
+        self
+    }
+
+    pub fn define_value(mut self, name: &str, value: impl ToString) -> Self {
+        self.set_define(name, Some(value.to_string()));
+        self
+    }
+
+    pub fn include_dir(self, include_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            include_dir: Some(include_dir.into()),
+            ..self
+        }
+    }
+
+    pub fn set_define(&mut self, name: &str, value: Option<String>) {
+        match self.defines.iter_mut().find(|(n, _)| n == name) {
+            Some(define) => define.1 = value,
+            None => self.defines.push((name.to_string(), value))
+        }
+    }
+
+    pub fn undefine(&mut self, name: &str) {
+        self.defines.retain(|(n, _)| n != name)
+    }
+
+    pub fn process(&self, path: &Path) -> Result<PreprocessedSource, PreprocessError> {
+        let include_dir = match &self.include_dir {
+            Some(dir) => dir.clone(),
+            None => path.parent().map(Path::to_path_buf).unwrap_or_default()
+        };
+        let mut output = PreprocessedSource::default();
+        let mut once = Vec::new();
+        let mut stack = Vec::new();
+        let mut defines_pending = true;
+
+        self.process_file(path, None, &include_dir, &mut output, &mut stack, &mut once, &mut defines_pending)?;
+        if defines_pending {
+            // no #version directive, defines go at the very top
+            let mut defines = PreprocessedSource::default();
+            self.push_defines(&mut defines);
+            output.source.insert_str(0, &defines.source);
+            defines.line_origins.append(&mut output.line_origins);
+            output.line_origins = defines.line_origins;
+        }
+        Ok(output)
+    }
+
+    /// Processes the file at `path`, or `builtin` source named by `path` if given
+    #[allow(clippy::too_many_arguments)]
+    fn process_file(&self, path: &Path, builtin: Option<&str>, include_dir: &Path, output: &mut PreprocessedSource, stack: &mut Vec<PathBuf>, once: &mut Vec<PathBuf>, defines_pending: &mut bool) -> Result<(), PreprocessError> {
+        let key = match builtin {
+            Some(_) => path.to_path_buf(),
+            None => path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+        };
+        // before the cycle check, a `#pragma once` file including itself back is fine
+        if once.contains(&key) {
+            return Ok(())
+        }
+        if let Some(start) = stack.iter().position(|p| *p == key) {
+            let mut chain = stack[start..].to_vec();
+            chain.push(key);
+            return Err(PreprocessError::IncludeCycle { chain })
+        }
+        let source = match builtin {
+            Some(source) => {
+                once.push(key.clone());
+                source.to_string()
+            },
+            None => read_to_string(path).map_err(|source| PreprocessError::Io { path: path.to_path_buf(), source })?
+        };
+        stack.push(key.clone());
+
+        let file_index = match output.files.iter().position(|p| p == path) {
+            Some(index) => index,
+            None => {
+                output.files.push(path.to_path_buf());
+                output.files.len() - 1
+            }
+        };
+
+        for (i, line) in source.lines().enumerate() {
+            let line_number = i + 1;
+            let trimmed = line.trim_start();
+            if let Some(rest) = directive(trimmed, "include") {
+                let rest = rest.trim();
+                let file = rest.strip_prefix('"').and_then(|rest| rest.strip_suffix('"'));
+                match file {
+                    Some(file) if !file.is_empty() => match BUILTIN_INCLUDES.iter().find(|(name, _)| *name == file) {
+                        Some((name, source)) => self.process_file(Path::new(name), Some(source), include_dir, output, stack, once, defines_pending)?,
+                        None => self.process_file(&include_dir.join(file), None, include_dir, output, stack, once, defines_pending)?
+                    },
+                    _ => return Err(PreprocessError::MalformedInclude { path: path.to_path_buf(), line: line_number })
+                }
+            } else if directive(trimmed, "pragma").map(|rest| rest.trim() == "once").unwrap_or(false) {
+                once.push(key.clone());
+            } else {
+                output.push_line(line, Some((file_index, line_number)));
+                if *defines_pending && directive(trimmed, "version").is_some() {
+                    self.push_defines(output);
+                    *defines_pending = false;
+                }
+            }
+        }
+
+        stack.pop();
+        Ok(())
+    }
+
+    fn push_defines(&self, output: &mut PreprocessedSource) {
+        for (name, value) in self.defines.iter() {
+            match value {
+                Some(value) => output.push_line(&format!("#define {} {}", name, value), None),
+                None => output.push_line(&format!("#define {}", name), None)
+            }
+        }
+    }
+}
+
+impl PreprocessedSource {
+    fn push_line(&mut self, line: &str, origin: Option<(usize, usize)>) {
+        self.source.push_str(line);
+        self.source.push('\n');
+        self.line_origins.push(origin);
+    }
+
+    /// Maps a 1-based line of the preprocessed source back to its file and line
+    pub fn map_line(&self, line: usize) -> Option<(&Path, usize)> {
+        let (file, line) = (*self.line_origins.get(line.checked_sub(1)?)?)?;
+        Some((self.files[file].as_path(), line))
+    }
+
+    /// Rewrites the line references in a compiler log (`0:12(5)`, `0(12)`, `ERROR: 0:12:`)
+    /// to point at the original files
+    pub fn map_log(&self, log: &str) -> String {
+        log.lines()
+            .map(|line| self.map_log_line(line))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn map_log_line(&self, line: &str) -> String {
+        let bytes = line.as_bytes();
+        for start in 0..bytes.len() {
+            if bytes[start] != b'0' || (start > 0 && bytes[start - 1].is_ascii_digit()) {
+                continue
+            }
+            let Some(&separator) = bytes.get(start + 1) else {
+                break
+            };
+            if separator != b':' && separator != b'(' {
+                continue
+            }
+            let digits_start = start + 2;
+            let digits_end = bytes[digits_start..].iter().position(|b| !b.is_ascii_digit()).map_or(bytes.len(), |i| digits_start + i);
+            if digits_end == digits_start || (separator == b'(' && bytes.get(digits_end) != Some(&b')')) {
+                continue
+            }
+            let end = if separator == b'(' { digits_end + 1 } else { digits_end };
+            let Some((path, original)) = line[digits_start..digits_end].parse().ok().and_then(|l| self.map_line(l)) else {
+                continue
+            };
+            return format!("{}{}:{}{}", &line[..start], path.display(), original, &line[end..])
+        }
+        line.to_string()
+    }
+}
+
+/// Returns the rest of the line if it is the given preprocessor directive
+fn directive<'a>(line: &'a str, name: &str) -> Option<&'a str> {
+    let rest = line.strip_prefix('#')?.trim_start().strip_prefix(name)?;
+    match rest.chars().next() {
+        None => Some(rest),
+        Some(c) if c.is_whitespace() || c == '"' => Some(rest),
+        _ => None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    fn write_files(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("preprocessor_{}_{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        for (file, source) in files {
+            fs::write(dir.join(file), source).unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn pragma_once_file_included_back_is_skipped() {
+        let dir = write_files("once_cycle", &[
+            ("a.glsl", "#pragma once\n#include \"b.glsl\"\na"),
+            ("b.glsl", "#include \"a.glsl\"\nb")
+        ]);
+        let output = Preprocessor::new().process(&dir.join("a.glsl")).unwrap();
+        fs::remove_dir_all(dir).unwrap();
+        assert_eq!(output.source, "b\na\n");
+    }
+
+    #[test]
+    fn cycle_without_pragma_once_is_an_error() {
+        let dir = write_files("cycle", &[
+            ("a.glsl", "#include \"b.glsl\"\na"),
+            ("b.glsl", "#include \"a.glsl\"\nb")
+        ]);
+        let result = Preprocessor::new().process(&dir.join("a.glsl"));
+        fs::remove_dir_all(dir).unwrap();
+        assert!(matches!(result, Err(PreprocessError::IncludeCycle { .. })));
+    }
+
+    #[test]
+    fn builtin_include_is_read_from_memory_once() {
+        let dir = write_files("builtin", &[
+            ("a.glsl", "#include \"engine/frame.glsl\"\n#include \"engine/frame.glsl\"\na")
+        ]);
+        let output = Preprocessor::new().process(&dir.join("a.glsl")).unwrap();
+        fs::remove_dir_all(dir).unwrap();
+        assert_eq!(output.source, format!("{}a\n", FRAME_UNIFORMS_GLSL));
+        assert_eq!(output.files[1], Path::new("engine/frame.glsl"));
+    }
+
+    #[test]
+    fn log_lines_map_through_nested_includes() {
+        let dir = write_files("map_log", &[
+            ("main.glsl", "#version 330 core\n#include \"a.glsl\"\nvoid main() {}"),
+            ("a.glsl", "#include \"b.glsl\"\nfloat a;"),
+            ("b.glsl", "float b;\nfloat bad;")
+        ]);
+        let output = Preprocessor::new().define("SHADOWS").process(&dir.join("main.glsl")).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        // #version, the injected define, b.glsl, the rest of a.glsl, then main.glsl after the include
+        assert_eq!(output.map_line(2), None);
+        let log = "0:4(7): error: bad\n0(5) : error C0000: a\nERROR: 0:6: main\nno line here";
+        assert_eq!(output.map_log(log), format!(
+            "{}:2(7): error: bad\n{}:2 : error C0000: a\nERROR: {}:3: main\nno line here",
+            dir.join("b.glsl").display(), dir.join("a.glsl").display(), dir.join("main.glsl").display()
+        ));
+    }
+}
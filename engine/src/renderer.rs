@@ -1,6 +1,114 @@
-use gl::*;
+use std::rc::Rc;
 
+use glm::Vec4;
+
+use crate::backend::Backend;
+use crate::events::Event;
+
+/// How world geometry is drawn, for checking meshes. Shaders read the mode
+/// from `viewMode` in the `Frame` block and pick their output colour with it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ViewMode {
+    #[default]
+    Shaded,
+    /// Triangle edges only
+    Wireframe,
+    /// World space normals mapped to colours
+    Normals,
+    /// Texture coordinates as red and green, with a checkerboard per texture repeat
+    Uv,
+    /// A random colour per chunk
+    ChunkColour,
+    /// Brighter where more fragments land on the same pixel
+    Overdraw
+}
+
+impl ViewMode {
+    pub const ALL: [ViewMode; 6] = [
+        ViewMode::Shaded,
+        ViewMode::Wireframe,
+        ViewMode::Normals,
+        ViewMode::Uv,
+        ViewMode::ChunkColour,
+        ViewMode::Overdraw
+    ];
+
+    /// Value of `viewMode` in the `Frame` block, matching the `VIEW_*` defines
+    pub fn id(self) -> i32 {
+        self as i32
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ViewMode::Shaded => "Shaded",
+            ViewMode::Wireframe => "Wireframe",
+            ViewMode::Normals => "Normals",
+            ViewMode::Uv => "UV",
+            ViewMode::ChunkColour => "Chunk colour",
+            ViewMode::Overdraw => "Overdraw"
+        }
+    }
+
+    /// The mode after this one, wrapping around
+    pub fn next(self) -> Self {
+        Self::ALL[(self.id() as usize + 1) % Self::ALL.len()]
+    }
+}
+
+/// Sets up GL state for the current view mode around world geometry passes
 pub struct Renderer {
-    
+    pub view_mode: ViewMode,
+    backend: Rc<dyn Backend>
 }
 
+impl Renderer {
+    pub fn new(backend: &Rc<dyn Backend>) -> Self {
+        Self {
+            view_mode: ViewMode::default(),
+            backend: backend.clone()
+        }
+    }
+
+    /// Keeps the viewport covering the window's framebuffer
+    pub fn handle_event(&self, event: &Event) {
+        if let Event::FramebufferResized { width, height } = *event {
+            self.backend.viewport(0, 0, width as i32, height as i32)
+        }
+    }
+
+    /// Clears colour and depth, to black in overdraw mode so only the count shows
+    pub fn clear(&self, colour: Vec4) {
+        let colour = if self.view_mode == ViewMode::Overdraw { Vec4::new(0.0, 0.0, 0.0, 1.0) } else { colour };
+        self.backend.clear_color(colour.x, colour.y, colour.z, colour.w);
+        self.backend.clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+    }
+
+    /// Whether the sky, clouds and effects should be drawn, they'd hide the overdraw count
+    pub fn shows_environment(&self) -> bool {
+        self.view_mode != ViewMode::Overdraw
+    }
+
+    /// Runs `draw` with the polygon, depth and blend state the view mode needs.
+    /// Every pass of world geometry should be drawn through this.
+    pub fn world_pass(&self, draw: impl FnOnce()) {
+        match self.view_mode {
+            ViewMode::Wireframe => self.backend.polygon_mode(gl::FRONT_AND_BACK, gl::LINE),
+            ViewMode::Overdraw => {
+                // every fragment adds a little, including the hidden ones
+                self.backend.disable(gl::DEPTH_TEST);
+                self.backend.enable(gl::BLEND);
+                self.backend.blend_func(gl::ONE, gl::ONE);
+            },
+            _ => {}
+        }
+        draw();
+        match self.view_mode {
+            ViewMode::Wireframe => self.backend.polygon_mode(gl::FRONT_AND_BACK, gl::FILL),
+            ViewMode::Overdraw => {
+                self.backend.disable(gl::BLEND);
+                self.backend.enable(gl::DEPTH_TEST);
+            },
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,116 @@
+use std::{collections::BTreeMap, fs, io, path::{Path, PathBuf}};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::camera::Camera;
+use crate::input::InputManager;
+use crate::Config;
+
+#[derive(Debug, Error)]
+pub enum ReplayError {
+    #[error("Failed to access recording {}: {source}", path.display())]
+    Io { path: PathBuf, source: io::Error },
+    #[error("Failed to parse recording {}: {source}", path.display())]
+    Parse { path: PathBuf, source: toml::de::Error },
+    #[error("Failed to serialise recording: {0}")]
+    Serialize(#[from] toml::ser::Error)
+}
+
+/// One tick of input after contexts were applied, so replaying it doesn't depend on
+/// bindings, menus or the window. Only actions that are on and axes that aren't zero are kept.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InputFrame {
+    pub delta_time: f32,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub down: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub pressed: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub released: Vec<String>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub axes: BTreeMap<String, f32>
+}
+
+/// Where the camera is and which way it faces
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct CameraState {
+    pub position: [f32; 3],
+    pub yaw: f32,
+    pub pitch: f32
+}
+
+impl CameraState {
+    pub fn from_camera(camera: &Camera) -> Self {
+        Self {
+            position: camera.position.coords.into(),
+            yaw: camera.yaw,
+            pitch: camera.pitch
+        }
+    }
+
+    pub fn apply_to(&self, camera: &mut Camera) {
+        camera.position = self.position.into();
+        camera.yaw = self.yaw;
+        camera.pitch = self.pitch;
+        // turning by nothing works out `front` and `right` from the angles
+        camera.process_look_input(0.0, 0.0, false);
+    }
+
+    /// Whether the states are within `tolerance` of each other in position and in degrees
+    pub fn matches(&self, other: &Self, tolerance: f32) -> bool {
+        self.position.iter().zip(other.position).all(|(a, b)| (a - b).abs() <= tolerance)
+            && (self.yaw - other.yaw).abs() <= tolerance
+            && (self.pitch - other.pitch).abs() <= tolerance
+    }
+}
+
+/// Per-tick input along with the settings, including the world seed, and camera it started from.
+/// Replaying every frame through the same game loop from the same start ends in the same place,
+/// which `end` records. Settings changed while recording aren't, so they should be left alone.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Recording {
+    pub config: Config,
+    pub start: CameraState,
+    /// Where the camera was when recording stopped, `None` until `finish`
+    pub end: Option<CameraState>,
+    pub frames: Vec<InputFrame>
+}
+
+impl Recording {
+    pub fn new(config: &Config, camera: &Camera) -> Self {
+        Self {
+            config: config.clone(),
+            start: CameraState::from_camera(camera),
+            end: None,
+            frames: Vec::new()
+        }
+    }
+
+    /// Adds the input manager's state from its last update, call once per tick after it
+    pub fn record(&mut self, input: &InputManager, delta_time: f32) {
+        self.frames.push(input.frame(delta_time));
+    }
+
+    pub fn finish(&mut self, camera: &Camera) {
+        self.end = Some(CameraState::from_camera(camera));
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, ReplayError> {
+        let path = path.as_ref();
+        let source = fs::read_to_string(path).map_err(|source| ReplayError::Io { path: path.to_path_buf(), source })?;
+        toml::from_str(&source).map_err(|source| ReplayError::Parse { path: path.to_path_buf(), source })
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), ReplayError> {
+        let path = path.as_ref();
+        let source = toml::to_string(self)?;
+        fs::write(path, source).map_err(|source| ReplayError::Io { path: path.to_path_buf(), source })
+    }
+
+    /// Seconds of play
+    pub fn duration(&self) -> f32 {
+        self.frames.iter().map(|frame| frame.delta_time).sum()
+    }
+}
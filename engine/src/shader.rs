@@ -1,164 +1,498 @@
-use glm::{Vec3, Mat4};
+use std::{cell::RefCell, collections::HashMap, fmt, fs::metadata, io, path::{Path, PathBuf}, rc::Rc, time::SystemTime};
+
+use glm::{Vec3, Vec4, Mat4};
 use gl::{types::*, VERTEX_SHADER, FRAGMENT_SHADER};
-use std::{ffi::{CStr, CString}, fs::read_to_string};
+use thiserror::Error;
+
+use crate::backend::{Backend, UniformValue};
+use crate::buffer::{Std140, Std140Writer};
+use crate::preprocessor::{PreprocessError, Preprocessor};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShaderStage {
+    Vertex,
+    Fragment
+}
+
+impl ShaderStage {
+    pub fn gl_enum(&self) -> GLenum {
+        match self {
+            ShaderStage::Vertex => VERTEX_SHADER,
+            ShaderStage::Fragment => FRAGMENT_SHADER
+        }
+    }
+}
+
+impl fmt::Display for ShaderStage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ShaderStage::Vertex => write!(f, "vertex"),
+            ShaderStage::Fragment => write!(f, "fragment")
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum ShaderError {
+    #[error("Failed to read shader file {}: {source}", path.display())]
+    Io {
+        path: PathBuf,
+        source: io::Error
+    },
+    #[error("Failed to compile {stage} shader {}:\n{log}", path.display())]
+    Compile {
+        stage: ShaderStage,
+        path: PathBuf,
+        log: String
+    },
+    #[error("Failed to link shader program:\n{0}")]
+    Link(String),
+    #[error(transparent)]
+    Preprocess(PreprocessError)
+}
+
+impl From<PreprocessError> for ShaderError {
+    fn from(err: PreprocessError) -> Self {
+        match err {
+            PreprocessError::Io { path, source } => ShaderError::Io { path, source },
+            err => ShaderError::Preprocess(err)
+        }
+    }
+}
+
+/// Where a program's sources come from
+#[derive(Debug, Clone)]
+enum ShaderSources {
+    Files {
+        vertex: PathBuf,
+        fragment: PathBuf
+    },
+    /// Built into the binary, `name` stands in for the path in messages
+    Embedded {
+        name: String,
+        vertex: String,
+        fragment: String
+    }
+}
+
+impl fmt::Display for ShaderSources {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ShaderSources::Files { vertex, fragment } => write!(f, "{}, {}", vertex.display(), fragment.display()),
+            ShaderSources::Embedded { name, .. } => write!(f, "{} (embedded)", name)
+        }
+    }
+}
+
+/// Binding point of the `Frame` uniform block
+pub const FRAME_UNIFORMS_BINDING: u32 = 0;
+
+/// Per-frame data shared by every program through a uniform buffer.
+///
+/// Matches this GLSL block:
+/// ```glsl
+/// layout (std140) uniform Frame {
+///     mat4 view;
+///     mat4 projection;
+///     vec3 cameraPosition;
+///     float time;
+///     vec4 fogColour;
+///     float fogStart;
+///     float fogEnd;
+///     vec3 lightDirection;
+///     float ambientLight;
+///     vec4 lightColour;
+///     int viewMode;
+/// };
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct FrameUniforms {
+    pub view: Mat4,
+    pub projection: Mat4,
+    pub camera_position: Vec3,
+    pub time: f32,
+    pub fog_colour: Vec4,
+    pub fog_start: f32,
+    pub fog_end: f32,
+    /// Unit vector pointing towards the main light
+    pub light_direction: Vec3,
+    pub ambient_light: f32,
+    pub light_colour: Vec4,
+    /// `ViewMode::id` of the current debug view, see `renderer`
+    pub view_mode: i32
+}
+
+/// GLSL declaration of the `Frame` block. Shaders built into the engine prepend it,
+/// shader files `#include "engine/frame.glsl"`.
+pub const FRAME_UNIFORMS_GLSL: &str = "layout (std140) uniform Frame {
+    mat4 view;
+    mat4 projection;
+    vec3 cameraPosition;
+    float time;
+    vec4 fogColour;
+    float fogStart;
+    float fogEnd;
+    vec3 lightDirection;
+    float ambientLight;
+    vec4 lightColour;
+    int viewMode;
+};
+
+// values of viewMode, see renderer::ViewMode
+#define VIEW_SHADED 0
+#define VIEW_WIREFRAME 1
+#define VIEW_NORMALS 2
+#define VIEW_UV 3
+#define VIEW_CHUNK_COLOUR 4
+#define VIEW_OVERDRAW 5
+";
+
+impl Std140 for FrameUniforms {
+    fn write_std140(&self, writer: &mut Std140Writer) {
+        writer
+            .mat4(&self.view)
+            .mat4(&self.projection)
+            .vec3(&self.camera_position)
+            .float(self.time)
+            .vec4(&self.fog_colour)
+            .float(self.fog_start)
+            .float(self.fog_end)
+            .vec3(&self.light_direction)
+            .float(self.ambient_light)
+            .vec4(&self.light_colour)
+            .int(self.view_mode);
+    }
+}
 
 pub struct Shader {
-    pub id: u32
+    pub id: u32,
+    backend: Rc<dyn Backend>,
+    sources: ShaderSources,
+    preprocessor: Preprocessor,
+    /// Every file the current program was built from, including `#include`s
+    source_files: Vec<PathBuf>,
+    /// Newest modification time of the sources, `Some` while watching
+    watched_modified: Option<SystemTime>,
+    /// Locations looked up so far, -1 for uniforms the program doesn't have
+    uniform_locations: RefCell<HashMap<String, i32>>,
+    /// Uniform blocks and their binding points, reapplied after a reload
+    uniform_blocks: Vec<(String, u32)>
 }
 
 impl Shader {
-    pub fn new(vertex_path: &str, fragment_path: &str ) -> Self {
-        let mut shader = Self {id: 0};
+    pub fn new(backend: &Rc<dyn Backend>, vertex_path: &str, fragment_path: &str ) -> Result<Self, ShaderError> {
+        Self::with_preprocessor(backend, vertex_path, fragment_path, Preprocessor::new())
+    }
 
-        let vertex_string = read_to_string(vertex_path).unwrap_or_else(|_| panic!("Failed to read vertex shader file: {}", vertex_path));
-        let fragment_string = read_to_string(fragment_path).unwrap_or_else(|_| panic!("Failed to read fragment shader file: {}", fragment_path));
-        
-        let v_shader_code = CString::new(vertex_string.as_bytes()).unwrap();
-        let f_shader_code = CString::new(fragment_string.as_bytes()).unwrap();
-        
-        let vertex_shader = vert_shader_from_source(&v_shader_code).unwrap();
-        let fragment_shader = frag_shader_from_source(&f_shader_code).unwrap();
+    /// Creates a shader whose sources are run through `preprocessor`, e.g. to inject `#define`s
+    pub fn with_preprocessor(backend: &Rc<dyn Backend>, vertex_path: &str, fragment_path: &str, preprocessor: Preprocessor) -> Result<Self, ShaderError> {
+        let sources = ShaderSources::Files {
+            vertex: PathBuf::from(vertex_path),
+            fragment: PathBuf::from(fragment_path)
+        };
+        Self::build(backend, sources, preprocessor)
+    }
+
+    /// Creates a shader from sources in memory, used for the engine's built in shaders.
+    /// `#include`s are not resolved and there is nothing to watch.
+    pub fn from_source(backend: &Rc<dyn Backend>, name: &str, vertex_source: &str, fragment_source: &str) -> Result<Self, ShaderError> {
+        let sources = ShaderSources::Embedded {
+            name: name.to_string(),
+            vertex: vertex_source.to_string(),
+            fragment: fragment_source.to_string()
+        };
+        Self::build(backend, sources, Preprocessor::new())
+    }
 
-        unsafe {
-            let id = gl::CreateProgram();
+    fn build(backend: &Rc<dyn Backend>, sources: ShaderSources, preprocessor: Preprocessor) -> Result<Self, ShaderError> {
+        let mut source_files = Vec::new();
+        let id = build_program(backend.as_ref(), &preprocessor, &sources, &mut source_files)?;
+
+        Ok(Self {
+            id,
+            backend: backend.clone(),
+            sources,
+            preprocessor,
+            source_files,
+            watched_modified: None,
+            uniform_locations: RefCell::new(HashMap::new()),
+            uniform_blocks: Vec::new()
+        })
+    }
 
-            gl::AttachShader(id, vertex_shader);
-            gl::AttachShader(id, fragment_shader);
+    /// Defines used when building the program, changes take effect on the next `reload`
+    pub fn preprocessor_mut(&mut self) -> &mut Preprocessor {
+        &mut self.preprocessor
+    }
 
-            gl::LinkProgram(id);
+    /// Starts watching the source files, see `reload_if_changed`
+    pub fn watch(&mut self) {
+        self.watched_modified = Some(self.sources_modified());
+    }
 
-            shader.check_compile_errors(id, "PROGRAM");
+    pub fn unwatch(&mut self) {
+        self.watched_modified = None;
+    }
 
-            gl::DeleteShader(vertex_shader);
-            gl::DeleteShader(fragment_shader);
+    pub fn is_watching(&self) -> bool {
+        self.watched_modified.is_some()
+    }
 
-            shader.id = id;
+    /// Reloads the program if watching and any source file changed since the last check.
+    /// Returns true if a new program was linked.
+    pub fn reload_if_changed(&mut self) -> bool {
+        let Some(last_modified) = self.watched_modified else {
+            return false
+        };
+        let modified = self.sources_modified();
+        if modified <= last_modified {
+            return false
+        }
+        self.watched_modified = Some(modified);
+        match self.reload() {
+            Ok(()) => {
+                println!("Reloaded shader: {}", self.sources);
+                true
+            },
+            Err(err) => {
+                println!("ERROR::SHADER_RELOAD_ERROR\n{}\n \
+                          -- --------------------------------------------------- -- ",
+                         err);
+                false
+            }
+        }
+    }
 
+    /// Recompiles and relinks the program from its source files.
+    /// On failure the previous program stays in use, but files the failed build read or
+    /// couldn't find are watched too, so fixing them triggers the next reload.
+    /// Uniform values are not carried over to the new program.
+    pub fn reload(&mut self) -> Result<(), ShaderError> {
+        let mut source_files = Vec::new();
+        let id = match build_program(self.backend.as_ref(), &self.preprocessor, &self.sources, &mut source_files) {
+            Ok(id) => id,
+            Err(err) => {
+                if let ShaderError::Io { path, .. } = &err {
+                    source_files.push(path.clone());
+                }
+                // the build may have stopped before reading every file, so keep the old ones too
+                for path in source_files {
+                    if !self.source_files.contains(&path) {
+                        self.source_files.push(path);
+                    }
+                }
+                return Err(err)
+            }
         };
-        shader
+        self.backend.delete_program(self.id);
+        self.id = id;
+        self.source_files = source_files;
+        self.uniform_locations.borrow_mut().clear();
+        for (name, binding) in self.uniform_blocks.iter() {
+            self.apply_uniform_block(name, *binding);
+        }
+        Ok(())
+    }
+
+    /// Attaches the named uniform block to a buffer binding point, see `UniformBuffer`
+    pub fn bind_uniform_block(&mut self, name: &str, binding: u32) {
+        self.apply_uniform_block(name, binding);
+        match self.uniform_blocks.iter_mut().find(|(n, _)| n == name) {
+            Some(block) => block.1 = binding,
+            None => self.uniform_blocks.push((name.to_string(), binding))
+        }
+    }
+
+    fn apply_uniform_block(&self, name: &str, binding: u32) {
+        let index = self.backend.uniform_block_index(self.id, name);
+        if index == gl::INVALID_INDEX {
+            println!("WARNING::SHADER: uniform block \"{}\" not found in {}", name, self.sources);
+            return
+        }
+        self.backend.uniform_block_binding(self.id, index, binding)
     }
 
-    pub unsafe fn use_program(&self) {
-        gl::UseProgram(self.id)
+    fn sources_modified(&self) -> SystemTime {
+        self.source_files
+            .iter()
+            .filter_map(|path| metadata(path).and_then(|meta| meta.modified()).ok())
+            .max()
+            .unwrap_or(SystemTime::UNIX_EPOCH)
+    }
+
+    pub fn use_program(&self) {
+        self.backend.use_program(self.id)
     }
 
     #[allow(dead_code)]
-    pub unsafe fn set_bool(&self, name: &CStr, value: bool) {
-        gl::Uniform1i(gl::GetUniformLocation(self.id, name.as_ptr()), value as i32)
+    pub fn set_bool(&self, name: &str, value: bool) {
+        self.set_uniform(name, UniformValue::Int(value as i32))
     }
 
     #[allow(dead_code)]
-    pub unsafe fn set_int(&self, name: &CStr, value: i32) {
-        gl::Uniform1i(gl::GetUniformLocation(self.id, name.as_ptr()), value)
+    pub fn set_int(&self, name: &str, value: i32) {
+        self.set_uniform(name, UniformValue::Int(value))
     }
 
     #[allow(dead_code)]
-    pub unsafe fn set_float(&self, name: &CStr, value: f32) {
-        gl::Uniform1f(gl::GetUniformLocation(self.id, name.as_ptr()), value)
+    pub fn set_float(&self, name: &str, value: f32) {
+        self.set_uniform(name, UniformValue::Float(value))
+    }
+
+    pub fn set_vec2(&self, name: &str, x: f32, y: f32) {
+        self.set_uniform(name, UniformValue::Vec2([x, y]))
     }
 
     #[allow(dead_code)]
-    pub unsafe fn set_vector3(&self, name: &CStr, value: &Vec3) {
-        gl::Uniform3fv(gl::GetUniformLocation(self.id, name.as_ptr()), 1, value.as_ptr())
+    pub fn set_vector3(&self, name: &str, value: &Vec3) {
+        self.set_uniform(name, UniformValue::Vec3([value.x, value.y, value.z]))
     }
 
     #[allow(dead_code)]
-    pub unsafe fn set_vec3(&self, name: &CStr, x: f32, y: f32, z: f32) {
-        gl::Uniform3f(gl::GetUniformLocation(self.id, name.as_ptr()), x, y, z)
+    pub fn set_vec3(&self, name: &str, x: f32, y: f32, z: f32) {
+        self.set_uniform(name, UniformValue::Vec3([x, y, z]))
     }
 
-    pub unsafe fn set_vec4(&self, name: &str, x: f32, y: f32, z: f32, w: f32) {
-        let c_name = CString::new(name).unwrap();
-        gl::Uniform4f(gl::GetUniformLocation(self.id, c_name.as_ptr()), x, y, z, w)
+    pub fn set_vec4(&self, name: &str, x: f32, y: f32, z: f32, w: f32) {
+        self.set_uniform(name, UniformValue::Vec4([x, y, z, w]))
     }
 
-    pub unsafe fn set_mat4(&self, name: &str, mat: &Mat4) {
-        let c_name = CString::new(name).unwrap();
-        gl::UniformMatrix4fv(gl::GetUniformLocation(self.id, c_name.as_ptr()), 1, gl::FALSE, mat.as_ptr())
+    pub fn set_mat4(&self, name: &str, mat: &Mat4) {
+        let mut value = [0.0; 16];
+        value.copy_from_slice(mat.as_slice());
+        self.set_uniform(name, UniformValue::Mat4(value))
     }
 
-    unsafe fn check_compile_errors(&self, shader: u32, type_: &str) {
-        let mut success = gl::FALSE as GLint;
-        let mut info_log: Vec<u8> = vec![0; 1024];
-        info_log.set_len(1024 - 1); // subtract 1 to skip the trailing null character
-        if type_ != "PROGRAM" {
-            gl::GetShaderiv(shader, gl::COMPILE_STATUS, &mut success);
-            if success != gl::TRUE as GLint {
-                gl::GetShaderInfoLog(shader, 1024, std::ptr::null_mut(), info_log.as_mut_ptr() as *mut GLchar);
-                println!("ERROR::SHADER_COMPILATION_ERROR of type: {}\n{}\n \
-                          -- --------------------------------------------------- -- ",
-                         type_,
-                         std::str::from_utf8(&info_log).unwrap());
-            }
+    /// Location of a uniform, looked up once per program.
+    /// Warns the first time a uniform that doesn't exist (or was optimised out) is asked for.
+    pub fn uniform_location(&self, name: &str) -> i32 {
+        if let Some(location) = self.uniform_locations.borrow().get(name) {
+            return *location
+        }
+        let location = self.backend.uniform_location(self.id, name);
+        if location == -1 {
+            println!("WARNING::SHADER: uniform \"{}\" not found in {}", name, self.sources);
+        }
+        self.uniform_locations.borrow_mut().insert(name.to_string(), location);
+        location
+    }
 
-        } else {
-            gl::GetProgramiv(shader, gl::LINK_STATUS, &mut success);
-            if success != gl::TRUE as GLint {
-                gl::GetProgramInfoLog(shader, 1024, std::ptr::null_mut(), info_log.as_mut_ptr() as *mut GLchar);
-                println!("ERROR::PROGRAM_LINKING_ERROR of type: {}\n{}\n \
-                          -- --------------------------------------------------- -- ",
-                         type_,
-                         std::str::from_utf8(&info_log).unwrap());
-            }
+    fn set_uniform(&self, name: &str, value: UniformValue) {
+        let location = self.uniform_location(name);
+        if location != -1 {
+            self.backend.set_uniform(location, value)
         }
+    }
+}
 
+impl Drop for Shader {
+    fn drop(&mut self) {
+        self.backend.delete_program(self.id)
     }
 }
 
-pub fn vert_shader_from_source(source: &CStr) -> Result<GLuint, String> {
-    shader_from_source(source, VERTEX_SHADER)
+pub fn vert_shader_from_source(backend: &dyn Backend, source: &str) -> Result<GLuint, String> {
+    backend.compile_shader(VERTEX_SHADER, source)
 }
 
-pub fn frag_shader_from_source(source: &CStr) -> Result<GLuint, String> {
-    shader_from_source(source, FRAGMENT_SHADER)
+pub fn frag_shader_from_source(backend: &dyn Backend, source: &str) -> Result<GLuint, String> {
+    backend.compile_shader(FRAGMENT_SHADER, source)
 }
 
+/// Compiles a preprocessed file, adding every file read to `source_files`
+fn compile_file(backend: &dyn Backend, preprocessor: &Preprocessor, stage: ShaderStage, path: &Path, source_files: &mut Vec<PathBuf>) -> Result<GLuint, ShaderError> {
+    let source = preprocessor.process(path)?;
+    source_files.extend(source.files.iter().cloned());
+    backend.compile_shader(stage.gl_enum(), &source.source).map_err(|log| ShaderError::Compile { stage, path: path.to_path_buf(), log: source.map_log(&log) })
+}
 
-fn shader_from_source(source: &CStr, type_: GLenum) -> Result<GLuint, String> {
-    let id = unsafe { gl::CreateShader(type_) };
+fn compile_stage(backend: &dyn Backend, preprocessor: &Preprocessor, stage: ShaderStage, sources: &ShaderSources, source_files: &mut Vec<PathBuf>) -> Result<GLuint, ShaderError> {
+    match sources {
+        ShaderSources::Files { vertex, fragment } => {
+            let path = match stage {
+                ShaderStage::Vertex => vertex,
+                ShaderStage::Fragment => fragment
+            };
+            compile_file(backend, preprocessor, stage, path, source_files)
+        },
+        ShaderSources::Embedded { name, vertex, fragment } => {
+            let source = match stage {
+                ShaderStage::Vertex => vertex,
+                ShaderStage::Fragment => fragment
+            };
+            backend.compile_shader(stage.gl_enum(), source).map_err(|log| ShaderError::Compile { stage, path: PathBuf::from(name), log })
+        }
+    }
+}
 
-    unsafe {
-        gl::ShaderSource(id, 1, &source.as_ptr(), std::ptr::null());
-        gl::CompileShader(id)
+/// Builds the program, adding every file read to `source_files` even if it fails
+fn build_program(backend: &dyn Backend, preprocessor: &Preprocessor, sources: &ShaderSources, source_files: &mut Vec<PathBuf>) -> Result<u32, ShaderError> {
+    let vertex_shader = compile_stage(backend, preprocessor, ShaderStage::Vertex, sources, source_files)?;
+    let fragment_shader = match compile_stage(backend, preprocessor, ShaderStage::Fragment, sources, source_files) {
+        Ok(shader) => shader,
+        Err(err) => {
+            backend.delete_shader(vertex_shader);
+            return Err(err)
+        }
     };
 
-    let mut success: gl::types::GLint = 1;
+    let program = backend.link_program(&[vertex_shader, fragment_shader]);
 
-    unsafe {
-        gl::GetShaderiv(id, gl::COMPILE_STATUS, &mut success);
-    }
+    backend.delete_shader(vertex_shader);
+    backend.delete_shader(fragment_shader);
+
+    program.map_err(ShaderError::Link)
+}
 
-    if success == 0 {
-        let mut len: gl::types::GLint = 0;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::{Command, NullBackend};
+
+    #[test]
+    fn uniform_locations_are_looked_up_once_per_name() {
+        let null = Rc::new(NullBackend::new());
+        let backend: Rc<dyn Backend> = null.clone();
+        let shader = Shader::from_source(&backend, "test", "void main() {}", "void main() {}").unwrap();
+        null.take_commands();
+
+        shader.set_float("time", 1.0);
+        shader.set_float("time", 2.0);
+        shader.set_int("mode", 1);
+        shader.set_float("time", 3.0);
+
+        let lookups: Vec<_> = null.commands().into_iter()
+            .filter_map(|command| match command {
+                Command::UniformLocation { program, name } => Some((program, name)),
+                _ => None
+            })
+            .collect();
+        assert_eq!(lookups, vec![(shader.id, "time".to_string()), (shader.id, "mode".to_string())]);
+        let time = shader.uniform_location("time");
+        assert_eq!(null.uniform_name(time).as_deref(), Some("time"));
+        assert_eq!(null.commands().last(), Some(&Command::SetUniform { location: time, value: UniformValue::Float(3.0) }));
+    }
 
-        unsafe {
-            gl::GetShaderiv(id, gl::INFO_LOG_LENGTH, &mut len);
-        }
-        
-        let mut buffer: Vec<u8> = Vec::with_capacity(len as usize + 1);
-        
-        buffer.extend([b' '].iter().cycle().take(len as usize));
-        
-        let error = create_whitespace_cstring_with_len(len as usize);
-
-        unsafe {
-            gl::GetShaderInfoLog(
-                id,
-                len,
-                std::ptr::null_mut(),
-                error.as_ptr() as *mut gl::types::GLchar
-            );
-        }
-        return Err(error.to_string_lossy().into_owned());
+    #[test]
+    fn failed_reload_watches_new_includes() {
+        let dir = std::env::temp_dir().join(format!("shader_reload_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (vertex, fragment) = (dir.join("test.vert"), dir.join("test.frag"));
+        std::fs::write(&vertex, "void main() {}").unwrap();
+        std::fs::write(&fragment, "void main() {}").unwrap();
+        let backend: Rc<dyn Backend> = Rc::new(NullBackend::new());
+        let mut shader = Shader::new(&backend, vertex.to_str().unwrap(), fragment.to_str().unwrap()).unwrap();
+
+        std::fs::write(&fragment, "#include \"lighting.glsl\"\nvoid main() {}").unwrap();
+        let result = shader.reload();
+        let watched = shader.source_files.clone();
+        std::fs::write(dir.join("lighting.glsl"), "").unwrap();
+        let fixed = shader.reload();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(matches!(result, Err(ShaderError::Io { .. })));
+        assert!(watched.contains(&dir.join("lighting.glsl")));
+        assert!(watched.contains(&vertex) && watched.contains(&fragment));
+        assert!(fixed.is_ok());
     }
-    Ok(id)
 }
-
-fn create_whitespace_cstring_with_len(len: usize) -> CString {
-    // allocate buffer of correct size
-    let mut buffer: Vec<u8> = Vec::with_capacity(len + 1);
-    // fill it with len spaces
-    buffer.extend([b' '].iter().cycle().take(len));
-    // convert buffer to CString
-    unsafe { CString::from_vec_unchecked(buffer) }
-}
\ No newline at end of file
@@ -0,0 +1,278 @@
+use std::rc::Rc;
+
+use glm::{Mat4, Vec3, Vec4};
+use thiserror::Error;
+
+use crate::backend::Backend;
+use crate::framebuffer::{Framebuffer, FramebufferError};
+use crate::shader::{Shader, ShaderError};
+use crate::texture::TextureFormat;
+
+/// Most cascades the sampling uniforms have room for
+pub const MAX_CASCADES: usize = 4;
+
+const VERTEX_SOURCE: &str = "#version 330 core
+layout (location = 0) in vec3 Position;
+
+uniform mat4 lightSpace;
+uniform mat4 model;
+
+void main()
+{
+    gl_Position = lightSpace * model * vec4(Position, 1.0);
+}
+";
+
+const FRAGMENT_SOURCE: &str = "#version 330 core
+
+void main()
+{
+}
+";
+
+#[derive(Debug, Error)]
+pub enum ShadowError {
+    #[error(transparent)]
+    Framebuffer(#[from] FramebufferError),
+    #[error(transparent)]
+    Shader(#[from] ShaderError)
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ShadowSettings {
+    /// Between 1 and `MAX_CASCADES`
+    pub cascades: usize,
+    /// Width and height of each cascade's tile in the atlas
+    pub resolution: u32,
+    /// Distance from the camera the last cascade reaches, nothing further away is shadowed
+    pub distance: f32,
+    /// Blend between uniform (0.0) and logarithmic (1.0) splits of the view distance
+    pub split_lambda: f32,
+    /// How far behind a cascade towards the light casters are still rendered
+    pub caster_margin: f32,
+    /// Slope scaled offset applied while rendering depth, in `glPolygonOffset` units
+    pub slope_bias: f32,
+    /// Offset along the surface normal when sampling, in shadow map texels
+    pub normal_bias: f32,
+    /// Texels sampled either side of the centre, 1 gives a 3x3 kernel
+    pub pcf_radius: i32
+}
+
+impl Default for ShadowSettings {
+    fn default() -> Self {
+        Self {
+            cascades: 4,
+            resolution: 1024,
+            distance: 120.0,
+            split_lambda: 0.75,
+            caster_margin: 64.0,
+            slope_bias: 2.0,
+            normal_bias: 1.5,
+            pcf_radius: 1
+        }
+    }
+}
+
+/// A slice of the camera frustum with the orthographic light projection covering it
+#[derive(Debug, Clone, Copy)]
+pub struct Cascade {
+    /// World space to light clip space, used while rendering depth
+    pub view_projection: Mat4,
+    /// World space to shadow atlas coordinates, used while sampling
+    pub atlas_matrix: Mat4,
+    /// View distance the cascade ends at
+    pub split: f32,
+    /// Radius of the sphere bounding the frustum slice
+    pub radius: f32,
+    /// Atlas coordinates of the cascade's tile as min x, min y, max x, max y
+    pub tile: Vec4
+}
+
+impl Cascade {
+    /// Whether a sphere can cast a shadow into this cascade, for culling casters
+    pub fn contains_sphere(&self, centre: &Vec3, radius: f32) -> bool {
+        let clip = self.view_projection * Vec4::new(centre.x, centre.y, centre.z, 1.0);
+        let extent = 1.0 + radius / self.radius;
+        clip.x.abs() <= extent && clip.y.abs() <= extent
+    }
+}
+
+/// Cascaded shadow maps for a single directional light. The cascades share one
+/// depth atlas, laid out two tiles wide, and are fitted to bounding spheres of
+/// the camera frustum so their size never changes as the camera turns. Each
+/// cascade is snapped to its texel grid to stop edges shimmering while moving.
+///
+/// Shaders sampling the shadows declare these uniforms, set by `bind`:
+/// `sampler2DShadow shadowMap`, `mat4 cascadeMatrices[4]`, `vec4 cascadeTiles[4]`,
+/// `vec4 cascadeSplits`, `vec4 cascadeTexelSizes`, `int cascadeCount`,
+/// `vec2 shadowTexelSize`, `float shadowNormalBias` and `int shadowPcfRadius`.
+pub struct ShadowMap {
+    /// Skips rendering and sets `cascadeCount` to 0 so everything is lit
+    pub enabled: bool,
+    settings: ShadowSettings,
+    cascades: Vec<Cascade>,
+    framebuffer: Framebuffer,
+    shader: Shader,
+    backend: Rc<dyn Backend>
+}
+
+impl ShadowMap {
+    pub fn new(backend: &Rc<dyn Backend>, settings: ShadowSettings) -> Result<Self, ShadowError> {
+        let settings = ShadowSettings { cascades: settings.cascades.clamp(1, MAX_CASCADES), ..settings };
+        let (columns, rows) = atlas_grid(settings.cascades);
+        let size = (settings.resolution * columns, settings.resolution * rows);
+        let framebuffer = Framebuffer::new(backend, size, None, Some(TextureFormat::Depth24))?;
+        if let Some(depth) = framebuffer.depth() {
+            // linear filtering on a comparison sampler gives a free 2x2 PCF per tap
+            depth.bind(0);
+            backend.tex_parameter(gl::TEXTURE_2D, gl::TEXTURE_COMPARE_MODE, gl::COMPARE_REF_TO_TEXTURE as i32);
+            backend.tex_parameter(gl::TEXTURE_2D, gl::TEXTURE_COMPARE_FUNC, gl::LEQUAL as i32);
+            depth.unbind(0);
+        }
+
+        Ok(Self {
+            enabled: true,
+            settings,
+            cascades: Vec::with_capacity(settings.cascades),
+            framebuffer,
+            shader: Shader::from_source(backend, "shadow depth", VERTEX_SOURCE, FRAGMENT_SOURCE)?,
+            backend: backend.clone()
+        })
+    }
+
+    pub fn settings(&self) -> &ShadowSettings {
+        &self.settings
+    }
+
+    /// Cascades fitted by the last `update`, nearest first
+    pub fn cascades(&self) -> &[Cascade] {
+        &self.cascades
+    }
+
+    /// Refits the cascades to the camera. `fov_y` is the vertical field of view in
+    /// radians and `light_direction` points towards the light.
+    pub fn update(&mut self, view: &Mat4, fov_y: f32, aspect: f32, near: f32, light_direction: &Vec3) {
+        let inverse_view = view.try_inverse().unwrap_or_else(Mat4::identity);
+        let light_direction = light_direction.normalize();
+        // a look at matrix breaks down when looking along its up vector
+        let up = if light_direction.y.abs() > 0.99 { Vec3::z() } else { Vec3::y() };
+        let tan_half_fov = (fov_y / 2.0).tan();
+        let count = self.settings.cascades;
+        let (columns, rows) = atlas_grid(count);
+        let resolution = self.settings.resolution as f32;
+
+        self.cascades.clear();
+        let mut slice_near = near;
+        for i in 0..count {
+            let slice_far = split_distance(near, self.settings.distance, (i + 1) as f32 / count as f32, self.settings.split_lambda);
+
+            let mut corners = [Vec3::zeros(); 8];
+            for (j, corner) in corners.iter_mut().enumerate() {
+                let depth = if j < 4 { slice_near } else { slice_far };
+                let x = if j & 1 == 0 { -1.0 } else { 1.0 };
+                let y = if j & 2 == 0 { -1.0 } else { 1.0 };
+                let view_position = Vec4::new(x * depth * tan_half_fov * aspect, y * depth * tan_half_fov, -depth, 1.0);
+                *corner = (inverse_view * view_position).xyz();
+            }
+            let centre = corners.iter().sum::<Vec3>() / 8.0;
+            let radius = corners.iter().map(|corner| glm::distance(corner, &centre)).fold(0.0, f32::max);
+            // rounding keeps the projection from changing size with floating point noise
+            let radius = (radius * 16.0).ceil() / 16.0;
+
+            let eye = centre + light_direction * (radius + self.settings.caster_margin);
+            let light_view = glm::look_at(&eye, &centre, &up);
+            let mut projection = glm::ortho(-radius, radius, -radius, radius, 0.0, 2.0 * radius + self.settings.caster_margin);
+
+            // move the projection by under a texel so the world origin lands on a texel,
+            // the light's rotation is fixed so every other point stays put relative to texels
+            let origin = (projection * light_view * Vec4::new(0.0, 0.0, 0.0, 1.0)).xy() * (resolution / 2.0);
+            let offset = (origin.map(f32::round) - origin) * (2.0 / resolution);
+            projection[(0, 3)] += offset.x;
+            projection[(1, 3)] += offset.y;
+            let view_projection = projection * light_view;
+
+            let tile_size = glm::vec2(1.0 / columns as f32, 1.0 / rows as f32);
+            let tile_min = glm::vec2((i as u32 % columns) as f32, (i as u32 / columns) as f32).component_mul(&tile_size);
+            // clip space to 0..1, then into the tile
+            let atlas_matrix = glm::translation(&Vec3::new(tile_min.x, tile_min.y, 0.0))
+                * glm::scaling(&Vec3::new(tile_size.x, tile_size.y, 1.0))
+                * glm::translation(&Vec3::new(0.5, 0.5, 0.5))
+                * glm::scaling(&Vec3::new(0.5, 0.5, 0.5))
+                * view_projection;
+
+            self.cascades.push(Cascade {
+                view_projection,
+                atlas_matrix,
+                split: slice_far,
+                radius,
+                tile: Vec4::new(tile_min.x, tile_min.y, tile_min.x + tile_size.x, tile_min.y + tile_size.y)
+            });
+            slice_near = slice_far;
+        }
+    }
+
+    /// Renders depth for each cascade into its tile. `draw` is called once per cascade
+    /// and should draw the casters with the given shader, setting its `model` uniform.
+    /// Leaves the default framebuffer bound, the caller restores its own target and viewport.
+    pub fn render(&self, mut draw: impl FnMut(&Shader, &Cascade)) {
+        if !self.enabled {
+            return
+        }
+        self.framebuffer.bind();
+        self.backend.clear(gl::DEPTH_BUFFER_BIT);
+        self.backend.enable(gl::DEPTH_TEST);
+        self.backend.enable(gl::POLYGON_OFFSET_FILL);
+        self.backend.polygon_offset(self.settings.slope_bias, 1.0);
+        self.shader.use_program();
+
+        let resolution = self.settings.resolution as i32;
+        let (columns, _) = atlas_grid(self.settings.cascades);
+        for (i, cascade) in self.cascades.iter().enumerate() {
+            let (column, row) = (i as u32 % columns, i as u32 / columns);
+            self.backend.viewport(column as i32 * resolution, row as i32 * resolution, resolution, resolution);
+            self.shader.set_mat4("lightSpace", &cascade.view_projection);
+            draw(&self.shader, cascade);
+        }
+
+        self.backend.disable(gl::POLYGON_OFFSET_FILL);
+        self.backend.bind_framebuffer(gl::FRAMEBUFFER, 0);
+    }
+
+    /// Binds the shadow atlas to `unit` and sets the sampling uniforms on `shader`
+    pub fn bind(&self, shader: &Shader, unit: u32) {
+        if let Some(depth) = self.framebuffer.depth() {
+            depth.bind(unit);
+        }
+        shader.use_program();
+        shader.set_int("shadowMap", unit as i32);
+        shader.set_int("cascadeCount", if self.enabled { self.cascades.len() as i32 } else { 0 });
+
+        let mut splits = [0.0; MAX_CASCADES];
+        let mut texel_sizes = [0.0; MAX_CASCADES];
+        let resolution = self.settings.resolution as f32;
+        for (i, cascade) in self.cascades.iter().enumerate() {
+            shader.set_mat4(&format!("cascadeMatrices[{}]", i), &cascade.atlas_matrix);
+            shader.set_vec4(&format!("cascadeTiles[{}]", i), cascade.tile.x, cascade.tile.y, cascade.tile.z, cascade.tile.w);
+            splits[i] = cascade.split;
+            texel_sizes[i] = 2.0 * cascade.radius / resolution;
+        }
+        shader.set_vec4("cascadeSplits", splits[0], splits[1], splits[2], splits[3]);
+        shader.set_vec4("cascadeTexelSizes", texel_sizes[0], texel_sizes[1], texel_sizes[2], texel_sizes[3]);
+        shader.set_vec2("shadowTexelSize", 1.0 / self.framebuffer.size.0 as f32, 1.0 / self.framebuffer.size.1 as f32);
+        shader.set_float("shadowNormalBias", self.settings.normal_bias);
+        shader.set_int("shadowPcfRadius", self.settings.pcf_radius);
+    }
+}
+
+/// Columns and rows of tiles in the atlas
+fn atlas_grid(cascades: usize) -> (u32, u32) {
+    let columns = cascades.min(2) as u32;
+    (columns, (cascades as u32).div_ceil(columns))
+}
+
+/// The practical split scheme, mixing logarithmic and uniform splits by `lambda`
+fn split_distance(near: f32, far: f32, fraction: f32, lambda: f32) -> f32 {
+    let logarithmic = near * (far / near).powf(fraction);
+    let uniform = near + (far - near) * fraction;
+    lambda * logarithmic + (1.0 - lambda) * uniform
+}
@@ -0,0 +1,151 @@
+use std::{ops::Range, rc::Rc};
+
+use glm::{Vec2, Vec4};
+use image::{DynamicImage, Rgba, RgbaImage};
+
+use crate::backend::Backend;
+use crate::shader::{Shader, ShaderError};
+use crate::text::{Font, TextStyle};
+use crate::texture::{Texture, TextureSettings};
+use crate::texture_atlas::UvRect;
+use crate::vertex_array::Mesh;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct SpriteVertex {
+    position: [f32; 2],
+    tex_coord: [f32; 2],
+    colour: [u8; 4]
+}
+crate::impl_vertex!(SpriteVertex { 0 => position, 1 => tex_coord, 2 => colour });
+
+const VERTEX_SOURCE: &str = "#version 330 core
+layout (location = 0) in vec2 Position;
+layout (location = 1) in vec2 aTexCoord;
+layout (location = 2) in vec4 aColour;
+
+out vec2 TexCoord;
+out vec4 Colour;
+
+// framebuffer size in pixels, sprites are positioned from the top left
+uniform vec2 screenSize;
+
+void main()
+{
+    vec2 ndc = Position / screenSize * 2.0 - 1.0;
+    gl_Position = vec4(ndc.x, -ndc.y, 0.0, 1.0);
+    TexCoord = aTexCoord;
+    Colour = aColour;
+}
+";
+
+const FRAGMENT_SOURCE: &str = "#version 330 core
+out vec4 Color;
+
+in vec2 TexCoord;
+in vec4 Colour;
+
+uniform sampler2D spriteTexture;
+
+void main()
+{
+    Color = texture(spriteTexture, TexCoord) * Colour;
+}
+";
+
+/// Vertices sharing a texture, drawn in one call
+#[derive(Debug)]
+struct DrawCall {
+    texture: u32,
+    vertices: Range<usize>
+}
+
+/// Collects coloured and textured 2D quads and draws them in as few calls as possible,
+/// starting a new call only when the texture changes. Quads are drawn in the order they
+/// were queued. Positions are in framebuffer pixels from the top left corner.
+pub struct SpriteBatch {
+    shader: Shader,
+    mesh: Mesh<SpriteVertex>,
+    vertices: Vec<SpriteVertex>,
+    draw_calls: Vec<DrawCall>,
+    /// 1x1 white texture for untextured quads
+    white: Texture,
+    backend: Rc<dyn Backend>
+}
+
+impl SpriteBatch {
+    pub fn new(backend: &Rc<dyn Backend>) -> Result<Self, ShaderError> {
+        Ok(Self {
+            shader: Shader::from_source(backend, "sprite batch", VERTEX_SOURCE, FRAGMENT_SOURCE)?,
+            mesh: Mesh::new(backend, &[], None, gl::STREAM_DRAW),
+            vertices: Vec::new(),
+            draw_calls: Vec::new(),
+            white: Texture::from_image(backend, &DynamicImage::ImageRgba8(RgbaImage::from_pixel(1, 1, Rgba([255; 4]))), TextureSettings::pixelated()),
+            backend: backend.clone()
+        })
+    }
+
+    /// Queues a solid rectangle
+    pub fn rect(&mut self, min: Vec2, max: Vec2, colour: Vec4) {
+        let white = self.white.id;
+        self.quad(white, min, max, UvRect::FULL, colour);
+    }
+
+    /// Queues part of a 2D texture stretched over a rectangle, tinted by `colour`
+    pub fn image(&mut self, texture: &Texture, min: Vec2, max: Vec2, uv: UvRect, colour: Vec4) {
+        self.quad(texture.id, min, max, uv, colour);
+    }
+
+    /// Queues `text` with its top left corner at `position`
+    pub fn text(&mut self, font: &Font, text: &str, position: Vec2, style: &TextStyle) {
+        font.layout(text, style.scale, style.max_width, |pen, glyph| {
+            let min = position + pen + glyph.offset * style.scale;
+            self.quad(font.texture.id, min, min + glyph.size * style.scale, glyph.uv, style.colour);
+        });
+    }
+
+    /// Draws everything queued since the last call over the current frame and clears the queue
+    pub fn draw(&mut self, screen_size: (i32, i32)) {
+        if self.vertices.is_empty() {
+            return
+        }
+        self.mesh.update(&self.vertices, None, gl::STREAM_DRAW);
+        self.vertices.clear();
+
+        self.backend.disable(gl::DEPTH_TEST);
+        self.backend.enable(gl::BLEND);
+        self.backend.blend_func(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+        self.shader.use_program();
+        self.shader.set_int("spriteTexture", 0);
+        self.shader.set_vec2("screenSize", screen_size.0 as f32, screen_size.1 as f32);
+        self.backend.active_texture(0);
+        for draw_call in self.draw_calls.drain(..) {
+            self.backend.bind_texture(gl::TEXTURE_2D, draw_call.texture);
+            self.mesh.draw_range(gl::TRIANGLES, draw_call.vertices);
+        }
+        self.backend.bind_texture(gl::TEXTURE_2D, 0);
+        self.backend.disable(gl::BLEND);
+        self.backend.enable(gl::DEPTH_TEST);
+    }
+
+    fn quad(&mut self, texture: u32, min: Vec2, max: Vec2, uv: UvRect, colour: Vec4) {
+        let start = self.vertices.len();
+        match self.draw_calls.last_mut() {
+            Some(draw_call) if draw_call.texture == texture => draw_call.vertices.end = start + 6,
+            _ => self.draw_calls.push(DrawCall { texture, vertices: start..start + 6 })
+        }
+
+        let colour = colour.map(|channel| (channel.clamp(0.0, 1.0) * 255.0).round() as u8);
+        let colour = [colour.x, colour.y, colour.z, colour.w];
+        let (uv_min, uv_max) = (uv.min, uv.max);
+        let corner = |x: f32, y: f32, u: f32, v: f32| SpriteVertex { position: [x, y], tex_coord: [u, v], colour };
+        self.vertices.extend([
+            corner(min.x, min.y, uv_min.x, uv_min.y),
+            corner(min.x, max.y, uv_min.x, uv_max.y),
+            corner(max.x, max.y, uv_max.x, uv_max.y),
+            corner(min.x, min.y, uv_min.x, uv_min.y),
+            corner(max.x, max.y, uv_max.x, uv_max.y),
+            corner(max.x, min.y, uv_max.x, uv_min.y)
+        ]);
+    }
+}
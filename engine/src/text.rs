@@ -0,0 +1,289 @@
+use std::{collections::HashMap, fs::read_to_string, io, path::{Path, PathBuf}, rc::Rc};
+
+use glm::{Vec2, Vec4};
+use image::GenericImageView;
+use thiserror::Error;
+
+use crate::backend::Backend;
+use crate::shader::ShaderError;
+use crate::sprite_batch::SpriteBatch;
+use crate::texture::{Texture, TextureError, TextureSettings};
+use crate::texture_atlas::UvRect;
+
+#[derive(Debug, Error)]
+pub enum FontError {
+    #[error("Failed to read font file {}: {source}", path.display())]
+    Io {
+        path: PathBuf,
+        source: io::Error
+    },
+    #[error("{}:{line}: {message}", path.display())]
+    Parse {
+        path: PathBuf,
+        line: usize,
+        message: String
+    },
+    #[error(transparent)]
+    Texture(#[from] TextureError)
+}
+
+/// Where a character is in the font texture and how it sits on the line, in pixels
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Glyph {
+    pub uv: UvRect,
+    pub size: Vec2,
+    /// From the pen position at the top of the line to the top left of the glyph
+    pub offset: Vec2,
+    pub advance: f32
+}
+
+/// A bitmap font, loaded from a BMFont text file or a grid of glyphs
+#[derive(Debug)]
+pub struct Font {
+    pub texture: Texture,
+    glyphs: HashMap<char, Glyph>,
+    kerning: HashMap<(char, char), f32>,
+    /// Distance between lines in pixels
+    pub line_height: f32
+}
+
+impl Font {
+    /// Loads a texture of equally sized cells holding consecutive characters from
+    /// `first_char`, left to right then top to bottom. Every glyph is a full cell wide.
+    pub fn from_grid<P: AsRef<Path>>(backend: &Rc<dyn Backend>, path: P, cell_size: (u32, u32), first_char: char) -> Result<Self, FontError> {
+        let image = image::open(path).map_err(TextureError::from)?;
+        let (width, height) = image.dimensions();
+        let (columns, rows) = (width / cell_size.0, height / cell_size.1);
+        let cell = Vec2::new(cell_size.0 as f32, cell_size.1 as f32);
+
+        let mut glyphs = HashMap::new();
+        for index in 0..columns * rows {
+            let Some(character) = char::from_u32(first_char as u32 + index) else {
+                continue
+            };
+            let min = Vec2::new((index % columns * cell_size.0) as f32, (index / columns * cell_size.1) as f32);
+            glyphs.insert(character, Glyph {
+                uv: pixel_uv(min, cell, (width, height)),
+                size: cell,
+                offset: Vec2::zeros(),
+                advance: cell.x
+            });
+        }
+
+        Ok(Self {
+            texture: Texture::from_image(backend, &image, TextureSettings::pixelated()),
+            glyphs,
+            kerning: HashMap::new(),
+            line_height: cell.y
+        })
+    }
+
+    /// Loads a font in the BMFont text format. Only single page fonts are supported,
+    /// the page image is looked up next to the `.fnt` file.
+    pub fn from_bmfont<P: AsRef<Path>>(backend: &Rc<dyn Backend>, path: P) -> Result<Self, FontError> {
+        let path = path.as_ref();
+        let source = read_to_string(path).map_err(|source| FontError::Io { path: path.to_path_buf(), source })?;
+        let parse_error = |line: usize, message: String| FontError::Parse { path: path.to_path_buf(), line, message };
+
+        let mut line_height = 0.0;
+        let mut texture_size = (1, 1);
+        let mut page = None;
+        // glyph pixel rects are converted to UVs once the texture size is known
+        let mut chars = Vec::new();
+        let mut kerning = HashMap::new();
+
+        for (i, line) in source.lines().enumerate() {
+            let line_number = i + 1;
+            let Some((tag, values)) = parse_bmfont_line(line) else {
+                continue
+            };
+            let get = |key: &str| -> Result<f32, FontError> {
+                values
+                    .get(key)
+                    .ok_or_else(|| parse_error(line_number, format!("missing {} in {}", key, tag)))?
+                    .parse::<f32>()
+                    .map_err(|err| parse_error(line_number, format!("invalid {}: {}", key, err)))
+            };
+            match tag {
+                "common" => {
+                    line_height = get("lineHeight")?;
+                    texture_size = (get("scaleW")? as u32, get("scaleH")? as u32);
+                    if get("pages")? as u32 > 1 {
+                        return Err(parse_error(line_number, "fonts with more than one page are not supported".to_string()))
+                    }
+                },
+                "page" => {
+                    let file = values.get("file").ok_or_else(|| parse_error(line_number, "missing file in page".to_string()))?;
+                    page = Some(path.parent().unwrap_or(Path::new("")).join(file));
+                },
+                "char" => {
+                    let id = get("id")? as u32;
+                    let Some(character) = char::from_u32(id) else {
+                        continue
+                    };
+                    chars.push((character, Vec2::new(get("x")?, get("y")?), Vec2::new(get("width")?, get("height")?), Vec2::new(get("xoffset")?, get("yoffset")?), get("xadvance")?));
+                },
+                "kerning" => {
+                    let (Some(first), Some(second)) = (char::from_u32(get("first")? as u32), char::from_u32(get("second")? as u32)) else {
+                        continue
+                    };
+                    kerning.insert((first, second), get("amount")?);
+                },
+                _ => ()
+            }
+        }
+
+        let page = page.ok_or_else(|| parse_error(0, "no page image".to_string()))?;
+        let glyphs = chars.into_iter().map(|(character, position, size, offset, advance)| {
+            (character, Glyph { uv: pixel_uv(position, size, texture_size), size, offset, advance })
+        }).collect();
+
+        Ok(Self {
+            texture: Texture::from_path(backend, page, TextureSettings::pixelated())?,
+            glyphs,
+            kerning,
+            line_height
+        })
+    }
+
+    /// Falls back to `?` for characters the font doesn't have
+    pub fn glyph(&self, character: char) -> Option<&Glyph> {
+        self.glyphs.get(&character).or_else(|| self.glyphs.get(&'?'))
+    }
+
+    /// Extra advance between two characters, usually negative
+    pub fn kerning(&self, first: char, second: char) -> f32 {
+        self.kerning.get(&(first, second)).copied().unwrap_or(0.0)
+    }
+
+    /// Size of `text` in pixels at `scale`, see `layout`
+    pub fn measure(&self, text: &str, scale: f32, max_width: Option<f32>) -> Vec2 {
+        let mut size = Vec2::zeros();
+        self.layout(text, scale, max_width, |position, glyph| {
+            let end = position + glyph.offset * scale + glyph.size * scale;
+            size = size.sup(&end);
+        });
+        size.y = size.y.max(self.line_height * scale);
+        size
+    }
+
+    /// Positions each glyph of `text` relative to its top left corner, breaking lines at `\n`
+    /// and between words that would go past `max_width`. `place` is given the pen position
+    /// of each visible glyph, add the glyph's scaled offset to get its top left corner.
+    pub fn layout(&self, text: &str, scale: f32, max_width: Option<f32>, mut place: impl FnMut(Vec2, &Glyph)) {
+        let mut pen = Vec2::zeros();
+        let mut previous: Option<char> = None;
+        for (line_index, line) in text.split('\n').enumerate() {
+            if line_index > 0 {
+                pen = Vec2::new(0.0, pen.y + self.line_height * scale);
+                previous = None;
+            }
+            for (word_index, word) in line.split(' ').enumerate() {
+                if word_index > 0 {
+                    pen.x += self.advance(previous, ' ') * scale;
+                    previous = Some(' ');
+                }
+                if let Some(max_width) = max_width {
+                    let word_width: f32 = word.chars().map(|c| self.advance(None, c)).sum::<f32>() * scale;
+                    if pen.x > 0.0 && pen.x + word_width > max_width {
+                        pen = Vec2::new(0.0, pen.y + self.line_height * scale);
+                        previous = None;
+                    }
+                }
+                for character in word.chars() {
+                    pen.x += previous.map_or(0.0, |previous| self.kerning(previous, character)) * scale;
+                    if let Some(glyph) = self.glyph(character) {
+                        place(pen, glyph);
+                        pen.x += glyph.advance * scale;
+                    }
+                    previous = Some(character);
+                }
+            }
+        }
+    }
+
+    fn advance(&self, previous: Option<char>, character: char) -> f32 {
+        let kerning = previous.map_or(0.0, |previous| self.kerning(previous, character));
+        kerning + self.glyph(character).map_or(0.0, |glyph| glyph.advance)
+    }
+}
+
+/// Splits `tag key=value key="quoted value"` into the tag and its values
+fn parse_bmfont_line(line: &str) -> Option<(&str, HashMap<&str, &str>)> {
+    let line = line.trim();
+    let (tag, mut rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+    if tag.is_empty() {
+        return None
+    }
+    let mut values = HashMap::new();
+    loop {
+        rest = rest.trim_start();
+        let Some((key, after)) = rest.split_once('=') else {
+            break
+        };
+        let (value, after) = match after.strip_prefix('"') {
+            Some(quoted) => quoted.split_once('"').unwrap_or((quoted, "")),
+            None => after.split_once(char::is_whitespace).unwrap_or((after, ""))
+        };
+        values.insert(key.trim(), value);
+        rest = after;
+    }
+    Some((tag, values))
+}
+
+fn pixel_uv(position: Vec2, size: Vec2, (width, height): (u32, u32)) -> UvRect {
+    let texture_size = Vec2::new(width as f32, height as f32);
+    UvRect::new(position.component_div(&texture_size), (position + size).component_div(&texture_size))
+}
+
+/// How a piece of queued text is drawn
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextStyle {
+    pub colour: Vec4,
+    /// Multiplier on the font's pixel size
+    pub scale: f32,
+    /// Wraps between words past this width in pixels
+    pub max_width: Option<f32>
+}
+
+impl Default for TextStyle {
+    fn default() -> Self {
+        Self {
+            colour: Vec4::new(1.0, 1.0, 1.0, 1.0),
+            scale: 1.0,
+            max_width: None
+        }
+    }
+}
+
+/// Collects text drawn during a frame and draws it all in one call.
+/// Positions are in framebuffer pixels from the top left corner.
+pub struct TextRenderer {
+    /// Shared so the same font texture can also be used by the UI
+    pub font: Rc<Font>,
+    batch: SpriteBatch
+}
+
+impl TextRenderer {
+    pub fn new(backend: &Rc<dyn Backend>, font: Rc<Font>) -> Result<Self, ShaderError> {
+        Ok(Self {
+            font,
+            batch: SpriteBatch::new(backend)?
+        })
+    }
+
+    /// Queues `text` with its top left corner at `position`
+    pub fn queue(&mut self, text: &str, position: Vec2, style: &TextStyle) {
+        self.batch.text(&self.font, text, position, style);
+    }
+
+    /// Size `text` would take up if queued with `style`
+    pub fn measure(&self, text: &str, style: &TextStyle) -> Vec2 {
+        self.font.measure(text, style.scale, style.max_width)
+    }
+
+    /// Draws everything queued since the last call over the current frame and clears the queue
+    pub fn draw(&mut self, screen_size: (i32, i32)) {
+        self.batch.draw(screen_size);
+    }
+}
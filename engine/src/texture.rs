@@ -1,5 +1,437 @@
+use std::{fmt, path::Path, rc::Rc};
+
+use gl::types::GLenum;
+use image::DynamicImage;
+use thiserror::Error;
+
+use crate::backend::Backend;
+
+// from EXT_texture_filter_anisotropic, core in 4.6 but not in the generated bindings
+const TEXTURE_MAX_ANISOTROPY: GLenum = 0x84FE;
+const MAX_TEXTURE_MAX_ANISOTROPY: GLenum = 0x84FF;
+
+#[derive(Debug, Error)]
+pub enum TextureError {
+    #[error("Failed to load image: {0}")]
+    Image(#[from] image::ImageError),
+    #[error("Texture array layer {layer} is {actual:?}, expected {expected:?}")]
+    SizeMismatch {
+        layer: usize,
+        expected: (u32, u32),
+        actual: (u32, u32)
+    },
+    #[error("Texture array needs at least one layer")]
+    NoLayers,
+    #[error("Texture data is {actual} bytes, expected {expected} for {width}x{height} RGBA8 texels")]
+    DataSize {
+        width: u32,
+        height: u32,
+        expected: usize,
+        actual: usize
+    },
+    #[error("Layer {layer} is out of range, the texture has {layers}")]
+    LayerOutOfRange {
+        layer: u32,
+        layers: u32
+    },
+    #[error("Only texture arrays have layers to update")]
+    NotAnArray
+}
+
+/// Bytes in a loaded or updated texel
+const BYTES_PER_TEXEL: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Wrap {
+    Repeat,
+    MirroredRepeat,
+    ClampToEdge
+}
+
+impl Wrap {
+    fn gl_enum(&self) -> GLenum {
+        match self {
+            Wrap::Repeat => gl::REPEAT,
+            Wrap::MirroredRepeat => gl::MIRRORED_REPEAT,
+            Wrap::ClampToEdge => gl::CLAMP_TO_EDGE
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Filter {
+    Nearest,
+    Linear
+}
+
+/// Storage of textures that are rendered into, loaded textures are always RGBA8
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextureFormat {
+    Rgba8,
+    /// Half float colour, for values outside 0.0 to 1.0 before tonemapping
+    Rgba16F,
+    Depth24,
+    Depth32F
+}
+
+impl TextureFormat {
+    pub fn is_depth(&self) -> bool {
+        matches!(self, TextureFormat::Depth24 | TextureFormat::Depth32F)
+    }
+
+    fn internal_format(&self) -> GLenum {
+        match self {
+            TextureFormat::Rgba8 => gl::RGBA8,
+            TextureFormat::Rgba16F => gl::RGBA16F,
+            TextureFormat::Depth24 => gl::DEPTH_COMPONENT24,
+            TextureFormat::Depth32F => gl::DEPTH_COMPONENT32F
+        }
+    }
+
+    /// Format and type of the pixel data passed when allocating storage
+    fn pixel_format(&self) -> (GLenum, GLenum) {
+        match self {
+            TextureFormat::Rgba8 => (gl::RGBA, gl::UNSIGNED_BYTE),
+            TextureFormat::Rgba16F => (gl::RGBA, gl::FLOAT),
+            TextureFormat::Depth24 => (gl::DEPTH_COMPONENT, gl::UNSIGNED_INT),
+            TextureFormat::Depth32F => (gl::DEPTH_COMPONENT, gl::FLOAT)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextureSettings {
+    pub wrap: Wrap,
+    pub min_filter: Filter,
+    pub mag_filter: Filter,
+    /// Generates mipmaps and filters between them when `Some`
+    pub mipmap_filter: Option<Filter>,
+    /// Requested anisotropy, clamped to what the driver supports. 1.0 turns it off.
+    pub anisotropy: f32,
+    /// Colour data is stored in sRGB and converted to linear when sampled
+    pub srgb: bool
+}
+
+impl Default for TextureSettings {
+    fn default() -> Self {
+        Self {
+            wrap: Wrap::Repeat,
+            min_filter: Filter::Linear,
+            mag_filter: Filter::Linear,
+            mipmap_filter: Some(Filter::Linear),
+            anisotropy: 1.0,
+            srgb: false
+        }
+    }
+}
+
+impl TextureSettings {
+    /// Nearest filtering without mipmaps, for blocky pixel art
+    pub fn pixelated() -> Self {
+        Self {
+            min_filter: Filter::Nearest,
+            mag_filter: Filter::Nearest,
+            mipmap_filter: None,
+            ..Default::default()
+        }
+    }
+
+    /// Linear filtering clamped to the edges without mipmaps, for framebuffer attachments
+    pub fn render_target() -> Self {
+        Self {
+            wrap: Wrap::ClampToEdge,
+            mipmap_filter: None,
+            ..Default::default()
+        }
+    }
+
+    fn min_filter_enum(&self) -> GLenum {
+        match (self.min_filter, self.mipmap_filter) {
+            (Filter::Nearest, None) => gl::NEAREST,
+            (Filter::Linear, None) => gl::LINEAR,
+            (Filter::Nearest, Some(Filter::Nearest)) => gl::NEAREST_MIPMAP_NEAREST,
+            (Filter::Nearest, Some(Filter::Linear)) => gl::NEAREST_MIPMAP_LINEAR,
+            (Filter::Linear, Some(Filter::Nearest)) => gl::LINEAR_MIPMAP_NEAREST,
+            (Filter::Linear, Some(Filter::Linear)) => gl::LINEAR_MIPMAP_LINEAR
+        }
+    }
+
+    fn mag_filter_enum(&self) -> GLenum {
+        match self.mag_filter {
+            Filter::Nearest => gl::NEAREST,
+            Filter::Linear => gl::LINEAR
+        }
+    }
+
+    fn internal_format(&self) -> GLenum {
+        if self.srgb { gl::SRGB8_ALPHA8 } else { gl::RGBA8 }
+    }
+}
+
+/// A 2D texture or 2D texture array of RGBA8 texels
 pub struct Texture {
-  id: u32,
-  dimensions: (u32, u32),
-  
-}
\ No newline at end of file
+    pub id: u32,
+    /// `gl::TEXTURE_2D` or `gl::TEXTURE_2D_ARRAY`
+    pub target: GLenum,
+    pub dimensions: (u32, u32),
+    /// 1 for 2D textures
+    pub layers: u32,
+    settings: TextureSettings,
+    backend: Rc<dyn Backend>
+}
+
+impl Texture {
+    /// Creates a 2D texture, leaving the contents undefined if `data` is `None`.
+    /// `data` must be RGBA8 texels covering all of `dimensions`.
+    pub fn new_2d(backend: &Rc<dyn Backend>, dimensions: (u32, u32), data: Option<&[u8]>, settings: TextureSettings) -> Result<Self, TextureError> {
+        if let Some(data) = data {
+            check_data_size(dimensions, data)?;
+        }
+        Ok(Self::new_2d_unchecked(backend, dimensions, data, settings))
+    }
+
+    /// `data` has to be the right size, the driver reads past the end of it otherwise
+    fn new_2d_unchecked(backend: &Rc<dyn Backend>, dimensions: (u32, u32), data: Option<&[u8]>, settings: TextureSettings) -> Self {
+        let texture = Self::create(backend, gl::TEXTURE_2D, dimensions, 1, settings);
+        backend.tex_image_2d(
+            gl::TEXTURE_2D,
+            0,
+            settings.internal_format(),
+            dimensions.0 as i32,
+            dimensions.1 as i32,
+            gl::RGBA,
+            gl::UNSIGNED_BYTE,
+            data
+        );
+        texture.finish();
+        texture
+    }
+
+    /// Creates an empty 2D texture to attach to a framebuffer
+    pub fn new_render_target(backend: &Rc<dyn Backend>, dimensions: (u32, u32), format: TextureFormat, settings: TextureSettings) -> Self {
+        let texture = Self::create(backend, gl::TEXTURE_2D, dimensions, 1, settings);
+        let (pixel_format, pixel_type) = format.pixel_format();
+        backend.tex_image_2d(
+            gl::TEXTURE_2D,
+            0,
+            format.internal_format(),
+            dimensions.0 as i32,
+            dimensions.1 as i32,
+            pixel_format,
+            pixel_type,
+            None
+        );
+        texture.finish();
+        texture
+    }
+
+    /// Creates a 2D texture array with `layers` layers of the same size
+    pub fn new_2d_array(backend: &Rc<dyn Backend>, dimensions: (u32, u32), layers: u32, settings: TextureSettings) -> Self {
+        let texture = Self::create(backend, gl::TEXTURE_2D_ARRAY, dimensions, layers, settings);
+        backend.tex_image_3d(
+            gl::TEXTURE_2D_ARRAY,
+            0,
+            settings.internal_format(),
+            dimensions.0 as i32,
+            dimensions.1 as i32,
+            layers as i32,
+            gl::RGBA,
+            gl::UNSIGNED_BYTE,
+            None
+        );
+        texture.finish();
+        texture
+    }
+
+    pub fn from_image(backend: &Rc<dyn Backend>, image: &DynamicImage, settings: TextureSettings) -> Self {
+        let image = image.to_rgba8();
+        Self::new_2d_unchecked(backend, image.dimensions(), Some(image.as_raw()), settings)
+    }
+
+    /// Loads a 2D texture from any format `image` supports
+    pub fn from_path<P: AsRef<Path>>(backend: &Rc<dyn Backend>, path: P, settings: TextureSettings) -> Result<Self, TextureError> {
+        Ok(Self::from_image(backend, &image::open(path)?, settings))
+    }
+
+    /// Creates a 2D texture array with one layer per image, all images must be the same size
+    pub fn array_from_images(backend: &Rc<dyn Backend>, images: &[DynamicImage], settings: TextureSettings) -> Result<Self, TextureError> {
+        let first = images.first().ok_or(TextureError::NoLayers)?;
+        let expected = (first.width(), first.height());
+        for (layer, image) in images.iter().enumerate() {
+            let actual = (image.width(), image.height());
+            if actual != expected {
+                return Err(TextureError::SizeMismatch { layer, expected, actual })
+            }
+        }
+
+        let texture = Self::new_2d_array(backend, expected, images.len() as u32, settings);
+        for (layer, image) in images.iter().enumerate() {
+            texture.update_layer(layer as u32, image.to_rgba8().as_raw())?;
+        }
+        Ok(texture)
+    }
+
+    pub fn array_from_paths<P: AsRef<Path>>(backend: &Rc<dyn Backend>, paths: &[P], settings: TextureSettings) -> Result<Self, TextureError> {
+        let images = paths.iter().map(image::open).collect::<Result<Vec<_>, _>>()?;
+        Self::array_from_images(backend, &images, settings)
+    }
+
+    /// Replaces a region of a 2D texture with RGBA8 `data`, regenerating mipmaps
+    pub fn update_sub_image(&self, x: u32, y: u32, width: u32, height: u32, data: &[u8]) -> Result<(), TextureError> {
+        check_data_size((width, height), data)?;
+        self.bind(0);
+        self.backend.tex_sub_image_2d(self.target, 0, x as i32, y as i32, width as i32, height as i32, gl::RGBA, gl::UNSIGNED_BYTE, data);
+        self.regenerate_mipmaps();
+        Ok(())
+    }
+
+    /// Replaces a whole layer of a texture array with RGBA8 `data`, regenerating mipmaps
+    pub fn update_layer(&self, layer: u32, data: &[u8]) -> Result<(), TextureError> {
+        if self.target != gl::TEXTURE_2D_ARRAY {
+            return Err(TextureError::NotAnArray)
+        }
+        if layer >= self.layers {
+            return Err(TextureError::LayerOutOfRange { layer, layers: self.layers })
+        }
+        check_data_size(self.dimensions, data)?;
+        self.bind(0);
+        self.backend.tex_sub_image_3d(
+            self.target,
+            0,
+            0,
+            0,
+            layer as i32,
+            self.dimensions.0 as i32,
+            self.dimensions.1 as i32,
+            1,
+            gl::RGBA,
+            gl::UNSIGNED_BYTE,
+            data
+        );
+        self.regenerate_mipmaps();
+        Ok(())
+    }
+
+    /// Binds to a 0-based texture unit
+    pub fn bind(&self, unit: u32) {
+        self.backend.active_texture(unit);
+        self.backend.bind_texture(self.target, self.id);
+    }
+
+    pub fn unbind(&self, unit: u32) {
+        self.backend.active_texture(unit);
+        self.backend.bind_texture(self.target, 0);
+    }
+
+    pub fn settings(&self) -> &TextureSettings {
+        &self.settings
+    }
+
+    fn create(backend: &Rc<dyn Backend>, target: GLenum, dimensions: (u32, u32), layers: u32, settings: TextureSettings) -> Self {
+        let texture = Self {
+            id: backend.create_texture(),
+            target,
+            dimensions,
+            layers,
+            settings,
+            backend: backend.clone()
+        };
+        texture.bind(0);
+
+        backend.tex_parameter(target, gl::TEXTURE_WRAP_S, settings.wrap.gl_enum() as i32);
+        backend.tex_parameter(target, gl::TEXTURE_WRAP_T, settings.wrap.gl_enum() as i32);
+
+        backend.tex_parameter(target, gl::TEXTURE_MIN_FILTER, settings.min_filter_enum() as i32);
+        backend.tex_parameter(target, gl::TEXTURE_MAG_FILTER, settings.mag_filter_enum() as i32);
+
+        if settings.anisotropy > 1.0 {
+            let max = backend.get_float(MAX_TEXTURE_MAX_ANISOTROPY);
+            if max >= 1.0 {
+                backend.tex_parameter_f(target, TEXTURE_MAX_ANISOTROPY, settings.anisotropy.min(max));
+            }
+        }
+        texture
+    }
+
+    fn finish(&self) {
+        self.regenerate_mipmaps();
+        self.backend.bind_texture(self.target, 0);
+    }
+
+    fn regenerate_mipmaps(&self) {
+        if self.settings.mipmap_filter.is_some() {
+            self.backend.generate_mipmap(self.target);
+        }
+    }
+}
+
+impl fmt::Debug for Texture {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Texture")
+            .field("id", &self.id)
+            .field("target", &self.target)
+            .field("dimensions", &self.dimensions)
+            .field("layers", &self.layers)
+            .field("settings", &self.settings)
+            .finish()
+    }
+}
+
+impl Drop for Texture {
+    fn drop(&mut self) {
+        self.backend.delete_texture(self.id)
+    }
+}
+
+/// Checks `data` holds exactly one RGBA8 texel per pixel of `dimensions`
+fn check_data_size(dimensions: (u32, u32), data: &[u8]) -> Result<(), TextureError> {
+    let expected = dimensions.0 as usize * dimensions.1 as usize * BYTES_PER_TEXEL;
+    if data.len() != expected {
+        return Err(TextureError::DataSize { width: dimensions.0, height: dimensions.1, expected, actual: data.len() })
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::{Command, NullBackend};
+
+    fn uploads(null: &NullBackend) -> usize {
+        null.commands().iter()
+            .filter(|command| matches!(command, Command::TexImage2D { .. } | Command::TexSubImage2D { .. } | Command::TexSubImage3D { .. }))
+            .count()
+    }
+
+    #[test]
+    fn short_data_is_rejected() {
+        let null = Rc::new(NullBackend::new());
+        let backend: Rc<dyn Backend> = null.clone();
+
+        let result = Texture::new_2d(&backend, (2, 2), Some(&[0; 15]), TextureSettings::default());
+        assert!(matches!(result, Err(TextureError::DataSize { expected: 16, actual: 15, .. })));
+        assert_eq!(uploads(&null), 0);
+
+        let texture = Texture::new_2d(&backend, (2, 2), Some(&[0; 16]), TextureSettings::default()).unwrap();
+        null.take_commands();
+        assert!(matches!(texture.update_sub_image(0, 0, 2, 1, &[0; 4]), Err(TextureError::DataSize { expected: 8, .. })));
+        assert!(texture.update_sub_image(1, 1, 1, 1, &[0; 4]).is_ok());
+        assert_eq!(uploads(&null), 1);
+    }
+
+    #[test]
+    fn update_layer_needs_an_array_layer() {
+        let null = Rc::new(NullBackend::new());
+        let backend: Rc<dyn Backend> = null.clone();
+        let flat = Texture::new_2d(&backend, (1, 1), None, TextureSettings::default()).unwrap();
+        let array = Texture::new_2d_array(&backend, (1, 1), 2, TextureSettings::default());
+        null.take_commands();
+
+        assert!(matches!(flat.update_layer(0, &[0; 4]), Err(TextureError::NotAnArray)));
+        assert!(matches!(array.update_layer(2, &[0; 4]), Err(TextureError::LayerOutOfRange { layer: 2, layers: 2 })));
+        assert!(matches!(array.update_layer(1, &[0; 3]), Err(TextureError::DataSize { .. })));
+        assert_eq!(uploads(&null), 0);
+        assert!(array.update_layer(1, &[0; 4]).is_ok());
+        assert_eq!(uploads(&null), 1);
+    }
+}
@@ -1,6 +1,80 @@
+use glm::Vec2;
 
+use crate::texture::Texture;
+
+/// A rectangle of a texture in UV coordinates
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UvRect {
+    pub min: Vec2,
+    pub max: Vec2
+}
+
+impl Default for UvRect {
+    fn default() -> Self {
+        Self::FULL
+    }
+}
+
+impl UvRect {
+    /// The whole texture
+    pub const FULL: UvRect = UvRect {
+        min: Vec2::new(0.0, 0.0),
+        max: Vec2::new(1.0, 1.0)
+    };
+
+    pub fn new(min: Vec2, max: Vec2) -> Self {
+        Self { min, max }
+    }
+
+    pub fn size(&self) -> Vec2 {
+        self.max - self.min
+    }
+
+    /// A part of this rect, `offset` and `size` are fractions of it
+    pub fn sub_rect(&self, offset: Vec2, size: Vec2) -> Self {
+        let min = self.min + self.size().component_mul(&offset);
+        Self {
+            min,
+            max: min + self.size().component_mul(&size)
+        }
+    }
+}
+
+/// A texture split into a grid of equally sized tiles,
+/// numbered left to right then top to bottom starting at 0
 #[derive(Debug)]
 pub struct TextureAtlas {
-    
+    pub texture: Texture,
+    pub tile_size: (u32, u32),
+    columns: u32,
+    rows: u32
 }
 
+impl TextureAtlas {
+    pub fn new(texture: Texture, tile_size: (u32, u32)) -> Self {
+        let columns = (texture.dimensions.0 / tile_size.0).max(1);
+        let rows = (texture.dimensions.1 / tile_size.1).max(1);
+        Self {
+            texture,
+            tile_size,
+            columns,
+            rows
+        }
+    }
+
+    pub fn tile_count(&self) -> u32 {
+        self.columns * self.rows
+    }
+
+    /// UVs of a tile, out of range tiles wrap around
+    pub fn uv_rect(&self, tile: u32) -> UvRect {
+        let tile = tile % self.tile_count();
+        let (column, row) = (tile % self.columns, tile / self.columns);
+        let size = Vec2::new(
+            self.tile_size.0 as f32 / self.texture.dimensions.0 as f32,
+            self.tile_size.1 as f32 / self.texture.dimensions.1 as f32
+        );
+        let min = Vec2::new(column as f32 * size.x, row as f32 * size.y);
+        UvRect::new(min, min + size)
+    }
+}
@@ -0,0 +1,471 @@
+use std::{collections::hash_map::DefaultHasher, hash::{Hash, Hasher}, ops::RangeInclusive, rc::Rc};
+
+use glfw::{Action, CursorMode, Key, MouseButton};
+use glm::{Vec2, Vec4};
+
+use crate::backend::Backend;
+use crate::events::Event;
+use crate::shader::ShaderError;
+use crate::sprite_batch::SpriteBatch;
+use crate::text::{Font, TextStyle};
+use crate::texture::Texture;
+use crate::texture_atlas::UvRect;
+use crate::window::Window;
+
+/// A rectangle in framebuffer pixels from the top left corner
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rect {
+    pub min: Vec2,
+    pub max: Vec2
+}
+
+impl Rect {
+    pub fn new(position: Vec2, size: Vec2) -> Self {
+        Self { min: position, max: position + size }
+    }
+
+    /// A rectangle of `size` in the middle of `outer`
+    pub fn centred_in(outer: Rect, size: Vec2) -> Self {
+        Self::new(outer.min + (outer.size() - size) / 2.0, size)
+    }
+
+    pub fn size(&self) -> Vec2 {
+        self.max - self.min
+    }
+
+    pub fn contains(&self, point: Vec2) -> bool {
+        point.x >= self.min.x && point.y >= self.min.y && point.x < self.max.x && point.y < self.max.y
+    }
+
+    /// Moves every edge inwards by `amount`
+    pub fn shrink(&self, amount: f32) -> Self {
+        Self { min: self.min.add_scalar(amount), max: self.max.add_scalar(-amount) }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct UiStyle {
+    /// Multiplier on the font's pixel size
+    pub text_scale: f32,
+    pub text_colour: Vec4,
+    /// Placeholder text in empty text fields
+    pub hint_colour: Vec4,
+    pub panel_colour: Vec4,
+    pub widget_colour: Vec4,
+    pub hovered_colour: Vec4,
+    /// Widgets being held down and focused text fields
+    pub active_colour: Vec4,
+    /// Slider fill, checkbox tick and text caret
+    pub accent_colour: Vec4,
+    /// Space between a widget's edge and its contents
+    pub padding: f32,
+    /// Space between widgets
+    pub spacing: f32,
+    /// Width of sliders and text fields in horizontal layouts
+    pub field_width: f32
+}
+
+impl Default for UiStyle {
+    fn default() -> Self {
+        Self {
+            text_scale: 2.0,
+            text_colour: Vec4::new(1.0, 1.0, 1.0, 1.0),
+            hint_colour: Vec4::new(1.0, 1.0, 1.0, 0.4),
+            panel_colour: Vec4::new(0.08, 0.08, 0.1, 0.85),
+            widget_colour: Vec4::new(0.25, 0.25, 0.3, 0.9),
+            hovered_colour: Vec4::new(0.35, 0.35, 0.42, 0.95),
+            active_colour: Vec4::new(0.45, 0.45, 0.55, 1.0),
+            accent_colour: Vec4::new(0.35, 0.6, 0.95, 1.0),
+            padding: 6.0,
+            spacing: 4.0,
+            field_width: 200.0
+        }
+    }
+}
+
+/// Hash of a widget's label and the panel it's in, identifying it between frames
+type WidgetId = u64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Direction {
+    Vertical,
+    Horizontal
+}
+
+/// Where the next widget goes in a panel or row
+#[derive(Debug)]
+struct Layout {
+    id: WidgetId,
+    direction: Direction,
+    /// Top left corner of the next widget
+    cursor: Vec2,
+    /// Width widgets stretch to in vertical layouts
+    width: f32,
+    /// Bottom right corner of everything placed so far
+    extent: Vec2
+}
+
+/// Mouse and keyboard input gathered from the window's events for one frame
+#[derive(Debug, Default)]
+struct UiInput {
+    mouse_position: Vec2,
+    mouse_down: bool,
+    mouse_pressed: bool,
+    mouse_released: bool,
+    typed: String,
+    /// Keys pressed or repeated, for editing text
+    keys: Vec<Key>
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Interaction {
+    hovered: bool,
+    held: bool,
+    clicked: bool
+}
+
+/// Immediate mode UI. Widgets are declared every frame between `begin_frame` and
+/// `end_frame`, returning whether they were used, and are drawn in one batch at the end.
+///
+/// Widgets are identified by their label, which has to be unique within a panel.
+/// While any interactive widget is shown the cursor is released for the UI, and
+/// captured again for gameplay once none are. The window needs cursor position,
+/// mouse button, key and char polling enabled.
+pub struct Ui {
+    pub style: UiStyle,
+    pub font: Rc<Font>,
+    batch: SpriteBatch,
+    input: UiInput,
+    screen_size: (i32, i32),
+    layouts: Vec<Layout>,
+    /// Widget the mouse was pressed on, until it's released
+    active: Option<WidgetId>,
+    /// Text field receiving typed text
+    focused: Option<WidgetId>,
+    /// Whether an interactive widget has been shown this frame
+    wants_cursor: bool,
+    cursor_released: bool
+}
+
+impl Ui {
+    pub fn new(backend: &Rc<dyn Backend>, font: Rc<Font>) -> Result<Self, ShaderError> {
+        Ok(Self {
+            style: UiStyle::default(),
+            font,
+            batch: SpriteBatch::new(backend)?,
+            input: UiInput::default(),
+            screen_size: (0, 0),
+            layouts: Vec::new(),
+            active: None,
+            focused: None,
+            wants_cursor: false,
+            cursor_released: false
+        })
+    }
+
+    /// Reads this frame's input from the events the window last processed
+    pub fn begin_frame(&mut self, window: &mut Window) {
+        self.screen_size = window.get_framebuffer_size();
+        let window_size = window.get_size();
+        // cursor positions are in screen coordinates, which differ from pixels on high DPI displays
+        let pixel_scale = Vec2::new(
+            self.screen_size.0 as f32 / window_size.0.max(1) as f32,
+            self.screen_size.1 as f32 / window_size.1.max(1) as f32
+        );
+
+        let input = &mut self.input;
+        input.mouse_pressed = false;
+        input.mouse_released = false;
+        input.typed.clear();
+        input.keys.clear();
+        for event in window.events() {
+            match *event {
+                Event::CursorMoved { position, .. } => input.mouse_position = position.component_mul(&pixel_scale),
+                Event::MouseButton { button: MouseButton::Button1, action: Action::Press, .. } => {
+                    input.mouse_down = true;
+                    input.mouse_pressed = true;
+                },
+                Event::MouseButton { button: MouseButton::Button1, action: Action::Release, .. } => {
+                    input.mouse_down = false;
+                    input.mouse_released = true;
+                },
+                Event::Char(character) => input.typed.push(character),
+                Event::Key { key, action: Action::Press | Action::Repeat, .. } => input.keys.push(key),
+                _ => {}
+            }
+        }
+
+        self.wants_cursor = false;
+        self.layouts.clear();
+        self.layouts.push(Layout {
+            id: 0,
+            direction: Direction::Vertical,
+            cursor: Vec2::zeros(),
+            width: self.screen_size.0 as f32,
+            extent: Vec2::zeros()
+        });
+    }
+
+    /// Switches the cursor mode if needed and draws the frame's widgets
+    pub fn end_frame(&mut self, window: &mut Window) {
+        if !self.input.mouse_down {
+            self.active = None;
+        }
+        if !self.wants_cursor {
+            self.focused = None;
+        }
+        if self.wants_cursor != self.cursor_released {
+            window.set_cursor_mode(if self.wants_cursor { CursorMode::Normal } else { CursorMode::Disabled });
+            self.cursor_released = self.wants_cursor;
+        }
+        self.batch.draw(self.screen_size);
+    }
+
+    /// Whether the cursor belongs to the UI, gameplay should ignore the mouse while it does
+    pub fn has_cursor(&self) -> bool {
+        self.cursor_released
+    }
+
+    /// Whether a text field is taking keyboard input
+    pub fn has_keyboard_focus(&self) -> bool {
+        self.focused.is_some()
+    }
+
+    pub fn screen_rect(&self) -> Rect {
+        Rect::new(Vec2::zeros(), Vec2::new(self.screen_size.0 as f32, self.screen_size.1 as f32))
+    }
+
+    /// For drawing things that aren't widgets, like HUD elements, in the same batch
+    pub fn batch(&mut self) -> &mut SpriteBatch {
+        &mut self.batch
+    }
+
+    /// A background at `rect` with its contents laid out top to bottom inside it
+    pub fn panel(&mut self, id: &str, rect: Rect, contents: impl FnOnce(&mut Self)) {
+        self.batch.rect(rect.min, rect.max, self.style.panel_colour);
+        let id = self.id(id);
+        let inner = rect.shrink(self.style.padding);
+        self.layouts.push(Layout {
+            id,
+            direction: Direction::Vertical,
+            cursor: inner.min,
+            width: inner.size().x,
+            extent: inner.min
+        });
+        contents(self);
+        self.layouts.pop();
+    }
+
+    /// Lays out the widgets added by `contents` left to right at their natural widths
+    pub fn horizontal(&mut self, contents: impl FnOnce(&mut Self)) {
+        let parent = self.layout();
+        let (id, start, width) = (parent.id, parent.cursor, parent.width);
+        self.layouts.push(Layout {
+            id,
+            direction: Direction::Horizontal,
+            cursor: start,
+            width,
+            extent: start
+        });
+        contents(self);
+        let row = self.layouts.pop().expect("row layout was pushed above");
+        self.allocate(row.extent - start, false);
+    }
+
+    pub fn label(&mut self, text: &str) {
+        let size = self.font.measure(text, self.style.text_scale, None);
+        let rect = self.allocate(Vec2::new(size.x, self.widget_height()), false);
+        let style = self.text_style(self.style.text_colour);
+        self.batch.text(&self.font, text, rect.min + Vec2::new(0.0, self.style.padding), &style);
+    }
+
+    /// Returns true on the frame the button is clicked
+    pub fn button(&mut self, label: &str) -> bool {
+        let id = self.id(label);
+        let text_size = self.font.measure(label, self.style.text_scale, None);
+        let rect = self.allocate(Vec2::new(text_size.x + self.style.padding * 2.0, self.widget_height()), true);
+        let interaction = self.interact(id, rect);
+
+        self.batch.rect(rect.min, rect.max, self.widget_colour(interaction));
+        let style = self.text_style(self.style.text_colour);
+        self.batch.text(&self.font, label, Rect::centred_in(rect, text_size).min, &style);
+        interaction.clicked
+    }
+
+    /// Returns true when the value is toggled
+    pub fn checkbox(&mut self, label: &str, value: &mut bool) -> bool {
+        let id = self.id(label);
+        let height = self.widget_height();
+        let text_size = self.font.measure(label, self.style.text_scale, None);
+        let rect = self.allocate(Vec2::new(height + self.style.padding + text_size.x, height), true);
+        let interaction = self.interact(id, rect);
+        if interaction.clicked {
+            *value = !*value;
+        }
+
+        let check = Rect::new(rect.min, Vec2::repeat(height));
+        self.batch.rect(check.min, check.max, self.widget_colour(interaction));
+        if *value {
+            let tick = check.shrink(self.style.padding);
+            self.batch.rect(tick.min, tick.max, self.style.accent_colour);
+        }
+        let style = self.text_style(self.style.text_colour);
+        let text_position = Vec2::new(check.max.x + self.style.padding, rect.min.y + self.style.padding);
+        self.batch.text(&self.font, label, text_position, &style);
+        interaction.clicked
+    }
+
+    /// Drags `value` within `range`, returns true when it changes
+    pub fn slider(&mut self, label: &str, value: &mut f32, range: RangeInclusive<f32>) -> bool {
+        let id = self.id(label);
+        let rect = self.allocate(Vec2::new(self.style.field_width, self.widget_height()), true);
+        let interaction = self.interact(id, rect);
+        let (start, end) = (*range.start(), *range.end());
+
+        let mut changed = false;
+        if interaction.held {
+            let fraction = ((self.input.mouse_position.x - rect.min.x) / rect.size().x).clamp(0.0, 1.0);
+            let new_value = start + fraction * (end - start);
+            changed = new_value != *value;
+            *value = new_value;
+        }
+
+        let fraction = if end > start { ((*value - start) / (end - start)).clamp(0.0, 1.0) } else { 0.0 };
+        self.batch.rect(rect.min, rect.max, self.widget_colour(interaction));
+        self.batch.rect(rect.min, Vec2::new(rect.min.x + rect.size().x * fraction, rect.max.y), self.style.accent_colour);
+        let text = format!("{}: {:.2}", label, value);
+        let text_size = self.font.measure(&text, self.style.text_scale, None);
+        let style = self.text_style(self.style.text_colour);
+        self.batch.text(&self.font, &text, Rect::centred_in(rect, text_size).min, &style);
+        changed
+    }
+
+    /// Edits `text` while focused by clicking it, `label` is shown while it's empty.
+    /// Enter, escape or clicking elsewhere unfocuses it. Returns true when the text changes.
+    pub fn text_field(&mut self, label: &str, text: &mut String) -> bool {
+        let id = self.id(label);
+        let rect = self.allocate(Vec2::new(self.style.field_width, self.widget_height()), true);
+        let interaction = self.interact(id, rect);
+        if self.input.mouse_pressed {
+            if interaction.hovered {
+                self.focused = Some(id);
+            } else if self.focused == Some(id) {
+                self.focused = None;
+            }
+        }
+
+        let mut changed = false;
+        let focused = self.focused == Some(id);
+        if focused {
+            for character in self.input.typed.chars().filter(|character| !character.is_control()) {
+                text.push(character);
+                changed = true;
+            }
+            let mut unfocus = false;
+            for key in &self.input.keys {
+                match key {
+                    Key::Backspace => changed |= text.pop().is_some(),
+                    Key::Enter | Key::KpEnter | Key::Escape => unfocus = true,
+                    _ => {}
+                }
+            }
+            if unfocus {
+                self.focused = None;
+            }
+        }
+
+        let background = if focused { self.style.active_colour } else { self.widget_colour(interaction) };
+        self.batch.rect(rect.min, rect.max, background);
+        let inner = rect.shrink(self.style.padding);
+        if text.is_empty() && !focused {
+            let style = self.text_style(self.style.hint_colour);
+            self.batch.text(&self.font, label, inner.min, &style);
+        } else {
+            // show the end of text that doesn't fit, that's where typing happens
+            let mut visible = text.as_str();
+            while !visible.is_empty() && self.font.measure(visible, self.style.text_scale, None).x > inner.size().x {
+                let mut characters = visible.chars();
+                characters.next();
+                visible = characters.as_str();
+            }
+            let style = self.text_style(self.style.text_colour);
+            self.batch.text(&self.font, visible, inner.min, &style);
+            if focused {
+                let caret_x = inner.min.x + if visible.is_empty() { 0.0 } else { self.font.measure(visible, self.style.text_scale, None).x };
+                let caret = Rect::new(Vec2::new(caret_x, inner.min.y), Vec2::new(self.style.text_scale, inner.size().y));
+                self.batch.rect(caret.min, caret.max, self.style.accent_colour);
+            }
+        }
+        changed
+    }
+
+    /// Part of a 2D texture at `size` pixels
+    pub fn image(&mut self, texture: &Texture, uv: UvRect, size: Vec2) {
+        let rect = self.allocate(size, false);
+        self.batch.image(texture, rect.min, rect.max, uv, Vec4::repeat(1.0));
+    }
+
+    fn layout(&mut self) -> &mut Layout {
+        self.layouts.last_mut().expect("begin_frame adds the root layout")
+    }
+
+    fn id(&mut self, label: &str) -> WidgetId {
+        let mut hasher = DefaultHasher::new();
+        self.layout().id.hash(&mut hasher);
+        label.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// Reserves space for the next widget, stretching it to the layout's width if `fill` is set
+    fn allocate(&mut self, size: Vec2, fill: bool) -> Rect {
+        let spacing = self.style.spacing;
+        let layout = self.layout();
+        let size = match layout.direction {
+            Direction::Vertical if fill => Vec2::new(layout.width, size.y),
+            _ => size
+        };
+        let rect = Rect::new(layout.cursor, size);
+        match layout.direction {
+            Direction::Vertical => layout.cursor.y += size.y + spacing,
+            Direction::Horizontal => layout.cursor.x += size.x + spacing
+        }
+        layout.extent = layout.extent.sup(&rect.max);
+        rect
+    }
+
+    fn interact(&mut self, id: WidgetId, rect: Rect) -> Interaction {
+        self.wants_cursor = true;
+        let hovered = self.cursor_released && rect.contains(self.input.mouse_position);
+        if hovered && self.input.mouse_pressed {
+            self.active = Some(id);
+        }
+        let active = self.active == Some(id);
+        Interaction {
+            hovered,
+            held: active && self.input.mouse_down,
+            clicked: active && hovered && self.input.mouse_released
+        }
+    }
+
+    fn widget_colour(&self, interaction: Interaction) -> Vec4 {
+        if interaction.held {
+            self.style.active_colour
+        } else if interaction.hovered {
+            self.style.hovered_colour
+        } else {
+            self.style.widget_colour
+        }
+    }
+
+    fn widget_height(&self) -> f32 {
+        self.font.line_height * self.style.text_scale + self.style.padding * 2.0
+    }
+
+    fn text_style(&self, colour: Vec4) -> TextStyle {
+        TextStyle {
+            colour,
+            scale: self.style.text_scale,
+            max_width: None
+        }
+    }
+}
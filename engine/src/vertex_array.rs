@@ -0,0 +1,376 @@
+use std::{fmt, mem::size_of, ops::Range, rc::Rc};
+
+use gl::types::GLenum;
+use glm::{Vec2, Vec3, Vec4};
+
+use crate::backend::Backend;
+use crate::buffer::Buffer;
+
+/// How a field type is passed to a vertex shader input
+pub trait AttributeFormat {
+    /// Number of components, 1 to 4
+    const COMPONENTS: i32;
+    const TYPE: GLenum;
+    /// Integer inputs (`int`, `uvec4`, ...) rather than floats
+    const INTEGER: bool = false;
+    /// Integer data converted to a 0..1 float in the shader
+    const NORMALIZED: bool = false;
+}
+
+macro_rules! attribute_format {
+    ($($t:ty => ($components:literal, $type_:expr $(, $flag:ident)?)),* $(,)?) => {
+        $(
+            impl AttributeFormat for $t {
+                const COMPONENTS: i32 = $components;
+                const TYPE: GLenum = $type_;
+                $(const $flag: bool = true;)?
+            }
+        )*
+    };
+}
+
+attribute_format! {
+    f32 => (1, gl::FLOAT),
+    [f32; 2] => (2, gl::FLOAT),
+    [f32; 3] => (3, gl::FLOAT),
+    [f32; 4] => (4, gl::FLOAT),
+    Vec2 => (2, gl::FLOAT),
+    Vec3 => (3, gl::FLOAT),
+    Vec4 => (4, gl::FLOAT),
+    i32 => (1, gl::INT, INTEGER),
+    [i32; 2] => (2, gl::INT, INTEGER),
+    [i32; 3] => (3, gl::INT, INTEGER),
+    [i32; 4] => (4, gl::INT, INTEGER),
+    u32 => (1, gl::UNSIGNED_INT, INTEGER),
+    [u32; 2] => (2, gl::UNSIGNED_INT, INTEGER),
+    [u32; 3] => (3, gl::UNSIGNED_INT, INTEGER),
+    [u32; 4] => (4, gl::UNSIGNED_INT, INTEGER),
+    [u8; 4] => (4, gl::UNSIGNED_BYTE, NORMALIZED),
+}
+
+/// One shader input read from a vertex struct
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VertexAttribute {
+    pub location: u32,
+    pub components: i32,
+    pub type_: GLenum,
+    pub integer: bool,
+    pub normalized: bool,
+    /// Byte offset of the field in the vertex
+    pub offset: usize
+}
+
+impl VertexAttribute {
+    /// Describes a field of `V` at `offset`, the accessor is only used to infer the field type
+    pub fn of<V, F: AttributeFormat>(location: u32, offset: usize, _field: fn(&V) -> &F) -> Self {
+        Self {
+            location,
+            components: F::COMPONENTS,
+            type_: F::TYPE,
+            integer: F::INTEGER,
+            normalized: F::NORMALIZED,
+            offset
+        }
+    }
+}
+
+/// A `#[repr(C)]` vertex struct whose fields map to shader input locations,
+/// usually implemented with `impl_vertex!`
+pub trait Vertex: Copy {
+    fn attributes() -> Vec<VertexAttribute>;
+}
+
+/// Implements `Vertex` for a `#[repr(C)]` struct from `location => field` pairs.
+///
+/// ```ignore
+/// #[repr(C)]
+/// #[derive(Clone, Copy)]
+/// struct ChunkVertex {
+///     position: [f32; 3],
+///     tex_coord: [f32; 2]
+/// }
+/// impl_vertex!(ChunkVertex { 0 => position, 1 => tex_coord });
+/// ```
+#[macro_export]
+macro_rules! impl_vertex {
+    ($vertex:ty { $($location:literal => $field:ident),* $(,)? }) => {
+        impl $crate::vertex_array::Vertex for $vertex {
+            fn attributes() -> Vec<$crate::vertex_array::VertexAttribute> {
+                vec![
+                    $($crate::vertex_array::VertexAttribute::of::<$vertex, _>(
+                        $location,
+                        std::mem::offset_of!($vertex, $field),
+                        |vertex| &vertex.$field
+                    ),)*
+                ]
+            }
+        }
+    };
+}
+
+pub struct VertexArray {
+    pub id: u32,
+    backend: Rc<dyn Backend>
+}
+
+impl VertexArray {
+    pub fn new(backend: &Rc<dyn Backend>) -> Self {
+        Self {
+            id: backend.create_vertex_array(),
+            backend: backend.clone()
+        }
+    }
+
+    pub fn bind(&self) {
+        self.backend.bind_vertex_array(self.id)
+    }
+
+    pub fn unbind(&self) {
+        self.backend.bind_vertex_array(0)
+    }
+
+    /// Points the attributes of `V` at `buffer`
+    pub fn set_vertex_buffer<V: Vertex>(&self, buffer: &Buffer<V>) {
+        self.set_attributes(buffer, 0)
+    }
+
+    /// Points the attributes of `I` at `buffer`, advancing once per instance.
+    /// Their locations must not overlap with the vertex buffer's.
+    pub fn set_instance_buffer<I: Vertex>(&self, buffer: &Buffer<I>) {
+        self.set_attributes(buffer, 1)
+    }
+
+    fn set_attributes<V: Vertex>(&self, buffer: &Buffer<V>, divisor: u32) {
+        self.bind();
+        buffer.bind();
+        let stride = size_of::<V>() as i32;
+        for attribute in V::attributes() {
+            self.backend.enable_vertex_attrib_array(attribute.location);
+            if attribute.integer {
+                self.backend.vertex_attrib_i_pointer(attribute.location, attribute.components, attribute.type_, stride, attribute.offset);
+            } else {
+                self.backend.vertex_attrib_pointer(attribute.location, attribute.components, attribute.type_, attribute.normalized, stride, attribute.offset);
+            }
+            if divisor != 0 {
+                self.backend.vertex_attrib_divisor(attribute.location, divisor);
+            }
+        }
+        self.unbind();
+    }
+
+    /// The index buffer binding is stored in the vertex array
+    pub fn set_index_buffer(&self, buffer: &Buffer<u32>) {
+        self.bind();
+        buffer.bind();
+        self.unbind();
+    }
+}
+
+impl fmt::Debug for VertexArray {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("VertexArray").field("id", &self.id).finish()
+    }
+}
+
+impl Drop for VertexArray {
+    fn drop(&mut self) {
+        self.backend.delete_vertex_array(self.id)
+    }
+}
+
+/// Vertices, optional indices and the vertex array tying them together
+#[derive(Debug)]
+pub struct Mesh<V: Vertex> {
+    pub vertex_array: VertexArray,
+    pub vertices: Buffer<V>,
+    pub indices: Option<Buffer<u32>>,
+    backend: Rc<dyn Backend>
+}
+
+impl<V: Vertex> Mesh<V> {
+    /// Empty `indices` are the same as none, every vertex is drawn in order
+    pub fn new(backend: &Rc<dyn Backend>, vertices: &[V], indices: Option<&[u32]>, usage: GLenum) -> Self {
+        let vertex_array = VertexArray::new(backend);
+        let vertices = Buffer::with_data(backend, gl::ARRAY_BUFFER, vertices, usage);
+        vertex_array.set_vertex_buffer(&vertices);
+        let indices = indices.filter(|indices| !indices.is_empty()).map(|indices| {
+            // element array bindings belong to the vertex array, so it has to be bound first
+            vertex_array.bind();
+            let indices = Buffer::with_data(backend, gl::ELEMENT_ARRAY_BUFFER, indices, usage);
+            vertex_array.unbind();
+            indices
+        });
+        backend.bind_buffer(gl::ARRAY_BUFFER, 0);
+
+        Self {
+            vertex_array,
+            vertices,
+            indices,
+            backend: backend.clone()
+        }
+    }
+
+    /// Replaces the mesh data, reusing the buffers it already has. Like `new`, empty
+    /// `indices` are the same as none, and the index buffer is dropped for them.
+    pub fn update(&mut self, vertices: &[V], indices: Option<&[u32]>, usage: GLenum) {
+        self.vertices.set_data(vertices, usage);
+        let indices = indices.filter(|indices| !indices.is_empty());
+        // element array bindings belong to the vertex array
+        self.vertex_array.bind();
+        match (&self.indices, indices) {
+            (Some(buffer), Some(indices)) => buffer.set_data(indices, usage),
+            (None, Some(indices)) => self.indices = Some(Buffer::with_data(&self.backend, gl::ELEMENT_ARRAY_BUFFER, indices, usage)),
+            (Some(_), None) => {
+                self.backend.bind_buffer(gl::ELEMENT_ARRAY_BUFFER, 0);
+                self.indices = None;
+            },
+            (None, None) => {}
+        }
+        self.vertex_array.unbind();
+    }
+
+    /// Number of vertices drawn, the index count if the mesh is indexed
+    pub fn count(&self) -> usize {
+        match &self.indices {
+            Some(indices) => indices.len(),
+            None => self.vertices.len()
+        }
+    }
+
+    /// GPU memory used by the vertex and index data
+    pub fn size_bytes(&self) -> usize {
+        self.vertices.size_bytes() + self.indices.as_ref().map_or(0, |indices| indices.size_bytes())
+    }
+
+    pub fn draw(&self, mode: GLenum) {
+        self.vertex_array.bind();
+        match &self.indices {
+            Some(indices) => self.backend.draw_elements(mode, indices.len() as i32, gl::UNSIGNED_INT, 0),
+            None => self.backend.draw_arrays(mode, 0, self.vertices.len() as i32)
+        }
+        self.vertex_array.unbind();
+    }
+
+    /// Draws part of the mesh, `range` counts indices if the mesh is indexed and vertices otherwise
+    pub fn draw_range(&self, mode: GLenum, range: Range<usize>) {
+        self.vertex_array.bind();
+        match &self.indices {
+            Some(_) => self.backend.draw_elements(mode, range.len() as i32, gl::UNSIGNED_INT, range.start * size_of::<u32>()),
+            None => self.backend.draw_arrays(mode, range.start as i32, range.len() as i32)
+        }
+        self.vertex_array.unbind();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::{Command, NullBackend};
+
+    #[repr(C)]
+    #[derive(Clone, Copy)]
+    struct TestVertex {
+        position: [f32; 3]
+    }
+    impl_vertex!(TestVertex { 0 => position });
+
+    const TRIANGLE: [TestVertex; 3] = [
+        TestVertex { position: [0.0, 0.0, 0.0] },
+        TestVertex { position: [1.0, 0.0, 0.0] },
+        TestVertex { position: [0.0, 1.0, 0.0] }
+    ];
+
+    #[test]
+    fn indexed_draw_binds_vertex_array_and_draws_every_index() {
+        let null = Rc::new(NullBackend::new());
+        let backend: Rc<dyn Backend> = null.clone();
+        let mesh = Mesh::new(&backend, &TRIANGLE, Some(&[0, 1, 2, 2, 1, 0]), gl::STATIC_DRAW);
+        null.take_commands();
+
+        mesh.draw(gl::TRIANGLES);
+
+        assert_eq!(null.take_commands(), vec![
+            Command::BindVertexArray(mesh.vertex_array.id),
+            Command::DrawElements { mode: gl::TRIANGLES, count: 6, index_type: gl::UNSIGNED_INT, offset: 0 },
+            Command::BindVertexArray(0)
+        ]);
+    }
+
+    #[test]
+    fn unindexed_draw_draws_every_vertex() {
+        let null = Rc::new(NullBackend::new());
+        let backend: Rc<dyn Backend> = null.clone();
+        let mesh = Mesh::new(&backend, &TRIANGLE, None, gl::STATIC_DRAW);
+        null.take_commands();
+
+        mesh.draw(gl::TRIANGLES);
+
+        assert_eq!(null.take_commands(), vec![
+            Command::BindVertexArray(mesh.vertex_array.id),
+            Command::DrawArrays { mode: gl::TRIANGLES, first: 0, count: 3 },
+            Command::BindVertexArray(0)
+        ]);
+    }
+
+    #[test]
+    fn update_switches_between_indexed_and_unindexed() {
+        let null = Rc::new(NullBackend::new());
+        let backend: Rc<dyn Backend> = null.clone();
+        let mut mesh = Mesh::new(&backend, &TRIANGLE, Some(&[0, 1, 2, 2, 1, 0]), gl::DYNAMIC_DRAW);
+
+        mesh.update(&TRIANGLE, None, gl::DYNAMIC_DRAW);
+        assert!(mesh.indices.is_none());
+        assert_eq!(mesh.count(), 3);
+
+        mesh.update(&TRIANGLE, Some(&[2, 1, 0]), gl::DYNAMIC_DRAW);
+        assert_eq!(mesh.count(), 3);
+        null.take_commands();
+        mesh.draw(gl::TRIANGLES);
+        assert!(null.commands().contains(&Command::DrawElements { mode: gl::TRIANGLES, count: 3, index_type: gl::UNSIGNED_INT, offset: 0 }));
+
+        mesh.update(&TRIANGLE[..2], Some(&[]), gl::DYNAMIC_DRAW);
+        assert!(mesh.indices.is_none());
+        assert_eq!(mesh.count(), 2);
+    }
+
+    #[repr(C)]
+    #[derive(Clone, Copy)]
+    struct LayoutVertex {
+        position: [f32; 3],
+        colour: [u8; 4],
+        layer: u32,
+        tex_coord: Vec2
+    }
+    impl_vertex!(LayoutVertex { 0 => position, 1 => colour, 2 => layer, 3 => tex_coord });
+
+    #[test]
+    fn attribute_pointers_follow_the_struct_layout() {
+        let null = Rc::new(NullBackend::new());
+        let backend: Rc<dyn Backend> = null.clone();
+        let vertex_array = VertexArray::new(&backend);
+        let buffer = Buffer::<LayoutVertex>::new(&backend, gl::ARRAY_BUFFER);
+        null.take_commands();
+
+        vertex_array.set_instance_buffer(&buffer);
+
+        let stride = size_of::<LayoutVertex>() as i32;
+        assert_eq!(stride, 28);
+        assert_eq!(null.take_commands(), vec![
+            Command::BindVertexArray(vertex_array.id),
+            Command::BindBuffer { target: gl::ARRAY_BUFFER, buffer: buffer.id },
+            Command::EnableVertexAttribArray(0),
+            Command::VertexAttribPointer { index: 0, size: 3, type_: gl::FLOAT, normalized: false, stride, offset: 0 },
+            Command::VertexAttribDivisor { index: 0, divisor: 1 },
+            Command::EnableVertexAttribArray(1),
+            Command::VertexAttribPointer { index: 1, size: 4, type_: gl::UNSIGNED_BYTE, normalized: true, stride, offset: 12 },
+            Command::VertexAttribDivisor { index: 1, divisor: 1 },
+            Command::EnableVertexAttribArray(2),
+            Command::VertexAttribIPointer { index: 2, size: 1, type_: gl::UNSIGNED_INT, stride, offset: 16 },
+            Command::VertexAttribDivisor { index: 2, divisor: 1 },
+            Command::EnableVertexAttribArray(3),
+            Command::VertexAttribPointer { index: 3, size: 2, type_: gl::FLOAT, normalized: false, stride, offset: 20 },
+            Command::VertexAttribDivisor { index: 3, divisor: 1 },
+            Command::BindVertexArray(0)
+        ]);
+    }
+}
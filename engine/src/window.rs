@@ -2,8 +2,10 @@ use std::sync::mpsc::Receiver;
 use thiserror::Error;
 
 use glfw::{WindowEvent, InitError, Context};
+use glm::Vec2;
+
+use super::events::Event;
 
-use super::camera::Camera;
 
 #[derive(Debug, Error)]
 pub enum WindowInitError {
@@ -20,7 +22,12 @@ pub struct Window {
     pub receiver: Receiver<(f64, WindowEvent)>,
     pub is_fullscreen: bool,
     pub last_pos: (i32, i32),
-    pub last_size: (i32, i32)
+    pub last_size: (i32, i32),
+    /// Events handled by the last `process_events`, for anything else that needs them
+    events: Vec<Event>,
+    /// `None` until the first cursor position, and again after the cursor mode changes
+    last_cursor: Option<Vec2>,
+    cursor_delta: Vec2
 }
 
 impl Window {
@@ -40,6 +47,9 @@ impl Window {
                             receiver: window_res.1,
                             last_pos: (0, 0),
                             last_size: (width as i32, height as i32),
+                            events: Vec::new(),
+                            last_cursor: None,
+                            cursor_delta: Vec2::zeros(),
                             is_fullscreen: match mode {
                                 glfw::WindowMode::FullScreen(_) => true,
                                 glfw::WindowMode::Windowed => false
@@ -56,41 +66,40 @@ impl Window {
         }
     }
 
-    pub fn process_events(&mut self, first_mouse: &mut bool, last_x: &mut f32, last_y: &mut f32, camera: &mut Camera) {
+    /// Handles pending events and keeps them for `events`
+    pub fn process_events(&mut self) {
+        self.events.clear();
+        self.cursor_delta = Vec2::zeros();
         for (_, event) in glfw::flush_messages(&self.receiver) {
+            let Some(event) = Event::from_glfw(event, self.last_cursor) else {
+                continue
+            };
             match event {
-                glfw::WindowEvent::FramebufferSize(width, height) => {
-                    unsafe { gl::Viewport(0, 0, width, height) }
-                },
-                glfw::WindowEvent::Pos(xpos, ypos) => {
-                    self.last_pos = (xpos, ypos);
+                Event::Moved { x, y } => {
+                    self.last_pos = (x, y);
                 },
-                glfw::WindowEvent::Size(width, height) => {
+                Event::Resized { width, height } => {
                     self.last_size = (width, height);
                 },
-                glfw::WindowEvent::CursorPos(x_pos, y_pos) => {
-                    let (xpos, ypos) = (x_pos as f32, y_pos as f32);
-                    if *first_mouse {
-                        *last_x = xpos;
-                        *last_y = ypos;
-                        *first_mouse = false;
-                    }
-    
-                    let x_offset = xpos - *last_x;
-                    let y_offset = *last_y - ypos;
-    
-                    *last_x = xpos;
-                    *last_y = ypos;
-                    camera.process_mouse_input(x_offset, y_offset, true)
-                },
-                glfw::WindowEvent::Scroll(_x_offset, y_offset) => {
-                    camera.process_scroll_input(y_offset as f32);
+                Event::CursorMoved { position, delta } => {
+                    self.last_cursor = Some(position);
+                    self.cursor_delta += delta;
                 },
                 _ => {}
             }
+            self.events.push(event);
         }
     }
 
+    pub fn events(&self) -> &[Event] {
+        &self.events
+    }
+
+    /// Cursor movement during the last `process_events` in screen coordinates, down is positive
+    pub fn cursor_delta(&self) -> Vec2 {
+        self.cursor_delta
+    }
+
     pub fn poll_events(&mut self) {
         self.context.poll_events()
     }
@@ -137,14 +146,26 @@ impl Window {
         }
     }
 
-    pub fn get_key(&mut self, key: glfw::Key) -> glfw::Action {
+    pub fn get_key(&self, key: glfw::Key) -> glfw::Action {
         self.window.get_key(key)
     }
 
+    pub fn get_mouse_button(&self, button: glfw::MouseButton) -> glfw::Action {
+        self.window.get_mouse_button(button)
+    }
+
     pub fn set_cursor_mode(&mut self, mode: glfw::CursorMode) {
+        if mode != self.window.get_cursor_mode() {
+            // the cursor jumps when it's captured or released, so start from wherever it ends up
+            self.last_cursor = None;
+        }
         self.window.set_cursor_mode(mode)
     }
 
+    pub fn get_cursor_mode(&self) -> glfw::CursorMode {
+        self.window.get_cursor_mode()
+    }
+
     pub fn get_framebuffer_size(&mut self) -> (i32, i32) {
         self.window.get_framebuffer_size()
     }
@@ -160,6 +181,26 @@ impl Window {
         self.window.set_scroll_polling(val)
     }
 
+    pub fn set_mouse_button_polling(&mut self, val: bool) {
+        self.window.set_mouse_button_polling(val)
+    }
+
+    pub fn set_key_polling(&mut self, val: bool) {
+        self.window.set_key_polling(val)
+    }
+
+    pub fn set_char_polling(&mut self, val: bool) {
+        self.window.set_char_polling(val)
+    }
+
+    pub fn set_focus_polling(&mut self, val: bool) {
+        self.window.set_focus_polling(val)
+    }
+
+    pub fn set_close_polling(&mut self, val: bool) {
+        self.window.set_close_polling(val)
+    }
+
     pub fn get_proc_address(&mut self, procname: &str) -> glfw::GLProc  {
         self.window.get_proc_address(procname)
     }
@@ -58,27 +58,38 @@ impl World {
 
 }
 
-use std::ffi::c_void;
-use std::mem::size_of;
+use std::rc::Rc;
 
 use block_mesh::{RIGHT_HANDED_Y_UP_CONFIG, greedy_quads, GreedyQuadsBuffer};
 use block_mesh::ndshape::{ConstShape, ConstShape3u32};
+use engine::backend::Backend;
 use engine::glm;
+use engine::impl_vertex;
 use engine::shader::Shader;
+use engine::texture::Texture;
+use engine::vertex_array::Mesh;
 use noise::{Fbm, Perlin, NoiseFn};
 
 use crate::glm::{I32Vec3, Vec3};
 
 type ChunkSize = ConstShape3u32<18_u32, 18_u32, 18_u32>;
 
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct ChunkVertex {
+    position: [f32; 3],
+    tex_coord: [f32; 2]
+}
+
+impl_vertex!(ChunkVertex { 0 => position, 1 => tex_coord });
+
 #[derive(Debug)]
 pub struct Chunk {
     position: I32Vec3,
     blocks: [Block; 5832],
     is_empty: bool,
-    mesh: Vec<f32>,
-    vao: u32,
-    vbo: u32
+    mesh: Vec<ChunkVertex>,
+    gpu_mesh: Option<Mesh<ChunkVertex>>
 }
 
 impl Chunk {
@@ -111,8 +122,7 @@ impl Chunk {
                 is_empty: true,
                 blocks: [Block::AIR; 5832],
                 mesh: Vec::new(),
-                vao: 0,
-                vbo: 0
+                gpu_mesh: None
             }
         }
         // Check first slice for air, if full of air, chunk empty
@@ -127,19 +137,18 @@ impl Chunk {
                 }
             }
         }
-        let (mesh, vao, vbo) = Chunk::create_mesh(&blocks);
+        let mesh = Chunk::create_mesh(&blocks);
 
         Self {
             blocks,
             mesh,
             position,
-            vao,
-            vbo,
+            gpu_mesh: None,
             is_empty: false
         }
     }
 
-    fn create_mesh(voxels: &[Block; 5832]) -> (Vec<f32>, u32, u32) {
+    fn create_mesh(voxels: &[Block; 5832]) -> Vec<ChunkVertex> {
         let faces = RIGHT_HANDED_Y_UP_CONFIG.faces;
 
         let mut buffer = GreedyQuadsBuffer::new(voxels.len());
@@ -173,76 +182,35 @@ impl Chunk {
                 ));
             }
         }
-        let mut test_vertices: Vec<f32> = Vec::with_capacity(num_indices * 5);
+        let mut test_vertices: Vec<ChunkVertex> = Vec::with_capacity(num_indices);
         for index in indices.into_iter() {
-            test_vertices.extend_from_slice(&positions[index as usize]);
-            test_vertices.extend_from_slice(&tex_coords[index as usize])
+            test_vertices.push(ChunkVertex {
+                position: positions[index as usize],
+                tex_coord: tex_coords[index as usize]
+            })
         }
         println!("test_vertices.len(), {}", test_vertices.len());
-        (test_vertices, 0, 0)
-        //unsafe {
-        //    Chunk::setup_mesh(test_vertices)
-        //}
+        test_vertices
+        //self.setup_mesh(backend)
     }
 
-    unsafe fn setup_mesh(data: Vec<f32>) -> (Vec<f32>, u32, u32) {
-        let mut vao: u32 = 0;
-        let mut vbo: u32 = 0;
-
-        gl::BindBuffer(gl::ARRAY_BUFFER, 0);
-        gl::BindVertexArray(0);
-
-        gl::GenVertexArrays(1, &mut vao);
-        gl::GenBuffers(1, &mut vbo);
-
-        gl::BindVertexArray(vao);
-
-        gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
-        gl::BufferData(
-            gl::ARRAY_BUFFER,
-            (data.len() * size_of::<gl::types::GLfloat>()) as gl::types::GLsizeiptr,
-            data.as_ptr().cast(),
-            gl::STATIC_DRAW
-        );
-
-        gl::EnableVertexAttribArray(0);
-        gl::VertexAttribPointer(
-            0,
-            3,
-            gl::FLOAT,
-            gl::FALSE,
-            (5 * size_of::<f32>()).try_into().unwrap(),
-            std::ptr::null(),
-        );
-
-        gl::EnableVertexAttribArray(1);
-        gl::VertexAttribPointer(
-            1,
-            2,
-            gl::FLOAT,
-            gl::FALSE,
-            (5 * size_of::<f32>()).try_into().unwrap(),
-            (3 * size_of::<f32>()) as *const c_void,
-        );
-
-        gl::BindBuffer(gl::ARRAY_BUFFER, 0);
-        gl::BindVertexArray(0);
-
-        (data, vao, vbo)
+    pub fn setup_mesh(&mut self, backend: &Rc<dyn Backend>) {
+        self.gpu_mesh = Some(Mesh::new(backend, &self.mesh, None, gl::STATIC_DRAW));
     }
 
-    pub unsafe fn draw(&self, texture: &u32, shader: &Shader) {
+    pub fn draw(&self, texture: &Texture, shader: &Shader) {
         if self.is_empty {
             return
         }
-        gl::BindVertexArray(self.vao);
-        gl::BindTexture(gl::TEXTURE_2D, *texture);
+        let Some(mesh) = self.gpu_mesh.as_ref() else {
+            return
+        };
+        texture.bind(0);
         shader.use_program();
         let model = glm::translation(&Vec3::new(((self.position.x * 16) - 1) as f32, ((self.position.y * 16) - 1 /* size of cube gen */) as f32, ((self.position.z * 16) - 1) as f32,));
         shader.set_mat4("model", &model);
-        gl::DrawArrays(gl::TRIANGLES, 0, (self.mesh.len() / 3) as i32);
-        gl::BindVertexArray(0);
-        gl::BindTexture(gl::TEXTURE_2D, 0);
+        mesh.draw(gl::TRIANGLES);
+        texture.unbind(0);
 
     }
 }
@@ -0,0 +1,21 @@
+#version 330 core
+#include "engine/frame.glsl"
+
+out vec4 Color;
+
+in vec3 WorldPos;
+in vec3 Normal;
+
+// horizontal distance at which clouds have fully faded into the fog
+uniform float fadeDistance;
+uniform float opacity;
+
+void main()
+{
+    float diffuse = max(dot(normalize(Normal), lightDirection), 0.0);
+    vec3 colour = min(vec3(ambientLight * 2.0) + diffuse * lightColour.rgb * 0.6, vec3(1.0));
+
+    float dist = length(WorldPos.xz - cameraPosition.xz);
+    float fade = smoothstep(fadeDistance * 0.4, fadeDistance, dist);
+    Color = vec4(mix(colour, fogColour.rgb, fade), opacity * (1.0 - fade));
+}
@@ -0,0 +1,18 @@
+#version 330 core
+#include "engine/frame.glsl"
+
+layout (location = 0) in vec3 Position;
+layout (location = 1) in vec3 aNormal;
+
+out vec3 WorldPos;
+out vec3 Normal;
+
+uniform mat4 model;
+
+void main()
+{
+    vec4 worldPos = model * vec4(Position, 1.0);
+    gl_Position = projection * view * worldPos;
+    WorldPos = worldPos.xyz;
+    Normal = aNormal;
+}
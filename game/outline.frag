@@ -0,0 +1,10 @@
+#version 330 core
+
+out vec4 Color;
+
+uniform vec4 colour;
+
+void main()
+{
+    Color = colour;
+}
@@ -0,0 +1,14 @@
+#version 330 core
+#include "engine/frame.glsl"
+
+layout (location = 0) in vec3 Position;
+
+uniform mat4 model;
+// moves the outline towards the camera in clip space so it isn't hidden by the faces it sits on
+uniform float depthOffset;
+
+void main()
+{
+    gl_Position = projection * view * model * vec4(Position, 1.0);
+    gl_Position.z -= depthOffset * gl_Position.w;
+}
@@ -0,0 +1,51 @@
+#pragma once
+// needs engine/frame.glsl included first, uniforms are set by engine::shadows::ShadowMap::bind
+
+#define MAX_CASCADES 4
+
+uniform sampler2DShadow shadowMap;
+uniform mat4 cascadeMatrices[MAX_CASCADES];
+// atlas tile of each cascade as min x, min y, max x, max y
+uniform vec4 cascadeTiles[MAX_CASCADES];
+// view distance each cascade ends at
+uniform vec4 cascadeSplits;
+// world space size of a shadow map texel in each cascade
+uniform vec4 cascadeTexelSizes;
+uniform int cascadeCount;
+uniform vec2 shadowTexelSize;
+uniform float shadowNormalBias;
+uniform int shadowPcfRadius;
+
+// 1.0 where the light reaches, 0.0 in full shadow
+float shadowFactor(vec3 worldPos, vec3 normal)
+{
+    float viewDepth = -(view * vec4(worldPos, 1.0)).z;
+    int cascade = -1;
+    for (int i = 0; i < cascadeCount; i++) {
+        if (viewDepth < cascadeSplits[i]) {
+            cascade = i;
+            break;
+        }
+    }
+    if (cascade < 0) {
+        return 1.0;
+    }
+
+    // pushing out along the normal keeps surfaces from shadowing themselves
+    vec3 offsetPos = worldPos + normal * shadowNormalBias * cascadeTexelSizes[cascade];
+    vec3 coord = (cascadeMatrices[cascade] * vec4(offsetPos, 1.0)).xyz;
+    // keep the kernel inside the tile so it doesn't read the neighbouring cascade
+    vec4 tile = cascadeTiles[cascade];
+    vec2 tileMin = tile.xy + shadowTexelSize * 0.5;
+    vec2 tileMax = tile.zw - shadowTexelSize * 0.5;
+
+    float lit = 0.0;
+    for (int x = -shadowPcfRadius; x <= shadowPcfRadius; x++) {
+        for (int y = -shadowPcfRadius; y <= shadowPcfRadius; y++) {
+            vec2 uv = clamp(coord.xy + vec2(x, y) * shadowTexelSize, tileMin, tileMax);
+            lit += texture(shadowMap, vec3(uv, coord.z));
+        }
+    }
+    float width = float(shadowPcfRadius * 2 + 1);
+    return lit / (width * width);
+}
@@ -0,0 +1,48 @@
+#version 330 core
+#include "engine/frame.glsl"
+
+out vec4 Color;
+
+in vec3 ViewRay;
+
+uniform vec3 sunDirection;
+uniform vec4 zenithColour;
+uniform vec4 horizonColour;
+uniform float starVisibility;
+
+const vec3 SUN_COLOUR = vec3(1.0, 0.95, 0.8);
+const vec3 MOON_COLOUR = vec3(0.8, 0.85, 0.95);
+
+float hash(vec3 p)
+{
+    p = fract(p * 0.3183099 + 0.1);
+    p *= 17.0;
+    return fract(p.x * p.y * p.z * (p.x + p.y + p.z));
+}
+
+void main()
+{
+    vec3 direction = normalize(ViewRay);
+    float height = max(direction.y, 0.0);
+    vec3 colour = mix(horizonColour.rgb, zenithColour.rgb, sqrt(height));
+
+    // stars turn with the sun so they rise and set
+    if (starVisibility > 0.0) {
+        vec3 axis = vec3(0.0, 0.0, 1.0);
+        float angle = atan(sunDirection.y, sunDirection.x);
+        vec3 starDirection = direction * cos(angle) + cross(axis, direction) * sin(angle) + axis * dot(axis, direction) * (1.0 - cos(angle));
+        vec3 cell = floor(starDirection * 200.0);
+        float star = step(0.9985, hash(cell));
+        float twinkle = 0.75 + 0.25 * sin(time * 3.0 + hash(cell + 1.0) * 40.0);
+        colour += vec3(star * twinkle * starVisibility * smoothstep(0.0, 0.1, height));
+    }
+
+    float sunDot = dot(direction, sunDirection);
+    colour += SUN_COLOUR * pow(max(sunDot, 0.0), 256.0) * 0.5;
+    colour = mix(colour, SUN_COLOUR, smoothstep(0.9990, 0.9993, sunDot));
+
+    float moonDot = dot(direction, -sunDirection);
+    colour = mix(colour, MOON_COLOUR, smoothstep(0.9994, 0.9996, moonDot));
+
+    Color = vec4(colour, 1.0);
+}
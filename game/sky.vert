@@ -0,0 +1,15 @@
+#version 330 core
+#include "engine/frame.glsl"
+
+// fullscreen triangle, no vertex buffer needed
+out vec3 ViewRay;
+
+void main()
+{
+    vec2 position = vec2((gl_VertexID << 1) & 2, gl_VertexID & 2) * 2.0 - 1.0;
+    gl_Position = vec4(position, 1.0, 1.0);
+    // rotation only, the sky is infinitely far away
+    mat4 inverseViewProjection = inverse(projection * mat4(mat3(view)));
+    vec4 ray = inverseViewProjection * vec4(position, 1.0, 1.0);
+    ViewRay = ray.xyz / ray.w;
+}
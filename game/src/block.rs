@@ -60,4 +60,10 @@ impl Block {
     pub const DIRT: Block = Block(1);
     pub const GRASS: Block = Block(2);
     pub const STONE: Block = Block(3);
+
+    /// Tile of the block texture atlas used for this block.
+    /// Chunks are drawn with a single texture for now, so every block shares tile 0.
+    pub fn atlas_tile(&self) -> u32 {
+        0
+    }
 }
\ No newline at end of file
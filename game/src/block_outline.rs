@@ -0,0 +1,67 @@
+use std::rc::Rc;
+
+use engine::{
+    backend::Backend,
+    glm::{self, IVec3, Vec3, Vec4},
+    impl_vertex,
+    shader::{Shader, ShaderError, FRAME_UNIFORMS_BINDING},
+    vertex_array::Mesh,
+};
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct OutlineVertex {
+    position: [f32; 3]
+}
+impl_vertex!(OutlineVertex { 0 => position });
+
+/// Wireframe box drawn around the block the player is looking at
+pub struct BlockOutline {
+    pub shader: Shader,
+    pub colour: Vec4,
+    /// Clip space depth the lines are pulled towards the camera by, enough to beat the
+    /// block's own faces without showing through blocks in front of it
+    pub depth_offset: f32,
+    mesh: Mesh<OutlineVertex>,
+    backend: Rc<dyn Backend>
+}
+
+impl BlockOutline {
+    pub fn new(backend: &Rc<dyn Backend>) -> Result<Self, ShaderError> {
+        let mut shader = Shader::new(backend, "outline.vert", "outline.frag")?;
+        shader.bind_uniform_block("Frame", FRAME_UNIFORMS_BINDING);
+
+        // the 12 edges of a unit cube, as pairs of corners
+        let mut vertices = Vec::with_capacity(24);
+        for axis in 0..3 {
+            for corner in 0..4 {
+                let mut start = [0.0; 3];
+                start[(axis + 1) % 3] = (corner & 1) as f32;
+                start[(axis + 2) % 3] = (corner >> 1) as f32;
+                let mut end = start;
+                end[axis] = 1.0;
+                vertices.push(OutlineVertex { position: start });
+                vertices.push(OutlineVertex { position: end });
+            }
+        }
+
+        Ok(Self {
+            shader,
+            colour: Vec4::new(0.0, 0.0, 0.0, 0.7),
+            depth_offset: 0.0005,
+            mesh: Mesh::new(backend, &vertices, None, gl::STATIC_DRAW),
+            backend: backend.clone()
+        })
+    }
+
+    pub fn draw(&self, block: IVec3) {
+        self.backend.enable(gl::BLEND);
+        self.backend.blend_func(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+        self.shader.use_program();
+        self.shader.set_mat4("model", &glm::translation(&glm::convert::<IVec3, Vec3>(block)));
+        self.shader.set_float("depthOffset", self.depth_offset);
+        self.shader.set_vec4("colour", self.colour.x, self.colour.y, self.colour.z, self.colour.w);
+        self.mesh.draw(gl::LINES);
+        self.backend.disable(gl::BLEND);
+    }
+}
@@ -1,11 +1,14 @@
 use std::collections::HashSet;
-use std::ffi::c_void;
-use std::mem::size_of;
+use std::rc::Rc;
 
 use block_mesh::{RIGHT_HANDED_Y_UP_CONFIG, greedy_quads, GreedyQuadsBuffer};
 use block_mesh::ndshape::{ConstShape, ConstShape2u32};
+use engine::backend::Backend;
 use engine::glm;
+use engine::impl_vertex;
+use engine::vertex_array::Mesh;
 use engine::shader::Shader;
+use engine::texture::Texture;
 use noise::{Fbm, Perlin, NoiseFn};
 
 use crate::block::Block;
@@ -15,6 +18,16 @@ use crate::ConstShape3u32;
 pub type ChunkSize = ConstShape3u32<18, 18, 18>;
 pub type NoiseSize = ConstShape2u32<16, 16>;
 
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct ChunkVertex {
+    pub position: [f32; 3],
+    pub normal: [f32; 3],
+    pub tex_coord: [f32; 2]
+}
+
+impl_vertex!(ChunkVertex { 0 => position, 1 => normal, 2 => tex_coord });
+
 #[derive(Debug, Clone)]
 pub struct Chunk {
     pub filled_blocks: HashSet<(u32, u32, u32)>,
@@ -23,14 +36,11 @@ pub struct Chunk {
     pub has_changed: bool,
     is_empty: bool,
     visible: bool,
-    mesh: Option<Vec<f32>>,
-    vao: Option<u32>,
-    vbo: Option<u32>
+    mesh: Option<Rc<Mesh<ChunkVertex>>>
 }
 
 impl Chunk {
     pub fn new(position: I32Vec3, noise: &Fbm<Perlin>) -> Self {
-        println!("{}", position.y);
         let x_offset = position.x * 16;
         let y_offset = position.y as u32 * 16;
         let z_offset = position.z * 16;
@@ -57,7 +67,6 @@ impl Chunk {
                 }
             }
         }
-        println!("pos_empty: {}, pos_full: {}", pos_empty, pos_full);
         if pos_empty == 256 {
             visible = false;
             blocks = [Block::AIR; 5832];
@@ -97,8 +106,6 @@ impl Chunk {
             mesh: None,
             position,
             visible,
-            vao: None,
-            vbo: None,
         }
     }
 
@@ -119,9 +126,21 @@ impl Chunk {
 
     pub fn update_block(&mut self, position: I32Vec3, block: Block) {
         self.blocks[ChunkSize::linearize([position.x as u32, position.y as u32, position.z as u32]) as usize] = block;
+        self.has_changed = true;
+    }
+
+    /// Whether the chunk has a mesh with something in it to draw
+    pub fn is_visible(&self) -> bool {
+        self.visible && self.mesh.is_some()
     }
 
-    pub fn create_mesh(&mut self) {
+    /// GPU memory used by the chunk's mesh
+    pub fn mesh_size_bytes(&self) -> usize {
+        self.mesh.as_ref().map_or(0, |mesh| mesh.size_bytes())
+    }
+
+    pub fn create_mesh(&mut self, backend: &Rc<dyn Backend>) {
+        self.has_changed = false;
         if self.is_empty {
             return
         }
@@ -137,10 +156,13 @@ impl Chunk {
             &faces,
             &mut buffer
         );
-        println!("quads: {}", buffer.quads.num_quads());
         if buffer.quads.num_quads() == 0 {
+            // an emptied chunk drops its old mesh, or it stays in GPU memory and in the stats
+            self.mesh = None;
             self.visible = false;
+            return
         }
+        self.visible = true;
 
         let num_indices = buffer.quads.num_quads() * 6;
         let num_vertices = buffer.quads.num_quads() * 4;
@@ -160,89 +182,47 @@ impl Chunk {
                 ));
             }
         }
-        let mut test_vertices: Vec<f32> = Vec::with_capacity(num_indices * 5);
-        for index in indices.into_iter() {
-            test_vertices.extend_from_slice(&positions[index as usize]);
-            test_vertices.extend_from_slice(&normals[index as usize]);
-            test_vertices.extend_from_slice(&tex_coords[index as usize])
-        }
-        println!("test_vertices.len(), {}", test_vertices.len());
-        let (vertices, vao, vbo) = unsafe {
-            Chunk::setup_mesh(test_vertices)
-        };
-        self.mesh = Some(vertices);
-        self.vao = Some(vao);
-        self.vbo = Some(vbo);
+        let vertices: Vec<ChunkVertex> = positions.into_iter()
+            .zip(normals)
+            .zip(tex_coords)
+            .map(|((position, normal), tex_coord)| ChunkVertex { position, normal, tex_coord })
+            .collect();
+        self.mesh = Some(Rc::new(Mesh::new(backend, &vertices, Some(&indices), gl::STATIC_DRAW)));
     }
 
-    unsafe fn setup_mesh(data: Vec<f32>) -> (Vec<f32>, u32, u32) {
-        let mut vao: u32 = 0;
-        let mut vbo: u32 = 0;
-
-        gl::BindBuffer(gl::ARRAY_BUFFER, 0);
-        gl::BindVertexArray(0);
-
-        gl::GenVertexArrays(1, &mut vao);
-        gl::GenBuffers(1, &mut vbo);
-
-        gl::BindVertexArray(vao);
-
-        gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
-        gl::BufferData(
-            gl::ARRAY_BUFFER,
-            (data.len() * size_of::<gl::types::GLfloat>()) as gl::types::GLsizeiptr,
-            data.as_ptr().cast(),
-            gl::STATIC_DRAW
-        );
-
-        gl::EnableVertexAttribArray(0); // position coords
-        gl::VertexAttribPointer(
-            0,
-            3,
-            gl::FLOAT,
-            gl::FALSE,
-            (8 * size_of::<f32>()).try_into().unwrap(),
-            std::ptr::null(),
-        );
-
-        gl::EnableVertexAttribArray(1); // normal coords
-        gl::VertexAttribPointer(
-            1,
-            3,
-            gl::FLOAT,
-            gl::FALSE,
-            (8 * size_of::<f32>()).try_into().unwrap(),
-            (3 * size_of::<f32>()) as *const c_void,
-        );
-
-        gl::EnableVertexAttribArray(2); // texture coords
-        gl::VertexAttribPointer(
-            2,
-            2,
-            gl::FLOAT,
-            gl::FALSE,
-            (8 * size_of::<f32>()).try_into().unwrap(),
-            (6 * size_of::<f32>()) as *const c_void,
-        );
-
-        gl::BindBuffer(gl::ARRAY_BUFFER, 0);
-        gl::BindVertexArray(0);
+    pub fn draw(&self, texture: &Texture, shader: &Shader) {
+        if !self.visible {
+            return
+        }
+        let Some(mesh) = self.mesh.as_ref() else {
+            return
+        };
+        texture.bind(0);
+        shader.use_program();
+        shader.set_mat4("model", &self.model_matrix());
+        mesh.draw(gl::TRIANGLES);
+        texture.unbind(0);
 
-        (data, vao, vbo)
     }
 
-    pub unsafe fn draw(&self, texture: &u32, shader: &Shader) {
+    /// Draws the mesh untextured with a shader that's already in use, for depth only passes
+    pub fn draw_depth(&self, shader: &Shader) {
         if !self.visible {
             return
         }
-        gl::BindVertexArray(self.vao.unwrap());
-        gl::BindTexture(gl::TEXTURE_2D, *texture);
-        shader.use_program();
-        let model = glm::translation(&Vec3::new(((self.position.x * 16) - 1) as f32, ((self.position.y * 16) - 1 /* size of cube gen */) as f32, ((self.position.z * 16) - 1) as f32,));
-        shader.set_mat4("model", &model);
-        gl::DrawArrays(gl::TRIANGLES, 0, (self.mesh.as_ref().unwrap().len() / 3) as i32);
-        gl::BindVertexArray(0);
-        gl::BindTexture(gl::TEXTURE_2D, 0);
+        let Some(mesh) = self.mesh.as_ref() else {
+            return
+        };
+        shader.set_mat4("model", &self.model_matrix());
+        mesh.draw(gl::TRIANGLES);
+    }
+
+    /// World space centre of the chunk
+    pub fn centre(&self) -> Vec3 {
+        (self.position * 16).cast::<f32>() + Vec3::repeat(8.0)
+    }
 
+    fn model_matrix(&self) -> glm::Mat4 {
+        glm::translation(&Vec3::new(((self.position.x * 16) - 1) as f32, ((self.position.y * 16) - 1 /* size of cube gen */) as f32, ((self.position.z * 16) - 1) as f32,))
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,206 @@
+use std::rc::Rc;
+
+use engine::{
+    backend::Backend,
+    glm::{self, IVec2, Vec2, Vec3},
+    impl_vertex,
+    shader::{Shader, ShaderError, FRAME_UNIFORMS_BINDING},
+    vertex_array::Mesh,
+};
+use noise::{Fbm, MultiFractal, NoiseFn, Perlin, Seedable};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CloudStyle {
+    /// One quad per cloud cell
+    Flat,
+    /// Cloud cells extruded into boxes `thickness` tall
+    Extruded
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CloudSettings {
+    /// Height of the bottom of the cloud layer
+    pub altitude: f32,
+    /// Width of a cloud cell in blocks
+    pub cell_size: f32,
+    pub thickness: f32,
+    /// Cells meshed in each direction around the camera
+    pub radius: i32,
+    /// Rough fraction of the sky covered, 0.0 to 1.0
+    pub coverage: f64,
+    /// Noise frequency per cell, smaller values give larger clouds
+    pub noise_scale: f64,
+    /// Blocks per second along x and z
+    pub wind: Vec2,
+    pub style: CloudStyle,
+    pub opacity: f32,
+    pub seed: u32
+}
+
+impl Default for CloudSettings {
+    fn default() -> Self {
+        Self {
+            altitude: 300.0,
+            cell_size: 8.0,
+            thickness: 4.0,
+            radius: 24,
+            coverage: 0.4,
+            noise_scale: 0.06,
+            wind: Vec2::new(2.0, 0.5),
+            style: CloudStyle::Extruded,
+            opacity: 0.8,
+            seed: 0
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct CloudVertex {
+    position: [f32; 3],
+    normal: [f32; 3]
+}
+impl_vertex!(CloudVertex { 0 => position, 1 => normal });
+
+/// A layer of noise generated clouds drifting with the wind.
+/// The mesh is built in cloud space around the camera and only rebuilt when
+/// the camera crosses into a new cloud cell, wind just moves the model matrix.
+pub struct Clouds {
+    settings: CloudSettings,
+    pub shader: Shader,
+    noise: Fbm<Perlin>,
+    mesh: Option<Mesh<CloudVertex>>,
+    /// Cloud cell the mesh was built around, `None` forces a rebuild
+    mesh_centre: Option<IVec2>,
+    /// Distance the wind has carried the clouds, in blocks
+    wind_offset: Vec2,
+    backend: Rc<dyn Backend>
+}
+
+impl Clouds {
+    pub fn new(backend: &Rc<dyn Backend>, settings: CloudSettings) -> Result<Self, ShaderError> {
+        let mut shader = Shader::new(backend, "clouds.vert", "clouds.frag")?;
+        shader.bind_uniform_block("Frame", FRAME_UNIFORMS_BINDING);
+        Ok(Self {
+            noise: Self::create_noise(settings.seed),
+            settings,
+            shader,
+            mesh: None,
+            mesh_centre: None,
+            wind_offset: Vec2::zeros(),
+            backend: backend.clone()
+        })
+    }
+
+    pub fn settings(&self) -> &CloudSettings {
+        &self.settings
+    }
+
+    /// Replaces the settings, rebuilding the mesh on the next `update`
+    pub fn set_settings(&mut self, settings: CloudSettings) {
+        if settings.seed != self.settings.seed {
+            self.noise = Self::create_noise(settings.seed);
+        }
+        self.settings = settings;
+        self.mesh_centre = None;
+    }
+
+    /// Moves the clouds with the wind and remeshes if the camera entered another cell
+    pub fn update(&mut self, delta_time: f32, camera_position: &Vec3) {
+        self.wind_offset += self.settings.wind * delta_time;
+
+        let cloud_space = (camera_position.xz() - self.wind_offset) / self.settings.cell_size;
+        let centre = IVec2::new(cloud_space.x.floor() as i32, cloud_space.y.floor() as i32);
+        if self.mesh_centre != Some(centre) {
+            self.build_mesh(centre);
+            self.mesh_centre = Some(centre);
+        }
+    }
+
+    /// Draws with blending, call after opaque geometry
+    pub fn draw(&self) {
+        let Some(mesh) = self.mesh.as_ref() else {
+            return
+        };
+        self.backend.enable(gl::BLEND);
+        self.backend.blend_func(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+        self.shader.use_program();
+        let model = glm::translation(&Vec3::new(self.wind_offset.x, self.settings.altitude, self.wind_offset.y));
+        self.shader.set_mat4("model", &model);
+        self.shader.set_float("fadeDistance", self.settings.radius as f32 * self.settings.cell_size);
+        self.shader.set_float("opacity", self.settings.opacity);
+        mesh.draw(gl::TRIANGLES);
+        self.backend.disable(gl::BLEND);
+    }
+
+    fn create_noise(seed: u32) -> Fbm<Perlin> {
+        Fbm::<Perlin>::default().set_seed(seed).set_octaves(3)
+    }
+
+    fn is_cloud(&self, cell: IVec2) -> bool {
+        let value = self.noise.get([cell.x as f64 * self.settings.noise_scale, cell.y as f64 * self.settings.noise_scale]) * 0.5 + 0.5;
+        value > 1.0 - self.settings.coverage
+    }
+
+    fn build_mesh(&mut self, centre: IVec2) {
+        let radius = self.settings.radius;
+        // one cell of padding so the edge cells can check their neighbours
+        let width = radius * 2 + 3;
+        let mut filled = vec![false; (width * width) as usize];
+        for z in 0..width {
+            for x in 0..width {
+                let cell = centre + IVec2::new(x - radius - 1, z - radius - 1);
+                let in_range = (1..width - 1).contains(&x) && (1..width - 1).contains(&z);
+                filled[(z * width + x) as usize] = in_range && self.is_cloud(cell);
+            }
+        }
+        let is_filled = |x: i32, z: i32| filled[(z * width + x) as usize];
+
+        let size = self.settings.cell_size;
+        let top = match self.settings.style {
+            CloudStyle::Flat => 0.0,
+            CloudStyle::Extruded => self.settings.thickness
+        };
+        let mut vertices = Vec::new();
+        let mut indices = Vec::new();
+        for z in 1..width - 1 {
+            for x in 1..width - 1 {
+                if !is_filled(x, z) {
+                    continue
+                }
+                let cell = centre + IVec2::new(x - radius - 1, z - radius - 1);
+                let (x0, z0) = (cell.x as f32 * size, cell.y as f32 * size);
+                let (x1, z1) = (x0 + size, z0 + size);
+
+                push_quad(&mut vertices, &mut indices, [[x0, top, z0], [x0, top, z1], [x1, top, z1], [x1, top, z0]], [0.0, 1.0, 0.0]);
+                if self.settings.style == CloudStyle::Flat {
+                    continue
+                }
+                push_quad(&mut vertices, &mut indices, [[x0, 0.0, z0], [x1, 0.0, z0], [x1, 0.0, z1], [x0, 0.0, z1]], [0.0, -1.0, 0.0]);
+                if !is_filled(x + 1, z) {
+                    push_quad(&mut vertices, &mut indices, [[x1, 0.0, z0], [x1, top, z0], [x1, top, z1], [x1, 0.0, z1]], [1.0, 0.0, 0.0]);
+                }
+                if !is_filled(x - 1, z) {
+                    push_quad(&mut vertices, &mut indices, [[x0, 0.0, z1], [x0, top, z1], [x0, top, z0], [x0, 0.0, z0]], [-1.0, 0.0, 0.0]);
+                }
+                if !is_filled(x, z + 1) {
+                    push_quad(&mut vertices, &mut indices, [[x1, 0.0, z1], [x1, top, z1], [x0, top, z1], [x0, 0.0, z1]], [0.0, 0.0, 1.0]);
+                }
+                if !is_filled(x, z - 1) {
+                    push_quad(&mut vertices, &mut indices, [[x0, 0.0, z0], [x0, top, z0], [x1, top, z0], [x1, 0.0, z0]], [0.0, 0.0, -1.0]);
+                }
+            }
+        }
+
+        match &mut self.mesh {
+            Some(mesh) => mesh.update(&vertices, Some(&indices), gl::DYNAMIC_DRAW),
+            None => self.mesh = Some(Mesh::new(&self.backend, &vertices, Some(&indices), gl::DYNAMIC_DRAW))
+        }
+    }
+}
+
+fn push_quad(vertices: &mut Vec<CloudVertex>, indices: &mut Vec<u32>, corners: [[f32; 3]; 4], normal: [f32; 3]) {
+    let start = vertices.len() as u32;
+    vertices.extend(corners.iter().map(|&position| CloudVertex { position, normal }));
+    indices.extend([start, start + 1, start + 2, start, start + 2, start + 3]);
+}
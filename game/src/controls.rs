@@ -0,0 +1,177 @@
+use engine::{
+    events::Event,
+    gamepad::Gamepads,
+    glfw::{GamepadAxis, GamepadButton, Key, MouseButton},
+    glm,
+    input::{Axis, InputContext, InputManager},
+    keybinds::{Binding, KeyMap},
+    ui::{Rect, Ui},
+};
+
+pub const MOVE_FORWARD: &str = "move_forward";
+pub const MOVE_BACK: &str = "move_back";
+pub const MOVE_LEFT: &str = "move_left";
+pub const MOVE_RIGHT: &str = "move_right";
+pub const MOVE_UP: &str = "move_up";
+pub const MOVE_DOWN: &str = "move_down";
+pub const SPRINT: &str = "sprint";
+pub const BREAK_BLOCK: &str = "break_block";
+pub const PAUSE: &str = "pause";
+pub const TOGGLE_FULLSCREEN: &str = "toggle_fullscreen";
+pub const TOGGLE_DEBUG_OVERLAY: &str = "toggle_debug_overlay";
+pub const TOGGLE_CHUNK_BORDERS: &str = "toggle_chunk_borders";
+pub const TOGGLE_COLLISION_BOXES: &str = "toggle_collision_boxes";
+pub const TOGGLE_RAYCASTS: &str = "toggle_raycasts";
+pub const CYCLE_VIEW_MODE: &str = "cycle_view_mode";
+/// Held to walk through blocks
+pub const DISABLE_COLLISION: &str = "disable_collision";
+
+/// Movement axes, positive is right, forward and up
+pub const STRAFE: &str = "strafe";
+pub const WALK: &str = "walk";
+pub const FLY: &str = "fly";
+pub const LOOK_X: &str = "look_x";
+pub const LOOK_Y: &str = "look_y";
+pub const ZOOM: &str = "zoom";
+/// Gamepad look stick, turned into degrees by `GamepadConfig::look` rather than read as mouse movement
+pub const PAD_LOOK_X: &str = "pad_look_x";
+pub const PAD_LOOK_Y: &str = "pad_look_y";
+
+/// Always at the bottom of the stack
+pub const GAMEPLAY: &str = "gameplay";
+/// The pause menu, which still lets the game be unpaused
+pub const MENU: &str = "menu";
+/// A text field has keyboard focus
+pub const TEXT_ENTRY: &str = "text_entry";
+/// The controls menu is waiting for a new binding
+pub const REBINDING: &str = "rebinding";
+
+/// Bindings for actions missing from the config file
+pub const DEFAULT_BINDINGS: &[(&str, &[Binding])] = &[
+    (MOVE_FORWARD, &[Binding::key(Key::W)]),
+    (MOVE_BACK, &[Binding::key(Key::S)]),
+    (MOVE_LEFT, &[Binding::key(Key::A)]),
+    (MOVE_RIGHT, &[Binding::key(Key::D)]),
+    (MOVE_UP, &[Binding::key(Key::Space), Binding::gamepad(GamepadButton::ButtonA)]),
+    (MOVE_DOWN, &[Binding::key(Key::LeftShift), Binding::gamepad(GamepadButton::ButtonB)]),
+    (SPRINT, &[Binding::key(Key::LeftControl), Binding::gamepad(GamepadButton::ButtonLeftThumb)]),
+    (BREAK_BLOCK, &[Binding::mouse(MouseButton::Button1), Binding::trigger(GamepadAxis::AxisRightTrigger)]),
+    (PAUSE, &[Binding::key(Key::Escape), Binding::gamepad(GamepadButton::ButtonStart)]),
+    (TOGGLE_FULLSCREEN, &[Binding::key(Key::F11)]),
+    (TOGGLE_DEBUG_OVERLAY, &[Binding::key(Key::F3), Binding::gamepad(GamepadButton::ButtonBack)]),
+    (TOGGLE_CHUNK_BORDERS, &[Binding::key(Key::F4)]),
+    (TOGGLE_COLLISION_BOXES, &[Binding::key(Key::F5)]),
+    (TOGGLE_RAYCASTS, &[Binding::key(Key::F6)]),
+    (CYCLE_VIEW_MODE, &[Binding::key(Key::F7)]),
+    (DISABLE_COLLISION, &[Binding::key(Key::Backslash)])
+];
+
+/// Registers the game's axes and contexts, leaving only gameplay active
+pub fn setup_input(input: &mut InputManager) {
+    input.add_axis(STRAFE, Axis::actions(MOVE_LEFT, MOVE_RIGHT));
+    input.add_axis(STRAFE, Axis::Gamepad(GamepadAxis::AxisLeftX));
+    input.add_axis(WALK, Axis::actions(MOVE_BACK, MOVE_FORWARD));
+    input.add_axis(WALK, Axis::Gamepad(GamepadAxis::AxisLeftY));
+    input.add_axis(FLY, Axis::actions(MOVE_DOWN, MOVE_UP));
+    input.add_axis(LOOK_X, Axis::MouseX);
+    input.add_axis(LOOK_Y, Axis::MouseY);
+    input.add_axis(ZOOM, Axis::Scroll);
+    input.add_axis(PAD_LOOK_X, Axis::Gamepad(GamepadAxis::AxisRightX));
+    input.add_axis(PAD_LOOK_Y, Axis::Gamepad(GamepadAxis::AxisRightY));
+
+    let gameplay: Vec<&str> = DEFAULT_BINDINGS.iter()
+        .map(|(action, _)| *action)
+        .chain([STRAFE, WALK, FLY, LOOK_X, LOOK_Y, ZOOM, PAD_LOOK_X, PAD_LOOK_Y])
+        .collect();
+    input.add_context(GAMEPLAY, InputContext::new(&gameplay, false));
+    input.add_context(MENU, InputContext::new(&[PAUSE, TOGGLE_FULLSCREEN, TOGGLE_DEBUG_OVERLAY], true));
+    input.add_context(TEXT_ENTRY, InputContext::new(&[], true));
+    input.add_context(REBINDING, InputContext::new(&[], true));
+    input.push_context(GAMEPLAY);
+}
+
+/// Pause menu page listing every action's bindings. Clicking one waits for the
+/// next key, mouse or gamepad button press and binds it, Escape cancels.
+#[derive(Debug, Default)]
+pub struct ControlsMenu {
+    pub open: bool,
+    /// Action waiting for a new binding
+    listening: Option<String>,
+    /// Why the last rebind failed
+    message: Option<String>
+}
+
+impl ControlsMenu {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_listening(&self) -> bool {
+        self.listening.is_some()
+    }
+
+    pub fn close(&mut self) {
+        self.open = false;
+        self.listening = None;
+        self.message = None;
+    }
+
+    pub fn draw(&mut self, ui: &mut Ui, events: &[Event], gamepads: &Gamepads, keymap: &mut KeyMap) {
+        if let Some(action) = self.listening.clone() {
+            let pressed = events.iter().find_map(Binding::from_event).or_else(|| gamepads.just_pressed().map(Binding::from));
+            if let Some(binding) = pressed {
+                self.listening = None;
+                if binding != Binding::key(Key::Escape) {
+                    self.message = keymap.rebind(&action, binding).err().map(|err| err.to_string());
+                }
+            }
+        }
+
+        // only the prompt while listening, so the click being bound can't press a button
+        if let Some(action) = &self.listening {
+            let rect = Rect::centred_in(ui.screen_rect(), glm::vec2(640.0, 80.0));
+            ui.panel("controls", rect, |ui| {
+                ui.label(&format!("Press a key, mouse or gamepad button for {}", action_label(action)));
+                ui.label("Escape cancels");
+            });
+            return
+        }
+
+        let actions: Vec<(String, String)> = keymap.actions()
+            .map(|(action, bindings)| (action.to_string(), binding_label(bindings)))
+            .collect();
+        let rows = actions.len().div_ceil(2) as f32 + 3.0;
+        let rect = Rect::centred_in(ui.screen_rect(), glm::vec2(760.0, rows * 36.0 + 12.0));
+        let (listening, message) = (&mut self.listening, &mut self.message);
+        let mut back = false;
+        ui.panel("controls", rect, |ui| {
+            ui.label("Controls");
+            for pair in actions.chunks(2) {
+                ui.horizontal(|ui| {
+                    for (action, bindings) in pair {
+                        if ui.button(&format!("{}: {}", action_label(action), bindings)) {
+                            *listening = Some(action.clone());
+                            *message = None;
+                        }
+                    }
+                });
+            }
+            ui.label(message.as_deref().unwrap_or(""));
+            back = ui.button("Back");
+        });
+        if back {
+            self.close();
+        }
+    }
+}
+
+fn action_label(action: &str) -> String {
+    action.replace('_', " ")
+}
+
+fn binding_label(bindings: &[Binding]) -> String {
+    if bindings.is_empty() {
+        return String::from("unbound")
+    }
+    bindings.iter().map(Binding::to_string).collect::<Vec<_>>().join(", ")
+}
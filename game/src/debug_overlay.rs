@@ -0,0 +1,87 @@
+use std::collections::VecDeque;
+
+use engine::{
+    glm::{self, Vec2, Vec4},
+    renderer::ViewMode,
+    text::{TextRenderer, TextStyle},
+};
+
+use crate::world::World;
+
+/// Frames averaged for the FPS and frame time readouts
+const FRAME_HISTORY: usize = 60;
+
+/// Toggleable screen of performance and position info, drawn in the top left
+#[derive(Debug, Default)]
+pub struct DebugOverlay {
+    pub visible: bool,
+    frame_times: VecDeque<f32>
+}
+
+impl DebugOverlay {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn toggle(&mut self) {
+        self.visible = !self.visible;
+    }
+
+    /// Call once per frame, even while hidden, so the averages are ready when it opens
+    pub fn record_frame(&mut self, delta_time: f32) {
+        if self.frame_times.len() == FRAME_HISTORY {
+            self.frame_times.pop_front();
+        }
+        self.frame_times.push_back(delta_time);
+    }
+
+    pub fn queue(&self, text: &mut TextRenderer, world: &World, view_mode: ViewMode) {
+        if !self.visible {
+            return
+        }
+        let frame_count = self.frame_times.len().max(1) as f32;
+        let average = self.frame_times.iter().sum::<f32>() / frame_count;
+        let worst = self.frame_times.iter().copied().fold(0.0, f32::max);
+
+        let camera = &world.player.camera;
+        let position = camera.position;
+        let local = world.player.floored_normal_position;
+        let chunk = world.current_chunk;
+        let stats = world.chunk_stats();
+
+        let lines = [
+            format!("{:.0} fps ({:.2} ms avg, {:.2} ms max)", 1.0 / average.max(f32::EPSILON), average * 1000.0, worst * 1000.0),
+            String::new(),
+            format!("XYZ: {:.3} / {:.3} / {:.3}", position.x, position.y, position.z),
+            format!("Chunk: {} {} {}", chunk.x, chunk.y, chunk.z),
+            format!("Block in chunk: {} {} {}", local.0, local.1, local.2),
+            format!("Facing: {} (yaw {:.1}, pitch {:.1})", facing(&camera.front.xz()), camera.yaw, camera.pitch),
+            String::new(),
+            format!("Chunks: {} loaded, {} visible, {} culled", stats.loaded, stats.visible, stats.culled),
+            format!("Jobs: {} generating, {} meshing", stats.pending_generation, stats.pending_meshes),
+            format!("Chunk meshes: {:.2} MiB", stats.mesh_bytes as f32 / (1024.0 * 1024.0)),
+            format!("Day {} {:.0}%", world.time.day, world.time.time_of_day() * 100.0),
+            format!("View: {} (F7)", view_mode.name())
+        ];
+
+        let style = TextStyle { scale: 2.0, ..Default::default() };
+        let shadow = TextStyle { colour: Vec4::new(0.0, 0.0, 0.0, 0.6), ..style };
+        let line_height = text.font.line_height * style.scale;
+        for (i, line) in lines.iter().enumerate() {
+            let position = Vec2::new(8.0, 8.0 + i as f32 * line_height);
+            text.queue(line, position + glm::vec2(style.scale, style.scale), &shadow);
+            text.queue(line, position, &style);
+        }
+    }
+}
+
+/// Compass direction of a horizontal vector, -z is north
+fn facing(direction: &Vec2) -> &'static str {
+    if direction.x.abs() > direction.y.abs() {
+        if direction.x > 0.0 { "east (+X)" } else { "west (-X)" }
+    } else if direction.y > 0.0 {
+        "south (+Z)"
+    } else {
+        "north (-Z)"
+    }
+}
@@ -0,0 +1,98 @@
+use engine::{
+    debug_draw::DebugDraw,
+    glm::{self, IVec3, Vec3, Vec4},
+    input::InputManager,
+    renderer::Renderer,
+};
+
+use crate::{controls, raycast::RaycastHit, Game};
+
+/// Seconds a recorded raycast stays visible, long enough to walk around it
+const RAYCAST_LIFETIME: f32 = 10.0;
+
+/// Hotkey toggled debug drawing of chunk borders, collision boxes and raycasts,
+/// plus a hotkey to cycle the renderer's view modes. F4 to F7 by default.
+#[derive(Debug, Default)]
+pub struct DebugView {
+    pub chunk_borders: bool,
+    pub collision_boxes: bool,
+    pub raycasts: bool
+}
+
+impl DebugView {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn handle_keys(&mut self, input: &InputManager, renderer: &mut Renderer) {
+        let pressed = [controls::TOGGLE_CHUNK_BORDERS, controls::TOGGLE_COLLISION_BOXES, controls::TOGGLE_RAYCASTS, controls::CYCLE_VIEW_MODE]
+            .map(|action| input.just_pressed(action));
+        let [chunk_borders, collision_boxes, raycasts, view_mode] = pressed;
+        self.chunk_borders ^= chunk_borders;
+        self.collision_boxes ^= collision_boxes;
+        self.raycasts ^= raycasts;
+        if view_mode {
+            renderer.view_mode = renderer.view_mode.next();
+        }
+    }
+
+    /// Queues this frame's chunk borders and collision boxes
+    pub fn queue(&self, debug: &mut DebugDraw, game: &Game) {
+        let world = &game.world;
+        if self.chunk_borders {
+            for x in -1..=1 {
+                for y in -1..=1 {
+                    for z in -1..=1 {
+                        let chunk = world.current_chunk + IVec3::new(x, y, z);
+                        if !world.chunk_positions.contains(&chunk) {
+                            continue
+                        }
+                        let colour = if chunk == world.current_chunk {
+                            Vec4::new(1.0, 0.9, 0.1, 1.0)
+                        } else {
+                            Vec4::new(0.3, 0.5, 1.0, 0.5)
+                        };
+                        // pushed out slightly so the lines don't z-fight with faces on the border
+                        let min = glm::convert::<IVec3, Vec3>(chunk * 16).add_scalar(-0.01);
+                        debug.aabb(min, min.add_scalar(16.02), colour, 0.0);
+                    }
+                }
+            }
+        }
+
+        if self.collision_boxes {
+            let position = world.player.camera.position.coords;
+            // the block collision is tested against, see `Game::detect_collision`
+            let block = position.map(f32::floor);
+            let colour = if game.player_is_colliding {
+                Vec4::new(1.0, 0.2, 0.2, 1.0)
+            } else {
+                Vec4::new(0.2, 1.0, 0.2, 1.0)
+            };
+            debug.aabb(block, block.add_scalar(1.0), colour, 0.0);
+            // `Game::move_player` tests the block under a point offset from the camera
+            let offset = position.add_scalar(-0.15);
+            let offset_block = offset.map(f32::floor);
+            debug.sphere(offset, 0.05, Vec4::new(0.2, 0.9, 1.0, 1.0), 0.0);
+            debug.aabb(offset_block.add_scalar(0.01), offset_block.add_scalar(0.99), Vec4::new(0.2, 0.9, 1.0, 1.0), 0.0);
+        }
+    }
+
+    /// Records a ray and what it hit, if raycasts are being shown
+    pub fn queue_raycast(&self, debug: &mut DebugDraw, origin: &Vec3, direction: &Vec3, max_distance: f32, hit: Option<&RaycastHit>) {
+        if !self.raycasts {
+            return
+        }
+        let Some(hit) = hit else {
+            debug.line(*origin, origin + direction * max_distance, Vec4::new(1.0, 0.2, 0.2, 1.0), RAYCAST_LIFETIME);
+            return
+        };
+        let point = origin + direction * hit.distance;
+        let block = glm::convert::<IVec3, Vec3>(hit.block);
+        let normal = glm::convert::<IVec3, Vec3>(hit.normal);
+        debug.line(*origin, point, Vec4::new(0.2, 1.0, 0.2, 1.0), RAYCAST_LIFETIME);
+        debug.sphere(point, 0.05, Vec4::new(1.0, 1.0, 1.0, 1.0), RAYCAST_LIFETIME);
+        debug.arrow(point, point + normal * 0.5, Vec4::new(0.2, 0.6, 1.0, 1.0), RAYCAST_LIFETIME);
+        debug.aabb(block.add_scalar(-0.01), block.add_scalar(1.01), Vec4::new(1.0, 0.9, 0.1, 1.0), RAYCAST_LIFETIME);
+    }
+}
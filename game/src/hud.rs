@@ -0,0 +1,18 @@
+use engine::{
+    glm::{Vec2, Vec4},
+    ui::Ui,
+};
+
+/// Plus sign in the middle of the screen, sized with the UI's text scale
+pub fn draw_crosshair(ui: &mut Ui) {
+    let centre = (ui.screen_rect().min + ui.screen_rect().max) / 2.0;
+    let scale = ui.style.text_scale;
+    let (half_length, half_thickness) = (6.0 * scale, 0.5 * scale);
+    let colour = Vec4::new(1.0, 1.0, 1.0, 0.8);
+
+    let batch = ui.batch();
+    batch.rect(centre - Vec2::new(half_length, half_thickness), centre + Vec2::new(half_length, half_thickness), colour);
+    // vertical bar, split so the centre isn't drawn twice
+    batch.rect(centre - Vec2::new(half_thickness, half_length), centre + Vec2::new(half_thickness, -half_thickness), colour);
+    batch.rect(centre + Vec2::new(-half_thickness, half_thickness), centre + Vec2::new(half_thickness, half_length), colour);
+}
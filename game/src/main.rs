@@ -5,36 +5,92 @@ pub mod chunk;
 pub mod block;
 pub mod world;
 pub mod player;
-
-use std::path::Path;
+pub mod sky;
+pub mod clouds;
+pub mod raycast;
+pub mod debug_overlay;
+pub mod block_outline;
+pub mod hud;
+pub mod debug_view;
+pub mod controls;
+pub mod replay;
+
+use std::{env, path::PathBuf, process, rc::Rc};
 
 use engine::{
     window::Window,
+    backend::{Backend, GlBackend},
     camera::{Camera, CameraMovement},
     glfw::*,
-    keybinds::{
-        InputFunctionArguments, 
-        KeyBinding
-    },
-    input_functions::*,
-    shader::Shader,
+    events::Event,
+    gamepad::GamepadEvent,
+    input::InputManager,
+    input_functions::{increase_movement_speed, CameraContext},
+    keybinds::KeyBinding,
+    replay::Recording,
+    shader::{Shader, FrameUniforms, FRAME_UNIFORMS_BINDING},
+    buffer::UniformBuffer,
+    texture::{Texture, TextureSettings},
+    texture_atlas::TextureAtlas,
+    particles::{ParticleSystem, ParticleSettings},
+    text::{Font, TextRenderer},
+    ui::{Rect, Ui},
+    debug_draw::DebugDraw,
+    renderer::Renderer,
+    framebuffer::Framebuffer,
+    post_process::{PostChain, PostEffect},
+    shadows::{ShadowMap, ShadowSettings},
+    Config,
+    config::GamepadConfig,
     glm::{
         self,
         vec3,
         IVec3, Vec3
     },
-    na::{Unit, OPoint, Const, Point3},
+    na::{OPoint, Const, Point3},
 };
 use block_mesh::ndshape::ConstShape3u32;
 
 
 use crate::player::Player;
+use crate::sky::Sky;
+use crate::clouds::{Clouds, CloudSettings};
+use crate::raycast::{raycast, RaycastHit};
+use crate::block_outline::BlockOutline;
+use crate::debug_view::DebugView;
+use crate::block::Block;
+use crate::debug_overlay::DebugOverlay;
+use crate::controls::ControlsMenu;
 pub use crate::world::World;
 
+/// How far away blocks can be targeted, in blocks
+const REACH: f32 = 6.0;
+
+/// Settings file, relative to the working directory
+const CONFIG_PATH: &str = "config.toml";
+
+/// Half the diagonal of a chunk, for culling shadow casters
+const CHUNK_BOUNDING_RADIUS: f32 = 13.9;
+
+/// The result of pressing break block
+pub struct BreakAttempt {
+    pub hit: Option<RaycastHit>,
+    /// The block that was removed, `None` if nothing was in reach
+    pub broken: Option<Block>
+}
+
 pub struct Game {
     pub world: World,
     pub player_is_colliding: bool,
-    pub test_collision: bool
+    pub test_collision: bool,
+    /// Run by `tick` on the game itself
+    bindings: Vec<KeyBinding<Game>>
+}
+
+impl CameraContext for Game {
+    fn camera(&mut self) -> &mut Camera {
+        &mut self.world.player.camera
+    }
 }
 
 impl Game {
@@ -42,7 +98,11 @@ impl Game {
         Self {
             world,
             player_is_colliding: false,
-            test_collision: true
+            test_collision: true,
+            bindings: vec![
+                KeyBinding::new(controls::SPRINT, false, increase_movement_speed),
+                KeyBinding::new(controls::DISABLE_COLLISION, false, |game: &mut Game, action| game.test_collision = action == Action::Release)
+            ]
         }
     }
 
@@ -52,6 +112,12 @@ impl Game {
         self.detect_collision();
     } 
 
+    /// The block the camera is looking at, if one is within reach
+    pub fn target_block(&self) -> Option<RaycastHit> {
+        let camera = &self.world.player.camera;
+        raycast(&camera.position.coords, &camera.front, REACH, |position| self.world.is_solid(position))
+    }
+
     pub fn set_current_chunk(&mut self) {
         let current_position = IVec3::new(self.world.player.camera.position.x.floor() as i32, self.world.player.camera.position.y.floor() as i32, self.world.player.camera.position.z.floor() as i32);
         let mut current_chunk = IVec3::new(current_position.x / 16, current_position.y / 16, current_position.z / 16);
@@ -128,11 +194,53 @@ impl Game {
         
     }
 
-    fn process_movement_input(&mut self, window: &mut Window, delta_time: &f32, bindings: &mut [(Key, CameraMovement)]) {
-        let mut vector: Vec<CameraMovement> = Vec::new();
+    /// Advances the player and world by one frame of input. This is everything a replay
+    /// runs, so anything affecting where the player ends up belongs here rather than in `main`.
+    pub fn tick(&mut self, input: &InputManager, gamepad: &GamepadConfig, delta_time: f32, backend: &Rc<dyn Backend>) -> Option<BreakAttempt> {
+        let mut bindings = std::mem::take(&mut self.bindings);
         for binding in bindings.iter_mut() {
-            if window.get_key(binding.0) == Action::Press {
-                vector.push(binding.1)
+            binding.update(input, self);
+        }
+        self.bindings = bindings;
+
+        let camera = &mut self.world.player.camera;
+        let look = input.vector(controls::LOOK_X, controls::LOOK_Y);
+        if look != glm::Vec2::zeros() {
+            camera.process_mouse_input(look.x, look.y, true);
+        }
+        let pad_look = gamepad.look(input.vector(controls::PAD_LOOK_X, controls::PAD_LOOK_Y), delta_time);
+        if pad_look != glm::Vec2::zeros() {
+            camera.process_look_input(pad_look.x, pad_look.y, true);
+        }
+        let zoom = input.axis(controls::ZOOM);
+        if zoom != 0.0 {
+            camera.process_scroll_input(zoom);
+        }
+        self.run_loop();
+        let movement = vec3(input.axis(controls::STRAFE), input.axis(controls::WALK), input.axis(controls::FLY));
+        self.process_movement_input(movement, &delta_time);
+        self.world.time.tick(delta_time);
+
+        if !input.just_pressed(controls::BREAK_BLOCK) {
+            return None
+        }
+        let hit = self.target_block();
+        let broken = hit.and_then(|hit| self.world.break_block(hit.block, backend));
+        Some(BreakAttempt { hit, broken })
+    }
+
+    /// Moves the player along the movement axes, x strafes, y walks and z flies
+    fn process_movement_input(&mut self, axes: Vec3, delta_time: &f32) {
+        let mut vector: Vec<CameraMovement> = Vec::new();
+        for (value, negative, positive) in [
+            (axes.x, CameraMovement::Left, CameraMovement::Right),
+            (axes.y, CameraMovement::Backward, CameraMovement::Forward),
+            (axes.z, CameraMovement::Down, CameraMovement::Up)
+        ] {
+            if value < 0.0 {
+                vector.push(negative)
+            } else if value > 0.0 {
+                vector.push(positive)
             }
         }
         if vector.len() != 0 {
@@ -143,50 +251,76 @@ impl Game {
     }
 }
 
+/// Command line options
+#[derive(Debug, Default)]
+struct Args {
+    /// File to record input to until the game closes
+    record: Option<PathBuf>,
+    /// Recording to play back before returning to live input
+    replay: Option<PathBuf>,
+    /// Play `replay` back without a window and exit with whether it ended where it was recorded
+    headless: bool
+}
+
+impl Args {
+    fn parse() -> Self {
+        let mut args = Self::default();
+        let mut iter = env::args().skip(1);
+        while let Some(arg) = iter.next() {
+            match arg.as_str() {
+                "--record" => args.record = iter.next().map(PathBuf::from),
+                "--replay" => args.replay = iter.next().map(PathBuf::from),
+                "--headless" => args.headless = true,
+                _ => println!("Unknown argument {}", arg)
+            }
+        }
+        args
+    }
+}
+
 fn main() {
-    let scr_width: u32 = 1280;
-    let scr_height: u32 = 720;
-    let camera = Camera {
+    let args = Args::parse();
+    let replay = args.replay.as_ref().map(|path| Recording::load(path).unwrap_or_else(|err| panic!("{}", err)));
+    if args.headless {
+        let Some(recording) = &replay else {
+            panic!("--headless needs a recording to play, pass one with --replay <file>")
+        };
+        process::exit(if replay::check(recording) { 0 } else { 1 });
+    }
+
+    let mut config = Config::load_or_default(CONFIG_PATH);
+    // what's on disk, so newly added default bindings get written out
+    let mut saved_config = config.clone();
+    if let Some(recording) = &replay {
+        // the recording's world and controls, but this machine's video settings
+        config = Config { video: config.video.clone(), ..recording.config.clone() };
+    }
+    config.bindings.add_defaults(controls::DEFAULT_BINDINGS);
+    for (binding, actions) in config.bindings.conflicts() {
+        println!("{} is bound to more than one action: {}", binding, actions.join(", "));
+    }
+    let scr_width: u32 = config.video.width;
+    let scr_height: u32 = config.video.height;
+    let mut camera = Camera {
         position: Point3::<f32>::new(1.0, 257.0, 1.0),
         ..Default::default()
     };
-    let mut first_mouse = true;
-    let mut last_x: f32 = scr_width as f32 / 2.0;
-    let mut last_y: f32 = scr_height as f32 / 2.0;
+    config.apply_to_camera(&mut camera);
+    if let Some(recording) = &replay {
+        recording.start.apply_to(&mut camera);
+    }
+    let mut replay_frames = replay.map(|recording| recording.frames.into_iter());
     let mut delta_time: f32;
     let mut last_frame: f32 = 0.0;
 
-    let img = image::open(&Path::new("dirt.png")).unwrap().to_rgba8();
-    let img_data = img.to_vec();
-
-    let mut input_keybindings:  Vec<(Key, CameraMovement)> = vec![
-        (Key::W, CameraMovement::Forward),
-        (Key::A, CameraMovement::Left),
-        (Key::S, CameraMovement::Backward),
-        (Key::D, CameraMovement::Right),
-        (Key::Space, CameraMovement::Up),
-        (Key::LeftShift, CameraMovement::Down)
-
-    ];
-
-    let mut keybindings: Vec<KeyBinding> = vec![
-        KeyBinding::new(Key::Escape, false, set_window_should_close),
-        //KeyBinding::new(Key::W, true, camera_forward),
-        //KeyBinding::new(Key::A, true, camera_left),
-        //KeyBinding::new(Key::S, true, camera_backward),
-        //KeyBinding::new(Key::D, true, camera_right),
-        //KeyBinding::new(Key::Space, true, camera_up),
-        //KeyBinding::new(Key::LeftShift, true, camera_down),
-        KeyBinding::new(Key::RightShift, false, toggle_cursor_mode),
-        KeyBinding::new(Key::RightControl, false, print_camera_pos),
-        KeyBinding::new(Key::LeftControl, false, increase_movement_speed),
-        KeyBinding::new(Key::F11, false, toggle_fullscreen)
-    ];
+    let mut input = InputManager::new();
+    controls::setup_input(&mut input);
+    config.apply_to_input(&mut input);
 
     let mut window = Window::init(
-        1280,
-        720,
-        "test",
+        scr_width,
+        scr_height,
+        &config.video.title,
         WindowMode::Windowed,
         vec![
             WindowHint::ContextVersion(3, 3),
@@ -195,111 +329,343 @@ fn main() {
         ).unwrap();
 
     gl::load_with(|ptr| window.get_proc_address(ptr) as *const _);
+    let backend: Rc<dyn Backend> = Rc::new(GlBackend);
     
-    let world = World::new(0, 16, false, Player::new(camera));
+    let mut world = World::new(config.world.seed, config.world.size, config.world.tall, Player::new(camera), &backend);
+    world.time.speed = config.world.day_speed;
 
-    unsafe {
-        gl::Enable(gl::DEPTH_TEST);
-        //gl::Enable(gl::CULL_FACE);
-    }
+    backend.enable(gl::DEPTH_TEST);
+    //backend.enable(gl::CULL_FACE);
     window.make_current();
+    config.apply_to_window(&mut window);
     window.set_cursor_pos_polling(true);
     window.set_framebuffer_size_polling(true);
     window.set_scroll_polling(true);
+    window.set_mouse_button_polling(true);
+    window.set_key_polling(true);
+    window.set_char_polling(true);
+    window.set_focus_polling(true);
     window.set_cursor_mode(CursorMode::Disabled);
 
     // mut ebo: u32 = 0;
-    let mut texture: u32 = 0;
-
-    unsafe {
-        gl::GenTextures(1, &mut texture);
-        gl::BindTexture(gl::TEXTURE_2D, texture);
-
-        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::REPEAT as i32);
-        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::REPEAT as i32);
-
-        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::NEAREST as i32);
-        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::NEAREST as i32);
-
-        gl::TexImage2D(
-            gl::TEXTURE_2D,
-            0,
-            gl::RGBA as i32,
-            img.width() as i32,
-            img.height() as i32,
-            0,
-            gl::RGBA,
-            gl::UNSIGNED_BYTE,
-            &img_data[0] as *const u8 as *const std::ffi::c_void
-        );
-        gl::GenerateMipmap(gl::TEXTURE_2D)
-
-    }
+    let texture = Texture::from_path(&backend, "dirt.png", TextureSettings::pixelated()).unwrap_or_else(|err| panic!("{}", err));
+    let block_atlas = TextureAtlas::new(texture, (16, 16));
 
     //unsafe {
     //    gl::BindVertexArray(vao);
     //    gl::BindTexture(gl::TEXTURE_2D, texture);
     //}
 
-    let shader_program = Shader::new("triangle.vert", "triangle.frag");
+    let mut shader_program = Shader::new(&backend, "triangle.vert", "triangle.frag").unwrap_or_else(|err| panic!("{}", err));
+    if cfg!(debug_assertions) {
+        shader_program.watch();
+    }
+    shader_program.bind_uniform_block("Frame", FRAME_UNIFORMS_BINDING);
+    let frame_uniforms: UniformBuffer<FrameUniforms> = UniformBuffer::new(&backend, FRAME_UNIFORMS_BINDING);
+
+    let mut sky = Sky::new(&backend).unwrap_or_else(|err| panic!("{}", err));
+    if cfg!(debug_assertions) {
+        sky.shader.watch();
+    }
+
+    let mut clouds = Clouds::new(&backend, CloudSettings::default()).unwrap_or_else(|err| panic!("{}", err));
+    if cfg!(debug_assertions) {
+        clouds.shader.watch();
+    }
+
+    let mut block_outline = BlockOutline::new(&backend).unwrap_or_else(|err| panic!("{}", err));
+    if cfg!(debug_assertions) {
+        block_outline.shader.watch();
+    }
+
+    let mut particles = ParticleSystem::new(&backend, 4096).unwrap_or_else(|err| panic!("{}", err));
+
+    let font = Rc::new(Font::from_grid(&backend, "font.png", (6, 10), ' ').unwrap_or_else(|err| panic!("{}", err)));
+    let mut text = TextRenderer::new(&backend, font.clone()).unwrap_or_else(|err| panic!("{}", err));
+    let mut debug_overlay = DebugOverlay::new();
+    let mut debug_draw = DebugDraw::new(&backend).unwrap_or_else(|err| panic!("{}", err));
+    let mut debug_view = DebugView::new();
+    let mut renderer = Renderer::new(&backend);
+
+    let (width, height) = window.get_framebuffer_size();
+    let mut post = PostChain::new(&backend, (width as u32, height as u32)).unwrap_or_else(|err| panic!("{}", err));
+    for (effect, enabled) in [
+        (PostEffect::Tonemap { exposure: 1.0 }, false),
+        (PostEffect::Gamma { gamma: 2.2 }, false),
+        (PostEffect::ColourGrade { brightness: 0.0, contrast: 1.05, saturation: 1.1, tint: Vec3::repeat(1.0) }, true),
+        (PostEffect::Fxaa, true),
+        (PostEffect::Vignette { strength: 0.35, radius: 0.8, softness: 0.45 }, true),
+        // for when the camera is underwater
+        (PostEffect::Tint { colour: glm::vec4(0.3, 0.5, 1.0, 0.6) }, false)
+    ] {
+        post.push(effect).unwrap_or_else(|err| panic!("{}", err)).enabled = enabled;
+    }
+
+    // off while the post chain's framebuffers failed to resize
+    let mut post_enabled = true;
+
+    let mut shadows = ShadowMap::new(&backend, ShadowSettings::default()).unwrap_or_else(|err| panic!("{}", err));
+    shadows.enabled = config.video.shadows;
+
+    let mut ui = Ui::new(&backend, font).unwrap_or_else(|err| panic!("{}", err));
+    let mut paused = false;
+    let mut controls_menu = ControlsMenu::new();
+    // what's in effect, to notice changes made in the pause menu
+    let mut applied_config = config.clone();
 
     let mut game = Game::new(world);
+    let mut recording = args.record.as_ref().map(|_| Recording::new(&config, &game.world.player.camera));
 
     while !window.should_close() {
         let current_frame = window.context.get_time() as f32;
         delta_time = current_frame - last_frame;
         last_frame = current_frame;
 
-        process_input(&mut window, &mut delta_time, &mut keybindings, &mut game.world.player.camera);
-        window.process_events(&mut first_mouse, &mut last_x, &mut last_y, &mut game.world.player.camera);
-        if window.get_key(Key::Backslash) == Action::Press {
-            game.test_collision = false;
+        window.process_events();
+        ui.begin_frame(&mut window);
+        for event in window.events() {
+            renderer.handle_event(event);
+            match post.handle_event(event) {
+                Ok(()) => post_enabled |= matches!(event, Event::FramebufferResized { .. }),
+                Err(err) => {
+                    // the scene is drawn straight to the window until a resize succeeds
+                    println!("{}", err);
+                    post_enabled = false;
+                }
+            }
+        }
+        // escape in a text field just unfocuses it, and cancels a rebind in the controls menu
+        input.set_context(controls::MENU, paused);
+        input.set_context(controls::REBINDING, controls_menu.is_listening());
+        input.set_context(controls::TEXT_ENTRY, ui.has_keyboard_focus());
+        if let Some(frame) = replay_frames.as_mut().and_then(Iterator::next) {
+            delta_time = frame.delta_time;
+            input.replay(&frame);
         } else {
-            game.test_collision = true;
+            if replay_frames.take().is_some() {
+                println!("Replay finished");
+            }
+            input.update_from_window(&window, &config.bindings);
+            for event in input.gamepads().events() {
+                match event {
+                    GamepadEvent::Connected { name, .. } => println!("Gamepad connected: {}", name),
+                    GamepadEvent::Disconnected(id) => println!("Gamepad disconnected: {:?}", id)
+                }
+            }
+        }
+        if let Some(recording) = &mut recording {
+            recording.record(&input, delta_time);
         }
-        game.run_loop();
-        game.process_movement_input(&mut window, &delta_time, &mut input_keybindings);
-        
 
-        unsafe {
-            gl::ClearColor(0.5, 0.5, 0.5, 1.0);
-            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
-        };
+        if input.just_pressed(controls::TOGGLE_FULLSCREEN) {
+            window.toggle_fullscreen();
+        }
+        // remember fullscreen like any other setting
+        config.video.fullscreen = window.is_fullscreen;
+        applied_config.video.fullscreen = window.is_fullscreen;
 
+        if input.just_pressed(controls::PAUSE) {
+            if controls_menu.open {
+                controls_menu.close();
+            } else {
+                paused = !paused;
+            }
+        }
+        let break_attempt = game.tick(&input, &config.gamepad, delta_time, &backend);
+        debug_overlay.record_frame(delta_time);
+
+        if input.just_pressed(controls::TOGGLE_DEBUG_OVERLAY) {
+            debug_overlay.toggle();
+        }
+        debug_view.handle_keys(&input, &mut renderer);
+
+        if let Some(attempt) = break_attempt {
+            let camera = &game.world.player.camera;
+            debug_view.queue_raycast(&mut debug_draw, &camera.position.coords, &camera.front, REACH, attempt.hit.as_ref());
+            if let (Some(hit), Some(block)) = (attempt.hit, attempt.broken) {
+                let centre = glm::convert::<IVec3, Vec3>(hit.block).add_scalar(0.5);
+                particles.burst(centre, 24, &break_particles(block, &block_atlas));
+            }
+        }
+        let target = if paused { None } else { game.target_block() };
+        particles.update(delta_time, |position| game.world.is_solid(position));
+        debug_draw.update(delta_time);
+        debug_view.queue(&mut debug_draw, &game);
+        shader_program.reload_if_changed();
+        sky.shader.reload_if_changed();
+        clouds.shader.reload_if_changed();
+        block_outline.shader.reload_if_changed();
+
+        let lighting = game.world.time.lighting();
         let (width, height) = window.get_framebuffer_size();
 
-        let model = glm::Mat4::from_axis_angle(&Unit::new_normalize(vec3(0.0, 1.0, 0.0)), 90.0);
-        let projection = glm::perspective_fov(1.0, width as f32, height as f32, 0.1, 100.0);
+        let projection = glm::perspective_fov(config.camera.fov_radians(), width as f32, height as f32, config.camera.near_plane, config.camera.far_plane);
         let view = game.world.player.camera.get_view_matrix();
 
-        unsafe {
-            shader_program.set_mat4("model", &model);
-            shader_program.set_mat4("view", &view);
-            shader_program.set_mat4("projection", &projection);
-            shader_program.set_vec4("ourColor", 1.0, 1.0, 1.0, 0.0);
+        shadows.update(&view, config.camera.fov_radians(), width as f32 / height.max(1) as f32, config.camera.near_plane, &lighting.light_direction);
+        shadows.render(|shader, cascade| {
+            for chunk in game.world.chunks.iter() {
+                if cascade.contains_sphere(&chunk.centre(), CHUNK_BOUNDING_RADIUS) {
+                    chunk.draw_depth(shader);
+                }
+            }
+        });
+
+        if post_enabled {
+            post.begin();
+        } else {
+            // the shadow pass leaves its own viewport set
+            Framebuffer::bind_default(&backend, (width as u32, height as u32));
+        }
+        renderer.clear(lighting.clear_colour());
+        let camera_position = game.world.player.camera.position;
+        let camera_position = vec3(camera_position.x, camera_position.y, camera_position.z);
+        clouds.update(delta_time, &camera_position);
+
+        frame_uniforms.update(&FrameUniforms {
+            view,
+            projection,
+            camera_position,
+            time: current_frame,
+            fog_colour: lighting.fog_colour(),
+            // terrain fades out just before the far plane clips it
+            fog_start: config.camera.far_plane * 0.6,
+            fog_end: config.camera.far_plane,
+            light_direction: lighting.light_direction,
+            ambient_light: lighting.ambient_light,
+            light_colour: lighting.light_colour,
+            view_mode: renderer.view_mode.id()
+        });
+
+        if renderer.shows_environment() {
+            sky.draw(&lighting);
         }
 
-        unsafe {
-            //shader_program.use_program();
-            //gl::DrawArrays(gl::TRIANGLES, 0, (test_vertices.len() / 3) as i32);
+        shader_program.set_vec4("ourColor", 1.0, 1.0, 1.0, 0.0);
+        shadows.bind(&shader_program, 1);
 
-            //chunk.draw(&texture, &shader_program)
+        //shader_program.use_program();
+        //backend.draw_arrays(gl::TRIANGLES, 0, (test_vertices.len() / 3) as i32);
 
+        //chunk.draw(&texture, &shader_program)
+
+        renderer.world_pass(|| {
             for chunk in game.world.chunks.iter() {
-                chunk.draw(&texture, &shader_program)
+                chunk.draw(&block_atlas.texture, &shader_program)
+            }
+        });
+        if let Some(target) = &target {
+            block_outline.draw(target.block);
+        }
+        debug_draw.draw();
+        if renderer.shows_environment() {
+            particles.draw(&block_atlas.texture);
+            if config.video.clouds {
+                clouds.draw();
             }
         }
+        if post_enabled {
+            post.finish();
+        }
+
+        debug_overlay.queue(&mut text, &game.world, renderer.view_mode);
+        text.draw((width, height));
+
+        if !paused {
+            hud::draw_crosshair(&mut ui);
+        }
+        if paused && controls_menu.open {
+            controls_menu.draw(&mut ui, window.events(), input.gamepads(), &mut config.bindings);
+        } else if paused {
+            match pause_menu(&mut ui, &mut game, &mut post, &mut config) {
+                Some(PauseAction::Resume) => paused = false,
+                Some(PauseAction::Controls) => controls_menu.open = true,
+                Some(PauseAction::Quit) => window.set_should_close(true),
+                None => {}
+            }
+        }
+        ui.end_frame(&mut window);
+
+        if config != applied_config {
+            apply_config(&config, &mut window, &mut input, &mut game, &mut shadows);
+            applied_config = config.clone();
+        }
+        // saved once the menu closes rather than on every slider step, replays use the recording's settings
+        if !paused && args.replay.is_none() && config != saved_config {
+            config.save(CONFIG_PATH).unwrap_or_else(|err| println!("{}", err));
+            saved_config = config.clone();
+        }
 
         window.swap_buffers();
         window.poll_events();
     };
+    if args.replay.is_none() && config != saved_config {
+        config.save(CONFIG_PATH).unwrap_or_else(|err| println!("{}", err));
+    }
+    if let (Some(path), Some(recording)) = (&args.record, &mut recording) {
+        recording.finish(&game.world.player.camera);
+        match recording.save(path) {
+            Ok(()) => println!("Recorded {} frames to {}", recording.frames.len(), path.display()),
+            Err(err) => println!("{}", err)
+        }
+    }
     println!("Hello world!");
 }
 
-fn process_input(window: &mut Window, delta_time: &f32, bindings: &mut [KeyBinding], camera: &mut Camera) {
-    for binding in bindings.iter_mut() {
-        let action = window.get_key(binding.key);
-        binding.update(action, InputFunctionArguments::new().camera(camera).window(window).delta_time(delta_time).action(action.clone()))
-    }
+/// Applies settings that can change while running, the world options need a new world
+fn apply_config(config: &Config, window: &mut Window, input: &mut InputManager, game: &mut Game, shadows: &mut ShadowMap) {
+    config.apply_to_window(window);
+    config.apply_to_input(input);
+    config.apply_to_camera(&mut game.world.player.camera);
+    game.world.time.speed = config.world.day_speed;
+    shadows.enabled = config.video.shadows;
+}
+
+enum PauseAction {
+    Resume,
+    Controls,
+    Quit
+}
+
+fn pause_menu(ui: &mut Ui, game: &mut Game, post: &mut PostChain, config: &mut Config) -> Option<PauseAction> {
+    let mut action = None;
+    let rect = Rect::centred_in(ui.screen_rect(), glm::vec2(320.0, 676.0));
+    ui.panel("pause", rect, |ui| {
+        ui.label("Paused");
+        ui.text_field("Name", &mut game.world.player.username);
+        ui.checkbox("Fullscreen", &mut config.video.fullscreen);
+        ui.checkbox("VSync", &mut config.video.vsync);
+        ui.checkbox("Clouds", &mut config.video.clouds);
+        ui.checkbox("Shadows", &mut config.video.shadows);
+        ui.slider("FOV", &mut config.camera.fov, 30.0..=110.0);
+        ui.slider("Sensitivity", &mut config.controls.mouse_sensitivity, 0.01..=0.5);
+        ui.slider("Day speed", &mut config.world.day_speed, 0.0..=20.0);
+        ui.label("Post processing");
+        for pass in post.passes_mut() {
+            ui.checkbox(pass.effect.name(), &mut pass.enabled);
+        }
+        if ui.button("Resume") {
+            action = Some(PauseAction::Resume);
+        }
+        if ui.button("Controls") {
+            action = Some(PauseAction::Controls);
+        }
+        if ui.button("Quit") {
+            action = Some(PauseAction::Quit);
+        }
+    });
+    action
 }
 
+/// Debris thrown out of a broken block, showing bits of its atlas tile.
+/// Every block shares tile 0 until chunks are textured from the atlas, see `Block::atlas_tile`.
+fn break_particles(block: Block, atlas: &TextureAtlas) -> ParticleSettings {
+    ParticleSettings {
+        velocity: vec3(0.0, 2.0, 0.0),
+        velocity_spread: vec3(2.0, 1.5, 2.0),
+        position_spread: Vec3::repeat(0.3),
+        uv: atlas.uv_rect(block.atlas_tile()),
+        uv_fraction: 0.25,
+        ..Default::default()
+    }
+}
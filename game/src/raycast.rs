@@ -0,0 +1,115 @@
+use engine::glm::{IVec3, Vec3};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RaycastHit {
+    /// The solid block that was hit
+    pub block: IVec3,
+    /// Normal of the face the ray entered through, zero if it started inside the block
+    pub normal: IVec3,
+    pub distance: f32
+}
+
+/// Steps through the voxel grid along a ray and returns the first block `is_solid` accepts.
+/// `direction` doesn't need to be normalised, `max_distance` is in blocks.
+pub fn raycast(origin: &Vec3, direction: &Vec3, max_distance: f32, is_solid: impl Fn(IVec3) -> bool) -> Option<RaycastHit> {
+    let direction = direction.try_normalize(f32::EPSILON)?;
+    let mut block = IVec3::new(origin.x.floor() as i32, origin.y.floor() as i32, origin.z.floor() as i32);
+    let mut normal = IVec3::zeros();
+
+    let mut step = IVec3::zeros();
+    // distance along the ray to the next boundary on each axis, and between boundaries
+    let mut next = Vec3::repeat(f32::INFINITY);
+    let mut delta = Vec3::repeat(f32::INFINITY);
+    for axis in 0..3 {
+        if direction[axis] > 0.0 {
+            step[axis] = 1;
+            delta[axis] = 1.0 / direction[axis];
+            next[axis] = (block[axis] as f32 + 1.0 - origin[axis]) * delta[axis];
+        } else if direction[axis] < 0.0 {
+            step[axis] = -1;
+            delta[axis] = -1.0 / direction[axis];
+            next[axis] = (origin[axis] - block[axis] as f32) * delta[axis];
+        }
+    }
+
+    let mut distance = 0.0;
+    while distance <= max_distance {
+        if is_solid(block) {
+            return Some(RaycastHit { block, normal, distance })
+        }
+        let axis = next.imin();
+        distance = next[axis];
+        next[axis] += delta[axis];
+        block[axis] += step[axis];
+        normal = IVec3::zeros();
+        normal[axis] = -step[axis];
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use engine::{backend::{Backend, NullBackend}, camera::Camera};
+
+    use super::*;
+    use crate::{player::Player, World};
+
+    /// One column of chunks at negative x and z, the ground at x -13 z -6 is at y 181
+    /// with a step up to 182 at z -7
+    fn world() -> World {
+        let backend: Rc<dyn Backend> = Rc::new(NullBackend::new());
+        World::new(0, 1, true, Player::new(Camera::default()), &backend)
+    }
+
+    #[test]
+    fn axis_aligned_rays_hit_the_face_they_enter() {
+        let world = world();
+        let is_solid = |position| world.is_solid(position);
+
+        let down = raycast(&Vec3::new(-12.5, 190.5, -5.5), &Vec3::new(0.0, -1.0, 0.0), 10.0, is_solid).unwrap();
+        assert_eq!(down, RaycastHit { block: IVec3::new(-13, 181, -6), normal: IVec3::new(0, 1, 0), distance: 8.5 });
+
+        let north = raycast(&Vec3::new(-12.5, 182.5, -5.5), &Vec3::new(0.0, 0.0, -1.0), 10.0, is_solid).unwrap();
+        assert_eq!(north, RaycastHit { block: IVec3::new(-13, 182, -7), normal: IVec3::new(0, 0, 1), distance: 0.5 });
+    }
+
+    #[test]
+    fn max_distance_cuts_the_ray_off() {
+        let world = world();
+        let origin = Vec3::new(-12.5, 190.5, -5.5);
+        let down = Vec3::new(0.0, -1.0, 0.0);
+        assert_eq!(raycast(&origin, &down, 8.4, |position| world.is_solid(position)), None);
+        assert!(raycast(&origin, &down, 8.5, |position| world.is_solid(position)).is_some());
+        assert_eq!(raycast(&origin, &Vec3::zeros(), 100.0, |position| world.is_solid(position)), None);
+    }
+
+    #[test]
+    fn diagonal_rays_through_negative_coordinates_match_small_steps() {
+        let world = world();
+        let origin = Vec3::new(-2.3, 190.7, -1.6);
+        let direction = Vec3::new(-0.6, -0.7, -0.4).normalize();
+        let hit = raycast(&origin, &direction, 40.0, |position| world.is_solid(position)).unwrap();
+
+        let stepped = (0..40_000).map(|i| origin + direction * (i as f32 * 0.001))
+            .map(|point| IVec3::new(point.x.floor() as i32, point.y.floor() as i32, point.z.floor() as i32))
+            .find(|block| world.is_solid(*block))
+            .unwrap();
+        assert_eq!(hit.block, stepped);
+        assert!(hit.block.x < 0 && hit.block.z < 0);
+        // the entry point is on the face the normal points out of
+        let entry = origin + direction * hit.distance;
+        let axis = hit.normal.iamax();
+        let face = hit.block[axis] as f32 + if hit.normal[axis] > 0 { 1.0 } else { 0.0 };
+        assert!((entry[axis] - face).abs() < 1e-3);
+        assert_eq!(hit.normal.abs().sum(), 1);
+    }
+
+    #[test]
+    fn starting_inside_a_block_hits_it_with_no_normal() {
+        let world = world();
+        let hit = raycast(&Vec3::new(-12.5, 170.5, -5.5), &Vec3::new(1.0, 0.0, 0.0), 5.0, |position| world.is_solid(position)).unwrap();
+        assert_eq!(hit, RaycastHit { block: IVec3::new(-13, 170, -6), normal: IVec3::zeros(), distance: 0.0 });
+    }
+}
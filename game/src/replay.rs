@@ -0,0 +1,65 @@
+use std::rc::Rc;
+
+use engine::{
+    backend::{Backend, NullBackend},
+    camera::Camera,
+    input::InputManager,
+    replay::{CameraState, Recording},
+};
+
+use crate::{controls, player::Player, Game, World};
+
+/// How far the replayed camera can be from where the recording ended, in blocks and degrees
+const END_TOLERANCE: f32 = 0.001;
+
+/// Plays a recording back through `Game::tick` without a window or GPU
+pub fn run_headless(recording: &Recording) -> Game {
+    let backend: Rc<dyn Backend> = Rc::new(NullBackend::new());
+    let config = &recording.config;
+    let mut camera = Camera::default();
+    config.apply_to_camera(&mut camera);
+    recording.start.apply_to(&mut camera);
+    let mut world = World::new(config.world.seed, config.world.size, config.world.tall, Player::new(camera), &backend);
+    world.time.speed = config.world.day_speed;
+
+    let mut game = Game::new(world);
+    let mut input = InputManager::new();
+    controls::setup_input(&mut input);
+    for frame in &recording.frames {
+        input.replay(frame);
+        game.tick(&input, &config.gamepad, frame.delta_time, &backend);
+    }
+    game
+}
+
+/// Replays headlessly and reports where the camera ended up, false if that isn't where the
+/// recording ended, so a recorded bug can be kept as a regression test
+pub fn check(recording: &Recording) -> bool {
+    let game = run_headless(recording);
+    let end = CameraState::from_camera(&game.world.player.camera);
+    println!("Replayed {} frames over {:.1} seconds, ended at {:?}", recording.frames.len(), recording.duration(), end);
+    match recording.end {
+        Some(expected) if !end.matches(&expected, END_TOLERANCE) => {
+            println!("Recording ended at {:?}", expected);
+            false
+        },
+        _ => true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn walking_into_a_wall_stops_at_it() {
+        let recording = Recording::load(concat!(env!("CARGO_MANIFEST_DIR"), "/replays/walk_into_wall.toml")).unwrap();
+        let end = CameraState::from_camera(&run_headless(&recording).world.player.camera);
+        let expected = recording.end.unwrap();
+        for axis in 0..3 {
+            assert!((end.position[axis] - expected.position[axis]).abs() <= END_TOLERANCE, "ended at {:?}, recorded {:?}", end, expected);
+        }
+        // moved, but stopped before z - 0.15, where collisions are tested, reached the block at z -7
+        assert!(end.position[2] < recording.start.position[2] && end.position[2] > -5.85);
+    }
+}
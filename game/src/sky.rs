@@ -0,0 +1,197 @@
+use std::{f32::consts::TAU, rc::Rc};
+
+use engine::{
+    backend::Backend,
+    glm::{self, Vec3, Vec4},
+    shader::{Shader, ShaderError, FRAME_UNIFORMS_BINDING},
+    vertex_array::VertexArray,
+};
+
+/// Time of day in the world, advanced every tick.
+/// 0.0 is midnight, 0.25 sunrise, 0.5 noon and 0.75 sunset.
+#[derive(Debug, Clone, Copy)]
+pub struct WorldTime {
+    /// Seconds into the current day
+    pub time: f32,
+    /// Length of a full day in seconds
+    pub day_length: f32,
+    /// Days passed since the world started
+    pub day: u32,
+    /// Multiplier on the tick delta, 0.0 stops time
+    pub speed: f32
+}
+
+impl Default for WorldTime {
+    fn default() -> Self {
+        Self {
+            time: 180.0,
+            day_length: 600.0,
+            day: 0,
+            speed: 1.0
+        }
+    }
+}
+
+impl WorldTime {
+    pub fn tick(&mut self, delta_time: f32) {
+        self.time += delta_time * self.speed;
+        while self.time >= self.day_length {
+            self.time -= self.day_length;
+            self.day += 1;
+        }
+    }
+
+    /// Fraction of the day passed, 0.0 to 1.0
+    pub fn time_of_day(&self) -> f32 {
+        self.time / self.day_length
+    }
+
+    pub fn set_time_of_day(&mut self, time_of_day: f32) {
+        self.time = time_of_day.rem_euclid(1.0) * self.day_length;
+    }
+
+    /// Unit vector towards the sun, rising in +x and setting in -x
+    pub fn sun_direction(&self) -> Vec3 {
+        let angle = (self.time_of_day() - 0.25) * TAU;
+        glm::normalize(&Vec3::new(angle.cos(), angle.sin(), 0.25))
+    }
+
+    pub fn lighting(&self) -> SkyLighting {
+        SkyLighting::from_sun(self.sun_direction())
+    }
+}
+
+/// Colours and light derived from the position of the sun
+#[derive(Debug, Clone, Copy)]
+pub struct SkyLighting {
+    pub sun_direction: Vec3,
+    /// Direction of the light lighting terrain, the sun by day and the moon by night
+    pub light_direction: Vec3,
+    pub light_colour: Vec4,
+    pub ambient_light: f32,
+    pub zenith_colour: Vec4,
+    pub horizon_colour: Vec4,
+    pub star_visibility: f32
+}
+
+impl SkyLighting {
+    pub fn from_sun(sun_direction: Vec3) -> Self {
+        let day_zenith = Vec3::new(0.30, 0.52, 0.90);
+        let day_horizon = Vec3::new(0.70, 0.80, 0.95);
+        let night_zenith = Vec3::new(0.01, 0.01, 0.04);
+        let night_horizon = Vec3::new(0.04, 0.05, 0.10);
+        let sunset_horizon = Vec3::new(0.95, 0.50, 0.25);
+
+        let daylight = glm::smoothstep(-0.15, 0.2, sun_direction.y);
+        // strongest while the sun is close to the horizon
+        let sunset = 1.0 - glm::smoothstep(0.0, 0.3, sun_direction.y.abs());
+
+        let zenith = glm::mix(&night_zenith, &day_zenith, daylight);
+        let horizon = glm::mix(&glm::mix(&night_horizon, &day_horizon, daylight), &sunset_horizon, sunset * 0.6);
+
+        let (light_direction, light_colour) = if sun_direction.y >= 0.0 {
+            let colour = glm::mix(&Vec3::new(1.0, 0.95, 0.85), &Vec3::new(1.0, 0.6, 0.35), sunset);
+            (sun_direction, colour * daylight)
+        } else {
+            (-sun_direction, Vec3::new(0.15, 0.17, 0.25) * (1.0 - daylight))
+        };
+
+        Self {
+            sun_direction,
+            light_direction,
+            light_colour: light_colour.push(1.0),
+            ambient_light: 0.1 + 0.3 * daylight,
+            zenith_colour: zenith.push(1.0),
+            horizon_colour: horizon.push(1.0),
+            star_visibility: 1.0 - glm::smoothstep(-0.2, 0.05, sun_direction.y)
+        }
+    }
+
+    /// Fog fades into the horizon so distant terrain blends with the sky
+    pub fn fog_colour(&self) -> Vec4 {
+        self.horizon_colour
+    }
+
+    pub fn clear_colour(&self) -> Vec4 {
+        self.horizon_colour
+    }
+}
+
+/// Draws the sky dome, sun, moon and stars behind everything else
+pub struct Sky {
+    pub shader: Shader,
+    // empty, core profile needs one bound to draw
+    vertex_array: VertexArray,
+    backend: Rc<dyn Backend>
+}
+
+impl Sky {
+    pub fn new(backend: &Rc<dyn Backend>) -> Result<Self, ShaderError> {
+        let mut shader = Shader::new(backend, "sky.vert", "sky.frag")?;
+        shader.bind_uniform_block("Frame", FRAME_UNIFORMS_BINDING);
+        Ok(Self {
+            shader,
+            vertex_array: VertexArray::new(backend),
+            backend: backend.clone()
+        })
+    }
+
+    /// Draws without depth testing, call first thing after clearing
+    pub fn draw(&self, lighting: &SkyLighting) {
+        self.backend.disable(gl::DEPTH_TEST);
+        self.shader.use_program();
+        self.shader.set_vector3("sunDirection", &lighting.sun_direction);
+        let (zenith, horizon) = (lighting.zenith_colour, lighting.horizon_colour);
+        self.shader.set_vec4("zenithColour", zenith.x, zenith.y, zenith.z, zenith.w);
+        self.shader.set_vec4("horizonColour", horizon.x, horizon.y, horizon.z, horizon.w);
+        self.shader.set_float("starVisibility", lighting.star_visibility);
+        self.vertex_array.bind();
+        self.backend.draw_arrays(gl::TRIANGLES, 0, 3);
+        self.vertex_array.unbind();
+        self.backend.enable(gl::DEPTH_TEST);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tick_wraps_into_the_next_days() {
+        let mut time = WorldTime { time: 590.0, day_length: 600.0, day: 0, speed: 2.0 };
+        time.tick(10.0);
+        assert_eq!((time.time, time.day), (10.0, 1));
+        time.tick(600.0);
+        assert_eq!((time.time, time.day), (10.0, 3));
+
+        time.speed = 0.0;
+        time.tick(100.0);
+        assert_eq!((time.time, time.day), (10.0, 3));
+    }
+
+    #[test]
+    fn set_time_of_day_wraps() {
+        let mut time = WorldTime::default();
+        time.set_time_of_day(1.25);
+        assert_eq!(time.time_of_day(), 0.25);
+        time.set_time_of_day(-0.25);
+        assert_eq!(time.time_of_day(), 0.75);
+    }
+
+    #[test]
+    fn sun_rises_in_x_peaks_at_noon_and_sets_in_minus_x() {
+        let mut time = WorldTime::default();
+        let mut sun_at = |time_of_day| {
+            time.set_time_of_day(time_of_day);
+            time.sun_direction()
+        };
+        let sunrise = sun_at(0.25);
+        assert!(sunrise.y.abs() < 1e-6 && sunrise.x > 0.9);
+        let noon = sun_at(0.5);
+        assert!(noon.y > 0.9 && noon.x.abs() < 1e-6);
+        let sunset = sun_at(0.75);
+        assert!(sunset.y.abs() < 1e-6 && sunset.x < -0.9);
+        assert!(sun_at(0.0).y < -0.9);
+        assert!((glm::length(&noon) - 1.0).abs() < 1e-6);
+    }
+}
@@ -1,12 +1,28 @@
 use std::collections::HashSet;
+use std::rc::Rc;
 
 // 2d heightmap for height
 use block_mesh::ndshape::{RuntimeShape, Shape, ConstShape3u32, ConstShape};
-use engine::glm::IVec3;
-use crate::{chunk::Chunk, player::Player};
+use engine::{backend::Backend, glm::IVec3};
+use crate::{block::Block, chunk::{Chunk, ChunkSize}, player::Player, sky::WorldTime};
 use noise::{Perlin, Fbm, Seedable, MultiFractal};
 use crate::glm::vec3;
 
+/// Chunk counts and memory use for the debug overlay
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ChunkStats {
+    pub loaded: usize,
+    /// Chunks with a mesh to draw
+    pub visible: usize,
+    /// Loaded chunks that are skipped when drawing
+    pub culled: usize,
+    /// Chunks waiting to be generated, always 0 while generation happens up front in `World::new`
+    pub pending_generation: usize,
+    /// Chunks whose blocks changed since they were last meshed
+    pub pending_meshes: usize,
+    pub mesh_bytes: usize
+}
+
 #[derive(Debug, Default)]
 pub struct World {
     pub current_chunk: IVec3,
@@ -16,13 +32,14 @@ pub struct World {
     pub chunk_positions: HashSet<IVec3>,
     pub chunks: Vec<Chunk>,
     pub player: Player,
+    pub time: WorldTime,
     noise: Fbm<Perlin>,
     pub min: (i32, i32, i32),
     total_size: (u32, u32, u32)
 }
 
 impl World {
-    pub fn new(seed: u32, cube_size: u32, tall: bool, player: Player) -> Self {
+    pub fn new(seed: u32, cube_size: u32, tall: bool, player: Player, backend: &Rc<dyn Backend>) -> Self {
         let min_val = -((cube_size % 2) as i32);
         let shape;
         let min;
@@ -56,13 +73,14 @@ impl World {
             chunk_positions,
             min,
             total_size,
-            player
+            player,
+            time: WorldTime::default()
         };
         for k in 0..shape.usize() {
             output.calculate_visibility(output.chunks[k].position);
         }
         for j in 0..shape.usize() {
-            output.chunks[j].create_mesh();
+            output.chunks[j].create_mesh(backend);
         }
         output
     }
@@ -172,6 +190,75 @@ impl World {
         self.chunks[chunk_index] = new_chunk;
     }
 
+    /// Chunk containing a block and the block's position within it
+    fn chunk_and_local(position: IVec3) -> (IVec3, IVec3) {
+        let chunk = IVec3::new(position.x.div_euclid(16), position.y.div_euclid(16), position.z.div_euclid(16));
+        (chunk, position - chunk * 16)
+    }
+
+    /// Block at a world position, `None` outside loaded chunks
+    pub fn block_at(&self, position: IVec3) -> Option<Block> {
+        let (chunk_position, local) = Self::chunk_and_local(position);
+        if !self.chunk_positions.contains(&chunk_position) {
+            return None
+        }
+        let chunk = &self.chunks[self.calc_chunk_index(chunk_position)];
+        let index = <ChunkSize as ConstShape<3>>::linearize([local.x as u32 + 1, local.y as u32 + 1, local.z as u32 + 1]) as usize;
+        Some(chunk.blocks[index])
+    }
+
+    pub fn is_solid(&self, position: IVec3) -> bool {
+        self.block_at(position).is_some_and(|block| block != Block::AIR)
+    }
+
+    /// Replaces a block with air and remeshes every chunk that borders it.
+    /// Returns the block that was broken.
+    pub fn break_block(&mut self, position: IVec3, backend: &Rc<dyn Backend>) -> Option<Block> {
+        let block = self.block_at(position).filter(|block| *block != Block::AIR)?;
+        let (chunk_position, _) = Self::chunk_and_local(position);
+        for x in -1..=1 {
+            for y in -1..=1 {
+                for z in -1..=1 {
+                    let neighbour = chunk_position + IVec3::new(x, y, z);
+                    if !self.chunk_positions.contains(&neighbour) {
+                        continue
+                    }
+                    // chunks keep a copy of the blocks one past their edges
+                    let local = position - neighbour * 16;
+                    if !local.iter().all(|coord| (-1..=16).contains(coord)) {
+                        continue
+                    }
+                    let index = self.calc_chunk_index(neighbour);
+                    let chunk = &mut self.chunks[index];
+                    chunk.update_block(local.add_scalar(1), Block::AIR);
+                    if local.iter().all(|coord| (0..16).contains(coord)) {
+                        chunk.filled_blocks.remove(&(local.x as u32, local.y as u32, local.z as u32));
+                    }
+                    chunk.create_mesh(backend);
+                }
+            }
+        }
+        Some(block)
+    }
+
+    pub fn chunk_stats(&self) -> ChunkStats {
+        let mut stats = ChunkStats {
+            loaded: self.chunks.len(),
+            ..Default::default()
+        };
+        for chunk in &self.chunks {
+            if chunk.is_visible() {
+                stats.visible += 1;
+            }
+            if chunk.has_changed {
+                stats.pending_meshes += 1;
+            }
+            stats.mesh_bytes += chunk.mesh_size_bytes();
+        }
+        stats.culled = stats.loaded - stats.visible;
+        stats
+    }
+
     pub fn calc_chunk_index(&self, pos: IVec3) -> usize {
         let shape = RuntimeShape::<u32, 3>::new([self.total_size.0, self.total_size.1, self.total_size.2]);
         let normal_pos = [(pos.x - self.min.0) as u32, pos.y as u32, (pos.z - self.min.2) as u32]; 
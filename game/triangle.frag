@@ -1,19 +1,59 @@
 #version 330 core
+#include "engine/frame.glsl"
+#include "shadows.glsl"
 
 out vec4 Color;
 
 in vec2 TexCoord;
+in vec3 WorldPos;
+in vec3 Normal;
+flat in vec3 ChunkOrigin;
 
 uniform vec4 ourColor;
 uniform sampler2D ourTexture;
 
-//float fog_maxdist = 8.0;
-//float fog_mindist = 0.1;
-//vec4  fog_colour = vec4(0.4, 0.4, 0.4, 1.0);
-//
-//float dist = length()
+// stable pseudo random colour for a position, kept away from black
+vec3 hashColour(vec3 position)
+{
+    vec3 hash = fract(sin(vec3(
+        dot(position, vec3(127.1, 311.7, 74.7)),
+        dot(position, vec3(269.5, 183.3, 246.1)),
+        dot(position, vec3(113.5, 271.9, 124.6))
+    )) * 43758.5453);
+    return 0.25 + hash * 0.75;
+}
 
 void main()
 {
-    Color = texture(ourTexture, TexCoord);
-}
\ No newline at end of file
+    vec3 normal = normalize(Normal);
+    if (viewMode == VIEW_NORMALS) {
+        Color = vec4(normal * 0.5 + 0.5, 1.0);
+        return;
+    }
+    if (viewMode == VIEW_UV) {
+        // greedy quads repeat the texture once per block, the checkerboard shows each repeat
+        float checker = mod(floor(TexCoord.x) + floor(TexCoord.y), 2.0);
+        Color = vec4(fract(TexCoord), checker * 0.5, 1.0);
+        return;
+    }
+    if (viewMode == VIEW_CHUNK_COLOUR) {
+        // fixed light so faces stay distinguishable at night
+        float shade = 0.6 + 0.4 * max(dot(normal, normalize(vec3(0.4, 1.0, 0.3))), 0.0);
+        Color = vec4(hashColour(ChunkOrigin) * shade, 1.0);
+        return;
+    }
+    if (viewMode == VIEW_OVERDRAW) {
+        // blended additively, so about 12 layers reach white
+        Color = vec4(0.08, 0.05, 0.03, 1.0);
+        return;
+    }
+
+    float dist = length(WorldPos - cameraPosition);
+    float fog = clamp((dist - fogStart) / (fogEnd - fogStart), 0.0, 1.0);
+    float diffuse = max(dot(normal, lightDirection), 0.0);
+    // faces turned away from the light are already dark, skip the lookups
+    float shadow = diffuse > 0.0 ? shadowFactor(WorldPos, normal) : 1.0;
+    vec4 albedo = texture(ourTexture, TexCoord);
+    vec3 lit = albedo.rgb * (ambientLight + diffuse * shadow * lightColour.rgb);
+    Color = mix(vec4(lit, albedo.a), fogColour, fog);
+}
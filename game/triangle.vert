@@ -1,18 +1,25 @@
 #version 330 core
+#include "engine/frame.glsl"
 
 layout (location = 0) in vec3 Position;
 layout (location = 1) in vec3 aNormalCoord;
 layout (location = 2) in vec2 aTexCoord;
 
 out vec2 TexCoord;
+out vec3 WorldPos;
+out vec3 Normal;
+// same for every vertex of a chunk, for colouring chunks apart
+flat out vec3 ChunkOrigin;
 //out 
 
-uniform mat4 view;
-uniform mat4 projection;
 uniform mat4 model;
 
 void main()
 {
-    gl_Position = projection * view * model * vec4(Position, 1.0);
+    vec4 worldPos = model * vec4(Position, 1.0);
+    gl_Position = projection * view * worldPos;
     TexCoord = aTexCoord;
+    WorldPos = worldPos.xyz;
+    Normal = aNormalCoord;
+    ChunkOrigin = model[3].xyz;
 }